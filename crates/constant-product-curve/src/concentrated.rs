@@ -0,0 +1,321 @@
+use crate::{
+    math::{div_rem_wide, full_mul, mul_div, mul_div_ceil},
+    CurveError, SwapDirection,
+};
+
+// Square root prices are Q64.64 fixed point
+pub const Q64: u128 = 1 << 64;
+
+// Price range of a concentrated liquidity pool, P = 1.0001ᵗ
+pub const MIN_TICK: i32 = -443_636;
+pub const MAX_TICK: i32 = 443_636;
+
+// √P at MIN_TICK and MAX_TICK
+pub const MIN_SQRT_PRICE: u128 = 4_295_048_017;
+pub const MAX_SQRT_PRICE: u128 = 79_226_673_515_401_279_992_447_579_061;
+
+// ⌊2¹²⁸ / √1.0001^(2ⁱ)⌋ for each bit i of a tick
+const TICK_FACTORS: [u128; 19] = [
+    0xfffcb933bd6fad37aa2d162d1a594001,
+    0xfff97272373d413259a46990580e2139,
+    0xfff2e50f5f656932ef12357cf3c7fdcb,
+    0xffe5caca7e10e4e61c3624eaa0941ccf,
+    0xffcb9843d60f6159c9db58835c926643,
+    0xff973b41fa98c081472e6896dfb254bf,
+    0xff2ea16466c96a3843ec78b326b52860,
+    0xfe5dee046a99a2a811c461f1969c3052,
+    0xfcbe86c7900a88aedcffc83b479aa3a3,
+    0xf987a7253ac413176f2b074cf7815e53,
+    0xf3392b0822b70005940c7a398e4b70f2,
+    0xe7159475a2c29b7443b29c7fa6e889d8,
+    0xd097f3bdfd2022b8845ad8f792aa5825,
+    0xa9f746462d870fdf8a65dc1f90e061e4,
+    0x70d869a156d2a1b890bb3df62baf32f6,
+    0x31be135f97d08fd981231505542fcfa5,
+    0x09aa508b5b7a84e1c677de54f3e99bc8,
+    0x005d6af8dedb81196699c329225ee604,
+    0x00002216e584f5fa1ea926041bedfe97,
+];
+
+// Get √P for a tick
+// √P = √1.0001ᵗ, built from the binary decomposition of |t| in Q128 and inverted for t > 0
+pub fn sqrt_price_from_tick(tick: i32) -> Result<u128, CurveError> {
+    if !(MIN_TICK..=MAX_TICK).contains(&tick) {
+        return Err(CurveError::Overflow);
+    }
+    let abs_tick = tick.unsigned_abs();
+    let mut ratio = u128::MAX;
+    for (i, factor) in TICK_FACTORS.iter().enumerate() {
+        if abs_tick & (1 << i) != 0 {
+            ratio = full_mul(ratio, *factor).0;
+        }
+    }
+    if tick > 0 {
+        // 2¹⁹² / ratio
+        Ok(div_rem_wide(1 << 64, 0, ratio).ok_or(CurveError::Overflow)?.0)
+    } else {
+        // Shift Q128 down to Q64.64, rounding up
+        Ok((ratio >> 64) + (ratio & u64::MAX as u128 != 0) as u128)
+    }
+}
+
+// Get the greatest tick whose √P is at or below a √P
+pub fn tick_from_sqrt_price(sqrt_price: u128) -> Result<i32, CurveError> {
+    if !(MIN_SQRT_PRICE..=MAX_SQRT_PRICE).contains(&sqrt_price) {
+        return Err(CurveError::Overflow);
+    }
+    let (mut low, mut high) = (MIN_TICK, MAX_TICK);
+    while low < high {
+        let mid = low + (high - low + 1) / 2;
+        if sqrt_price_from_tick(mid)? <= sqrt_price {
+            low = mid;
+        } else {
+            high = mid - 1;
+        }
+    }
+    Ok(low)
+}
+
+// Amount of X between two prices for liquidity L
+// ΔX = L·(√Pb - √Pa) / (√Pa·√Pb)
+pub fn x_amount_delta(sqrt_price_a: u128, sqrt_price_b: u128, liquidity: u128, round_up: bool) -> Result<u128, CurveError> {
+    let (sqrt_price_a, sqrt_price_b) = if sqrt_price_a < sqrt_price_b { (sqrt_price_a, sqrt_price_b) } else { (sqrt_price_b, sqrt_price_a) };
+    if sqrt_price_a == 0 {
+        return Err(CurveError::Overflow);
+    }
+    // L·(√Pb - √Pa) = Q·√Pb + R, so ΔX = (Q·2⁶⁴ + R·2⁶⁴ / √Pb) / √Pa with a single rounding of each term
+    let (hi, lo) = full_mul(liquidity, sqrt_price_b - sqrt_price_a);
+    let (q, r) = div_rem_wide(hi, lo, sqrt_price_b).ok_or(CurveError::Overflow)?;
+    let remainder = if round_up { mul_div_ceil(r, Q64, sqrt_price_b) } else { mul_div(r, Q64, sqrt_price_b) }
+        .ok_or(CurveError::Overflow)?;
+    let (hi, lo) = full_mul(q, Q64);
+    let (lo, carry) = lo.overflowing_add(remainder);
+    let (amount, r) = div_rem_wide(hi + carry as u128, lo, sqrt_price_a).ok_or(CurveError::Overflow)?;
+    if round_up && r > 0 {
+        amount.checked_add(1).ok_or(CurveError::Overflow)
+    } else {
+        Ok(amount)
+    }
+}
+
+// Amount of Y between two prices for liquidity L
+// ΔY = L·(√Pb - √Pa)
+pub fn y_amount_delta(sqrt_price_a: u128, sqrt_price_b: u128, liquidity: u128, round_up: bool) -> Result<u128, CurveError> {
+    let delta = sqrt_price_a.abs_diff(sqrt_price_b);
+    if round_up {
+        mul_div_ceil(liquidity, delta, Q64)
+    } else {
+        mul_div(liquidity, delta, Q64)
+    }
+    .ok_or(CurveError::Overflow)
+}
+
+// Get √P after adding or removing amount A of X, rounded up
+// √P₂ = L·√P / (L ± A·√P)
+pub fn next_sqrt_price_from_x(sqrt_price: u128, liquidity: u128, a: u64, add: bool) -> Result<u128, CurveError> {
+    if a == 0 {
+        return Ok(sqrt_price);
+    }
+    let denominator = if add {
+        let product = mul_div(a as u128, sqrt_price, Q64).ok_or(CurveError::Overflow)?;
+        liquidity.checked_add(product)
+    } else {
+        let product = mul_div_ceil(a as u128, sqrt_price, Q64).ok_or(CurveError::Overflow)?;
+        liquidity.checked_sub(product).filter(|denominator| *denominator > 0)
+    }
+    .ok_or(CurveError::Overflow)?;
+    mul_div_ceil(liquidity, sqrt_price, denominator).ok_or(CurveError::Overflow)
+}
+
+// Get √P after adding or removing amount A of Y, rounded down
+// √P₂ = √P ± A / L
+pub fn next_sqrt_price_from_y(sqrt_price: u128, liquidity: u128, a: u64, add: bool) -> Result<u128, CurveError> {
+    if add {
+        sqrt_price.checked_add(mul_div(a as u128, Q64, liquidity).ok_or(CurveError::Overflow)?)
+    } else {
+        sqrt_price.checked_sub(mul_div_ceil(a as u128, Q64, liquidity).ok_or(CurveError::Overflow)?)
+    }
+    .ok_or(CurveError::Overflow)
+}
+
+// Amounts of X and Y backing liquidity L over a tick range at the current price
+pub fn xy_amounts_from_liquidity(
+    sqrt_price: u128,
+    sqrt_price_lower: u128,
+    sqrt_price_upper: u128,
+    liquidity: u128,
+    round_up: bool,
+) -> Result<(u64, u64), CurveError> {
+    let (x, y) = if sqrt_price <= sqrt_price_lower {
+        (x_amount_delta(sqrt_price_lower, sqrt_price_upper, liquidity, round_up)?, 0)
+    } else if sqrt_price < sqrt_price_upper {
+        (
+            x_amount_delta(sqrt_price, sqrt_price_upper, liquidity, round_up)?,
+            y_amount_delta(sqrt_price_lower, sqrt_price, liquidity, round_up)?,
+        )
+    } else {
+        (0, y_amount_delta(sqrt_price_lower, sqrt_price_upper, liquidity, round_up)?)
+    };
+    Ok((
+        u64::try_from(x).map_err(|_| CurveError::Overflow)?,
+        u64::try_from(y).map_err(|_| CurveError::Overflow)?,
+    ))
+}
+
+// Price of the input token in the output token as Q64.64 at a √P
+// P(X in Y) = √P², P(Y in X) = (1 / √P)²
+pub fn spot_price_from_sqrt_price(sqrt_price: u128, direction: SwapDirection) -> Result<u128, CurveError> {
+    let sqrt_price = match direction {
+        SwapDirection::XToY => sqrt_price,
+        SwapDirection::YToX => mul_div(Q64, Q64, sqrt_price).ok_or(CurveError::Overflow)?,
+    };
+    mul_div(sqrt_price, sqrt_price, Q64).ok_or(CurveError::Overflow)
+}
+
+// Result of swapping within a single tick range
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SwapStep {
+    pub sqrt_price_next: u128,
+    pub amount_in: u64,
+    pub amount_out: u64,
+    pub fee_amount: u64,
+}
+
+// Swap from the current √P towards a target √P without crossing a tick
+// The fee is charged on the input, amount_in excludes it
+// Exact in consumes up to amount_remaining including the fee, exact out pays out up to amount_remaining
+pub fn compute_swap_step(
+    sqrt_price_current: u128,
+    sqrt_price_target: u128,
+    liquidity: u128,
+    amount_remaining: u64,
+    fee: u16,
+    exact_in: bool,
+) -> Result<SwapStep, CurveError> {
+    if fee >= 10_000 {
+        return Err(CurveError::Overflow);
+    }
+    let x_to_y = sqrt_price_current >= sqrt_price_target;
+
+    // Input and output of moving all the way to the target
+    let amount_in_to_target = |sqrt_price: u128| if x_to_y {
+        x_amount_delta(sqrt_price, sqrt_price_current, liquidity, true)
+    } else {
+        y_amount_delta(sqrt_price_current, sqrt_price, liquidity, true)
+    };
+    let amount_out_to_target = |sqrt_price: u128| if x_to_y {
+        y_amount_delta(sqrt_price, sqrt_price_current, liquidity, false)
+    } else {
+        x_amount_delta(sqrt_price_current, sqrt_price, liquidity, false)
+    };
+
+    let sqrt_price_next = if exact_in {
+        let amount_remaining_less_fee = (amount_remaining as u128 * (10_000 - fee) as u128 / 10_000) as u64;
+        if amount_remaining_less_fee as u128 >= amount_in_to_target(sqrt_price_target)? {
+            sqrt_price_target
+        } else if x_to_y {
+            next_sqrt_price_from_x(sqrt_price_current, liquidity, amount_remaining_less_fee, true)?
+        } else {
+            next_sqrt_price_from_y(sqrt_price_current, liquidity, amount_remaining_less_fee, true)?
+        }
+    } else if amount_remaining as u128 >= amount_out_to_target(sqrt_price_target)? {
+        sqrt_price_target
+    } else if x_to_y {
+        next_sqrt_price_from_y(sqrt_price_current, liquidity, amount_remaining, false)?
+    } else {
+        next_sqrt_price_from_x(sqrt_price_current, liquidity, amount_remaining, false)?
+    };
+
+    let amount_in = u64::try_from(amount_in_to_target(sqrt_price_next)?).map_err(|_| CurveError::Overflow)?;
+    let mut amount_out = u64::try_from(amount_out_to_target(sqrt_price_next)?).map_err(|_| CurveError::Overflow)?;
+    if !exact_in {
+        amount_out = amount_out.min(amount_remaining);
+    }
+
+    // When exact in stops short of the target, the rest of the input is kept as the fee
+    let fee_amount = if exact_in && sqrt_price_next != sqrt_price_target {
+        amount_remaining.checked_sub(amount_in).ok_or(CurveError::Overflow)?
+    } else {
+        u64::try_from((amount_in as u128 * fee as u128).div_ceil((10_000 - fee) as u128)).map_err(|_| CurveError::Overflow)?
+    };
+
+    Ok(SwapStep { sqrt_price_next, amount_in, amount_out, fee_amount })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::concentrated::{
+        compute_swap_step, spot_price_from_sqrt_price, sqrt_price_from_tick, tick_from_sqrt_price, x_amount_delta,
+        xy_amounts_from_liquidity, y_amount_delta, MAX_SQRT_PRICE, MAX_TICK, MIN_SQRT_PRICE, MIN_TICK, Q64,
+    };
+    use crate::{ConstantProduct, Curve, SwapDirection};
+
+    #[test]
+    fn tick_math() {
+        assert_eq!(sqrt_price_from_tick(0).unwrap(), Q64);
+        assert_eq!(sqrt_price_from_tick(MIN_TICK).unwrap(), MIN_SQRT_PRICE);
+        assert_eq!(sqrt_price_from_tick(MAX_TICK).unwrap(), MAX_SQRT_PRICE);
+        assert!(sqrt_price_from_tick(MAX_TICK + 1).is_err());
+        // √1.0001 = 1.0000499987500625
+        assert!(sqrt_price_from_tick(1).unwrap().abs_diff(18_447_666_387_855_959_850) <= 1);
+        for tick in [MIN_TICK, -100_000, -1, 0, 1, 7, 100_000, MAX_TICK] {
+            let sqrt_price = sqrt_price_from_tick(tick).unwrap();
+            assert_eq!(tick_from_sqrt_price(sqrt_price).unwrap(), tick);
+            if tick < MAX_TICK {
+                assert_eq!(tick_from_sqrt_price(sqrt_price + 1).unwrap(), tick);
+            }
+        }
+    }
+
+    #[test]
+    fn liquidity_amounts() {
+        let (lower, upper) = (sqrt_price_from_tick(-1_000).unwrap(), sqrt_price_from_tick(1_000).unwrap());
+        // Below the range only X is needed, above it only Y
+        let (x, y) = xy_amounts_from_liquidity(sqrt_price_from_tick(-2_000).unwrap(), lower, upper, 1_000_000_000, true).unwrap();
+        assert_eq!(y, 0);
+        assert_eq!(x as u128, x_amount_delta(lower, upper, 1_000_000_000, true).unwrap());
+        let (x, y) = xy_amounts_from_liquidity(sqrt_price_from_tick(2_000).unwrap(), lower, upper, 1_000_000_000, true).unwrap();
+        assert_eq!(x, 0);
+        assert_eq!(y as u128, y_amount_delta(lower, upper, 1_000_000_000, true).unwrap());
+        // Rounding up never asks for less than rounding down
+        let (x_up, y_up) = xy_amounts_from_liquidity(Q64, lower, upper, 1_000_000_000, true).unwrap();
+        let (x_down, y_down) = xy_amounts_from_liquidity(Q64, lower, upper, 1_000_000_000, false).unwrap();
+        assert!(x_up >= x_down && y_up >= y_down);
+        assert_eq!(x_down, 48_768_197);
+    }
+
+    #[test]
+    fn spot_price() {
+        assert_eq!(spot_price_from_sqrt_price(Q64, SwapDirection::XToY).unwrap(), Q64);
+        // √P = 2 prices X at 4 Y and Y at 0.25 X
+        assert_eq!(spot_price_from_sqrt_price(2 << 64, SwapDirection::XToY).unwrap(), 4 << 64);
+        assert_eq!(spot_price_from_sqrt_price(2 << 64, SwapDirection::YToX).unwrap(), 1 << 62);
+    }
+
+    #[test]
+    fn swap_step() {
+        // Liquidity of 1,000,000 at a price of 1 behaves like a constant product pool of 1,000,000 X and Y
+        let step = compute_swap_step(Q64, sqrt_price_from_tick(MIN_TICK).unwrap(), 1_000_000, 10_000, 0, true).unwrap();
+        let (cp_amount_out, _) = ConstantProduct.swap_out(1_000_000, 1_000_000, 10_000, 0, SwapDirection::XToY).unwrap();
+        assert_eq!(step.amount_in + step.fee_amount, 10_000);
+        assert!(step.amount_out.abs_diff(cp_amount_out) <= 1);
+        assert!(step.amount_out <= cp_amount_out);
+
+        // The fee is charged on the input
+        let step = compute_swap_step(Q64, sqrt_price_from_tick(MAX_TICK).unwrap(), 1_000_000, 10_000, 30, true).unwrap();
+        assert_eq!(step.amount_in + step.fee_amount, 10_000);
+        assert_eq!(step.fee_amount, 30);
+
+        // Stopping at the target leaves the rest of the input unspent
+        let target = sqrt_price_from_tick(-10).unwrap();
+        let step = compute_swap_step(Q64, target, 1_000_000, 10_000, 30, true).unwrap();
+        assert_eq!(step.sqrt_price_next, target);
+        assert!(step.amount_in + step.fee_amount < 10_000);
+
+        // Exact out pays exactly the requested amount and charges at least the exact in price
+        let step = compute_swap_step(Q64, sqrt_price_from_tick(MIN_TICK).unwrap(), 1_000_000, 9_871, 30, false).unwrap();
+        assert_eq!(step.amount_out, 9_871);
+        let exact_in = compute_swap_step(Q64, sqrt_price_from_tick(MIN_TICK).unwrap(), 1_000_000, step.amount_in + step.fee_amount, 30, true).unwrap();
+        assert!(exact_in.amount_out >= 9_871);
+    }
+}
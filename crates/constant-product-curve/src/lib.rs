@@ -1,21 +1,150 @@
+pub mod concentrated;
+pub mod math;
+mod stable;
+mod weighted;
+pub use stable::{StableSwap, MAX_AMP};
+pub use weighted::{Weighted, MAX_WEIGHT, MIN_WEIGHT};
+
 #[derive(Debug, PartialEq, Eq)]
 pub enum CurveError {
-    Overflow
+    Overflow,
+    // A reserve or amount the invariant needs is zero
+    ZeroAmount,
+    // Curve parameters are out of range
+    InvalidParams,
+}
+
+// Direction of a swap through a pool of X and Y
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SwapDirection {
+    XToY,
+    YToX,
+}
+
+// An invariant a pool of X and Y can price swaps, deposits and withdrawals against
+pub trait Curve {
+    // Calculate the amount out and fee in the output token from swapping amount A in
+    fn swap_out(&self, x: u64, y: u64, a: u64, fee: u16, direction: SwapDirection) -> Result<(u64, u64), CurveError>;
+
+    // Calculate the amount in and fee in the output token required to withdraw amount A out
+    fn swap_in(&self, x: u64, y: u64, a: u64, fee: u16, direction: SwapDirection) -> Result<(u64, u64), CurveError>;
+
+    // Get liquidity token amount for the first deposit of X and Y
+    fn initial_liquidity(&self, x: u64, y: u64) -> Result<u64, CurveError> {
+        l_from_xy(x, y)
+    }
+
+    // Get amount of X and Y to deposit from liquidity token amount
+    fn deposit_amounts(&self, x: u64, y: u64, l: u64, a: u64) -> Result<(u64, u64), CurveError> {
+        xy_deposit_amounts_from_l(x, y, l, a, 1_000_000_000)
+    }
+
+    // Get amount of X and Y to withdraw from liquidity token amount
+    fn withdraw_amounts(&self, x: u64, y: u64, l: u64, a: u64) -> Result<(u64, u64), CurveError> {
+        xy_withdraw_amounts_from_l(x, y, l, a, 1_000_000_000)
+    }
+
+    // Split a single-sided deposit of amount A into a swap through the curve and a deposit of the rest with the swap's output.
+    // The largest swap that leaves the rest at least as large a share of the post-swap reserves as the output is found by bisection,
+    // then whichever of it and the next amount mints more LP is kept. The protocol's cut of the fee, in basis points of the fee, leaves
+    // the output reserve the LP is priced on. Returns the amount swapped, the output and fee, and the LP minted.
+    // LP is minted for the smaller of the two shares, so the excess of the other stays with existing LPs. The split sits where the shares
+    // cross, so that excess is bounded by the change in share from swapping one more unit, plus rounding
+    fn deposit_single_amounts(&self, x: u64, y: u64, l: u64, a: u64, fee: u16, protocol_fee: u16, direction: SwapDirection) -> Result<(u64, u64, u64, u64), CurveError> {
+        let (reserve_in, reserve_out) = match direction {
+            SwapDirection::XToY => (x, y),
+            SwapDirection::YToX => (y, x),
+        };
+        // LP minted for the rest and the output of swapping S, as the share of each side's post-swap reserves
+        let deposit = |s: u64| -> Result<(u128, u128, u64, u64), CurveError> {
+            let (amount_out, fee) = match s {
+                0 => (0, 0),
+                _ => self.swap_out(x, y, s, fee, direction)?,
+            };
+            let new_in = (reserve_in as u128).checked_add(s as u128).ok_or(CurveError::Overflow)?;
+            let protocol_fee = (fee as u128 * protocol_fee as u128 / 10_000) as u64;
+            let new_out = reserve_out.checked_sub(amount_out).and_then(|r| r.checked_sub(protocol_fee)).ok_or(CurveError::Overflow)? as u128;
+            Ok((
+                ((a - s) as u128 * l as u128).checked_div(new_in).ok_or(CurveError::Overflow)?,
+                (amount_out as u128 * l as u128).checked_div(new_out).ok_or(CurveError::Overflow)?,
+                amount_out,
+                fee,
+            ))
+        };
+
+        let (mut low, mut high) = (0u64, a);
+        while low < high {
+            let mid = low + (high - low).div_ceil(2);
+            let (lp_rest, lp_out, _, _) = deposit(mid)?;
+            match lp_rest >= lp_out {
+                true => low = mid,
+                false => high = mid - 1,
+            }
+        }
+
+        let mut best = (0, 0, 0, 0);
+        for s in [low, low.saturating_add(1).min(a)] {
+            let (lp_rest, lp_out, amount_out, fee) = deposit(s)?;
+            let lp = u64::try_from(lp_rest.min(lp_out)).map_err(|_| CurveError::Overflow)?;
+            if lp > best.3 {
+                best = (s, amount_out, fee, lp);
+            }
+        }
+        Ok(best)
+    }
+
+    // Get the marginal price of the input token in the output token as Q64.64
+    // P = Out / In
+    fn spot_price(&self, x: u64, y: u64, direction: SwapDirection) -> Result<u128, CurveError> {
+        if x == 0 || y == 0 {
+            return Err(CurveError::ZeroAmount);
+        }
+        let (reserve_in, reserve_out) = match direction {
+            SwapDirection::XToY => (x, y),
+            SwapDirection::YToX => (y, x),
+        };
+        math::mul_div(reserve_out as u128, concentrated::Q64, reserve_in as u128).ok_or(CurveError::Overflow)
+    }
+}
+
+// Constant product invariant
+// K = X·Y
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ConstantProduct;
+
+impl Curve for ConstantProduct {
+    #[inline]
+    fn swap_out(&self, x: u64, y: u64, a: u64, fee: u16, direction: SwapDirection) -> Result<(u64, u64), CurveError> {
+        match direction {
+            SwapDirection::XToY => delta_y_from_x_swap_amount_with_fee(x, y, a, fee),
+            SwapDirection::YToX => delta_x_from_y_swap_amount_with_fee(x, y, a, fee),
+        }
+    }
+
+    #[inline]
+    fn swap_in(&self, x: u64, y: u64, a: u64, fee: u16, direction: SwapDirection) -> Result<(u64, u64), CurveError> {
+        match direction {
+            SwapDirection::XToY => delta_x_from_y_withdraw_amount_with_fee(x, y, a, fee),
+            SwapDirection::YToX => delta_y_from_x_withdraw_amount_with_fee(x, y, a, fee),
+        }
+    }
 }
 
 // Static Invariant calculation
 #[inline]
 pub fn k_from_xy(x: u64, y: u64) -> Result<u128, CurveError> {
-    assert_ne!(x, 0);
-    assert_ne!(y, 0);
+    if x == 0 || y == 0 {
+        return Err(CurveError::ZeroAmount);
+    }
     Ok((x as u128).checked_mul(y as u128).ok_or(CurveError::Overflow)?)
 }
 
 // Get spot price for a token in its opposing token
 #[inline]
 pub fn spot_price_from_pair(x: u64, y: u64, precision: u32) -> Result<u64, CurveError> {
-    assert_ne!(x, 0);
-    assert_ne!(y, 0);
+    if x == 0 || y == 0 {
+        return Err(CurveError::ZeroAmount);
+    }
     Ok(
         u64::try_from(
             (x as u128)
@@ -26,6 +155,30 @@ pub fn spot_price_from_pair(x: u64, y: u64, precision: u32) -> Result<u64, Curve
     )
 }
 
+// Get initial liquidity token amount from the geometric mean of X and Y
+// L = √(X·Y)
+#[inline]
+pub fn l_from_xy(x: u64, y: u64) -> Result<u64, CurveError> {
+    u64::try_from(sqrt_u128(k_from_xy(x, y)?)).map_err(|_| CurveError::Overflow)
+}
+
+// Integer square root, rounded down
+#[inline]
+pub fn sqrt_u128(n: u128) -> u128 {
+    if n < 2 {
+        return n;
+    }
+    // Start from a power of two at or above √n and converge downwards
+    let mut x = 1u128 << (128 - n.leading_zeros()).div_ceil(2);
+    loop {
+        let y = (x + n / x) / 2;
+        if y >= x {
+            return x;
+        }
+        x = y;
+    }
+}
+
 // Get amount of X and Y to deposit from liquidity token amount
 #[inline]
 pub fn xy_deposit_amounts_from_l(x: u64, y: u64, l: u64, a: u64, precision: u32) -> Result<(u64, u64), CurveError> {
@@ -122,9 +275,81 @@ pub fn delta_y_from_x_swap_amount_with_fee(x: u64, y: u64, a: u64, fee: u16) ->
     delta_x_from_y_swap_amount_with_fee(y,x,a, fee)
 }
 
+// Split a raw output amount into the amount after fees and the fee
+#[inline]
+pub fn split_fee_amount(raw_amount: u64, fee: u16) -> Result<(u64, u64), CurveError> {
+    let amount = raw_amount.checked_mul((10_000 - fee).into()).ok_or(CurveError::Overflow)?.saturating_div(10_000);
+    Ok((amount, raw_amount - amount))
+}
+
+// Gross up an output amount A so that at least A remains after fees
+// Raw Amount = ⌈A / (1 - fee)⌉
+#[inline]
+pub fn gross_up_fee_amount(a: u64, fee: u16) -> Result<u64, CurveError> {
+    u64::try_from(
+        (a as u128)
+            .checked_mul(10_000).ok_or(CurveError::Overflow)?
+            .div_ceil((10_000 - fee).into())
+    ).map_err(|_| CurveError::Overflow)
+}
+
+// Calculate new value of X required to withdraw amount A of Y, rounded up in the pool's favour
+// When we withdraw amount A of Y by swapping in X, we must calculate the new balance of X from invariant K
+// Y₂ = Y₁ - Amount
+// X₂ = ⌈K / Y₂⌉
+#[inline]
+pub fn x2_from_y_withdraw_amount(x: u64, y: u64, a: u64) -> Result<u64, CurveError> {
+    let k = k_from_xy(x, y)?;
+    let y_new = (y as u128).checked_sub(a as u128).ok_or(CurveError::Overflow)?;
+    // Withdrawing the entire reserve is never possible
+    if y_new == 0 {
+        return Err(CurveError::Overflow);
+    }
+    u64::try_from(k.div_ceil(y_new)).map_err(|_| CurveError::Overflow)
+}
+
+// Calculate new value of Y₂ required to withdraw amount A of X
+// X₂ = X₁ - Amount
+// Y₂ = ⌈K / X₂⌉
+#[inline]
+pub fn y2_from_x_withdraw_amount(x: u64, y: u64, a: u64) -> Result<u64, CurveError> {
+    x2_from_y_withdraw_amount(y, x, a)
+}
+
+// Calculate the deposit amount of X required to withdraw amount A of Y
+// ΔX = X₂ - X₁
+#[inline]
+pub fn delta_x_from_y_withdraw_amount(x: u64, y: u64, a: u64) -> Result<u64, CurveError> {
+    x2_from_y_withdraw_amount(x, y, a)?.checked_sub(x).ok_or(CurveError::Overflow)
+}
+
+// Calculate the deposit amount of Y required to withdraw amount A of X
+// ΔY = Y₂ - Y₁
+#[inline]
+pub fn delta_y_from_x_withdraw_amount(x: u64, y: u64, a: u64) -> Result<u64, CurveError> {
+    delta_x_from_y_withdraw_amount(y, x, a)
+}
+
+// Calculate the deposit amount of X required to withdraw amount A of Y after fees
+// The fee is taken from the output, so we withdraw ⌈A / (1 - fee)⌉ of Y before fees
+// Returns the deposit amount of X and the fee in Y
+#[inline]
+pub fn delta_x_from_y_withdraw_amount_with_fee(x: u64, y: u64, a: u64, fee: u16) -> Result<(u64, u64), CurveError> {
+    let raw_amount = gross_up_fee_amount(a, fee)?;
+    let amount = delta_x_from_y_withdraw_amount(x, y, raw_amount)?;
+    Ok((amount, raw_amount - a))
+}
+
+// Calculate the deposit amount of Y required to withdraw amount A of X after fees
+// Returns the deposit amount of Y and the fee in X
+#[inline]
+pub fn delta_y_from_x_withdraw_amount_with_fee(x: u64, y: u64, a: u64, fee: u16) -> Result<(u64, u64), CurveError> {
+    delta_x_from_y_withdraw_amount_with_fee(y, x, a, fee)
+}
+
 #[cfg(test)]
 mod tests {
-    use crate::delta_y_from_x_swap_amount_with_fee;
+    use crate::{delta_x_from_y_withdraw_amount_with_fee, delta_y_from_x_swap_amount_with_fee, l_from_xy, sqrt_u128, ConstantProduct, Curve, CurveError, StableSwap, SwapDirection, Weighted};
     #[test]
     fn swap() {
         let (amount_out, fee) = delta_y_from_x_swap_amount_with_fee(20, 30, 5, 0).unwrap();
@@ -141,4 +366,80 @@ mod tests {
         assert_eq!(amount_out, 5);
         assert_eq!(fee, 1);
     }
+
+    #[test]
+    fn swap_exact_out_with_fee() {
+        let (amount_in, fee) = delta_x_from_y_withdraw_amount_with_fee(20, 30, 5, 100).unwrap();
+        assert_eq!(amount_in, 5);
+        assert_eq!(fee, 1);
+        // Swapping the quoted input back through the curve yields at least the requested output
+        let (amount_in, _) = delta_x_from_y_withdraw_amount_with_fee(1_000, 3_000, 250, 30).unwrap();
+        let (amount_out, _) = delta_y_from_x_swap_amount_with_fee(1_000, 3_000, amount_in, 30).unwrap();
+        assert!(amount_out >= 250);
+        // The whole reserve can never be withdrawn
+        assert!(delta_x_from_y_withdraw_amount_with_fee(20, 30, 30, 0).is_err());
+    }
+
+    #[test]
+    fn constant_product_curve() {
+        let curve = ConstantProduct;
+        assert_eq!(curve.swap_out(20, 30, 5, 100, SwapDirection::XToY).unwrap(), (5, 1));
+        assert_eq!(curve.swap_out(30, 20, 5, 100, SwapDirection::YToX).unwrap(), (5, 1));
+        assert_eq!(curve.swap_in(20, 30, 5, 100, SwapDirection::XToY).unwrap(), (5, 1));
+        assert_eq!(curve.swap_in(30, 20, 5, 100, SwapDirection::YToX).unwrap(), (5, 1));
+    }
+
+    #[test]
+    fn deposit_single_amounts() {
+        let curve = ConstantProduct;
+        // Half of a deposit into a balanced pool is swapped, a little more with a fee
+        assert_eq!(curve.deposit_single_amounts(1_000_000, 1_000_000, 1_000_000, 2_000, 0, 0, SwapDirection::XToY).unwrap().3, 999);
+        assert_eq!(curve.deposit_single_amounts(1_000_000, 1_000_000, 1_000_000, 2_000, 30, 0, SwapDirection::XToY).unwrap(), (1_002, 997, 4, 997));
+        // Nothing is minted for a deposit too small to split
+        assert_eq!(curve.deposit_single_amounts(1_000_000, 1_000_000, 1_000_000, 1, 30, 0, SwapDirection::XToY).unwrap().3, 0);
+        // The protocol's cut of the fee isn't in the output reserve the LP is priced on, which lifts the output's share
+        let (s, out, fee, lp) = curve.deposit_single_amounts(1_000_000, 1_000_000, 1_000_000, 200_000, 30, 10_000, SwapDirection::XToY).unwrap();
+        assert!(lp > curve.deposit_single_amounts(1_000_000, 1_000_000, 1_000_000, 200_000, 30, 0, SwapDirection::XToY).unwrap().3);
+        // The share left unminted is no more than swapping one more unit moves the output's share
+        let (out_next, fee_next) = curve.swap_out(1_000_000, 1_000_000, s + 1, 30, SwapDirection::XToY).unwrap();
+        let lp_rest = (200_000 - s) as u128 * 1_000_000 / (1_000_000 + s) as u128;
+        let lp_out = out as u128 * 1_000_000 / (1_000_000 - out - fee) as u128;
+        let lp_out_next = out_next as u128 * 1_000_000 / (1_000_000 - out_next - fee_next) as u128;
+        assert!(lp_rest.abs_diff(lp_out) <= lp_out_next - lp_out + 1);
+        // Both directions are symmetric
+        assert_eq!(
+            curve.deposit_single_amounts(1_000_000, 1_000_000, 1_000_000, 2_000, 30, 0, SwapDirection::XToY).unwrap(),
+            curve.deposit_single_amounts(1_000_000, 1_000_000, 1_000_000, 2_000, 30, 0, SwapDirection::YToX).unwrap(),
+        );
+    }
+
+    #[test]
+    fn initial_liquidity() {
+        assert_eq!(l_from_xy(1_000_000, 1_000_000).unwrap(), 1_000_000);
+        assert_eq!(l_from_xy(20, 30).unwrap(), 24);
+        assert_eq!(l_from_xy(u64::MAX, u64::MAX).unwrap(), u64::MAX);
+        assert_eq!(sqrt_u128(u128::MAX), u64::MAX as u128);
+    }
+
+    #[test]
+    fn zero_amounts() {
+        // An empty side can't seed a pool, and an empty pool can't be swapped against or priced, whatever its curve
+        let curves: [&dyn Curve; 3] = [&ConstantProduct, &StableSwap { amp: 100 }, &Weighted { weight_x: 8_000 }];
+        for curve in curves {
+            assert_eq!(curve.initial_liquidity(0, 1_000_000), Err(CurveError::ZeroAmount));
+            assert_eq!(curve.swap_out(0, 0, 1_000, 30, SwapDirection::XToY), Err(CurveError::ZeroAmount));
+            assert_eq!(curve.swap_in(0, 0, 1_000, 30, SwapDirection::YToX), Err(CurveError::ZeroAmount));
+            assert_eq!(curve.spot_price(1_000_000, 0, SwapDirection::XToY), Err(CurveError::ZeroAmount));
+        }
+    }
+
+    #[test]
+    fn invalid_params() {
+        for amp in [0, crate::MAX_AMP + 1] {
+            assert_eq!(StableSwap { amp }.swap_out(1_000_000, 1_000_000, 1_000, 30, SwapDirection::XToY), Err(CurveError::InvalidParams));
+        }
+        for weight_x in [crate::MIN_WEIGHT - 1, crate::MAX_WEIGHT + 1] {
+            assert_eq!(Weighted { weight_x }.swap_out(1_000_000, 1_000_000, 1_000, 30, SwapDirection::XToY), Err(CurveError::InvalidParams));
+        }
+    }
 }
\ No newline at end of file
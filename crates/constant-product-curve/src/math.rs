@@ -0,0 +1,171 @@
+// 256-bit intermediate helpers for invariants whose products overflow u128
+
+const MASK: u128 = u64::MAX as u128;
+
+// Multiply two u128 values into a 256-bit (hi, lo) pair
+#[inline]
+pub fn full_mul(a: u128, b: u128) -> (u128, u128) {
+    let (a1, a0) = (a >> 64, a & MASK);
+    let (b1, b0) = (b >> 64, b & MASK);
+    let p00 = a0 * b0;
+    let p01 = a0 * b1;
+    let p10 = a1 * b0;
+    let p11 = a1 * b1;
+    let mid = (p00 >> 64) + (p01 & MASK) + (p10 & MASK);
+    let lo = (p00 & MASK) | (mid << 64);
+    let hi = p11 + (p01 >> 64) + (p10 >> 64) + (mid >> 64);
+    (hi, lo)
+}
+
+// Divide a 256-bit (hi, lo) value by D, returning the quotient and remainder
+// Returns None if D is zero or the quotient does not fit in a u128
+#[inline]
+pub fn div_rem_wide(hi: u128, lo: u128, d: u128) -> Option<(u128, u128)> {
+    if d == 0 || hi >= d {
+        return None;
+    }
+    if hi == 0 {
+        return Some((lo / d, lo % d));
+    }
+    // Shift-subtract long division, the remainder always stays below D
+    let mut rem = hi;
+    let mut q = 0u128;
+    for i in (0..128).rev() {
+        let carry = rem >> 127;
+        rem = (rem << 1) | ((lo >> i) & 1);
+        q <<= 1;
+        if carry == 1 || rem >= d {
+            rem = rem.wrapping_sub(d);
+            q |= 1;
+        }
+    }
+    Some((q, rem))
+}
+
+// ⌊A·B / C⌋
+#[inline]
+pub fn mul_div(a: u128, b: u128, c: u128) -> Option<u128> {
+    let (hi, lo) = full_mul(a, b);
+    div_rem_wide(hi, lo, c).map(|(q, _)| q)
+}
+
+// ⌈A·B / C⌉
+#[inline]
+pub fn mul_div_ceil(a: u128, b: u128, c: u128) -> Option<u128> {
+    let (hi, lo) = full_mul(a, b);
+    let (q, r) = div_rem_wide(hi, lo, c)?;
+    if r > 0 {
+        q.checked_add(1)
+    } else {
+        Some(q)
+    }
+}
+
+// 18 decimal fixed point
+pub const WAD: i128 = 1_000_000_000_000_000_000;
+
+// ln(2) in 18 decimal fixed point
+const LN_2: i128 = 693_147_180_559_945_309;
+
+// A·B for signed 18 decimal fixed-point values, rounded towards zero
+#[inline]
+pub fn mul_wad(a: i128, b: i128) -> Option<i128> {
+    let product = i128::try_from(mul_div(a.unsigned_abs(), b.unsigned_abs(), WAD as u128)?).ok()?;
+    Some(if (a < 0) != (b < 0) { -product } else { product })
+}
+
+// Natural logarithm of a positive 18 decimal fixed-point value
+// ln(X) = k·ln(2) + ln(M), M = X / 2ᵏ in [1, 2)
+// ln(M) = 2·atanh(Z) = 2·(Z + Z³/3 + Z⁵/5 + ...), Z = (M - 1) / (M + 1)
+pub fn ln_wad(x: i128) -> Option<i128> {
+    if x <= 0 {
+        return None;
+    }
+    let mut k: i128 = 0;
+    let mut m = x;
+    while m >= 2 * WAD {
+        m >>= 1;
+        k += 1;
+    }
+    while m < WAD {
+        m <<= 1;
+        k -= 1;
+    }
+    let z = (m - WAD) * WAD / (m + WAD);
+    let z2 = z * z / WAD;
+    let mut term = z;
+    let mut sum = 0;
+    let mut n = 1;
+    while term != 0 {
+        sum += term / n;
+        term = term * z2 / WAD;
+        n += 2;
+    }
+    k.checked_mul(LN_2)?.checked_add(2 * sum)
+}
+
+// e raised to an 18 decimal fixed-point power
+// eˣ = 2ᵏ·eᴿ, R = X - k·ln(2) in [0, ln(2))
+// eᴿ = 1 + R + R²/2! + R³/3! + ...
+pub fn exp_wad(x: i128) -> Option<i128> {
+    let k = x.div_euclid(LN_2);
+    let r = x.rem_euclid(LN_2);
+    let mut term = WAD;
+    let mut sum = WAD;
+    let mut n = 1;
+    while term != 0 {
+        term = term * r / WAD / n;
+        sum += term;
+        n += 1;
+    }
+    if k >= 0 {
+        // Shifting must not push any bits into or past the sign bit
+        if k >= 127 || sum.leading_zeros() as i128 <= k {
+            return None;
+        }
+        Some(sum << k)
+    } else if k <= -127 {
+        Some(0)
+    } else {
+        Some(sum >> -k)
+    }
+}
+
+// Base raised to an exponent, both 18 decimal fixed point
+// Bᴱ = e^(E·ln(B))
+pub fn pow_wad(base: i128, exponent: i128) -> Option<i128> {
+    if exponent == 0 || base == WAD {
+        return Some(WAD);
+    }
+    exp_wad(mul_wad(ln_wad(base)?, exponent)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::math::{exp_wad, full_mul, ln_wad, mul_div, mul_div_ceil, pow_wad, WAD};
+
+    #[test]
+    fn wide_mul_div() {
+        assert_eq!(full_mul(u128::MAX, u128::MAX), (u128::MAX - 1, 1));
+        assert_eq!(mul_div(u128::MAX, u128::MAX, u128::MAX), Some(u128::MAX));
+        assert_eq!(mul_div(1 << 100, 1 << 100, 1 << 90), Some(1 << 110));
+        assert_eq!(mul_div(7, 3, 2), Some(10));
+        assert_eq!(mul_div_ceil(7, 3, 2), Some(11));
+        assert_eq!(mul_div(u128::MAX, 2, 1), None);
+        assert_eq!(mul_div(1, 1, 0), None);
+    }
+
+    #[test]
+    fn fixed_point_ln_exp_pow() {
+        assert_eq!(ln_wad(WAD), Some(0));
+        assert!(ln_wad(2 * WAD).unwrap().abs_diff(693_147_180_559_945_309) <= 10);
+        assert!(ln_wad(WAD / 10).unwrap().abs_diff(-2_302_585_092_994_045_684) <= 10);
+        assert_eq!(ln_wad(0), None);
+        assert_eq!(exp_wad(0), Some(WAD));
+        assert!(exp_wad(WAD).unwrap().abs_diff(2_718_281_828_459_045_235) <= 100);
+        assert!(exp_wad(-WAD).unwrap().abs_diff(367_879_441_171_442_321) <= 100);
+        assert_eq!(exp_wad(100 * WAD), None);
+        assert!(pow_wad(4 * WAD, WAD / 2).unwrap().abs_diff(2 * WAD) <= 100);
+        assert!(pow_wad(WAD / 2, 3 * WAD).unwrap().abs_diff(WAD / 8) <= 100);
+    }
+}
@@ -0,0 +1,201 @@
+use crate::{
+    concentrated::Q64,
+    gross_up_fee_amount, split_fee_amount,
+    math::{div_rem_wide, full_mul, mul_div},
+    Curve, CurveError, SwapDirection,
+};
+
+// Upper bound on the amplification coefficient
+pub const MAX_AMP: u64 = 1_000_000;
+
+// Newton's method iteration limit when solving for D and Y
+const MAX_ITERATIONS: usize = 255;
+
+// StableSwap invariant for two tokens with amplification coefficient A
+// A·nⁿ·(X + Y) + D = A·D·nⁿ + Dⁿ⁺¹ / (nⁿ·X·Y), n = 2
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct StableSwap {
+    pub amp: u64,
+}
+
+impl StableSwap {
+    // A·nⁿ
+    #[inline]
+    fn ann(&self) -> Result<u128, CurveError> {
+        if self.amp == 0 || self.amp > MAX_AMP {
+            return Err(CurveError::InvalidParams);
+        }
+        Ok(self.amp as u128 * 4)
+    }
+
+    // Solve for the invariant D with Newton's method
+    // D_P = D³ / (4·X·Y)
+    // D = (Ann·S + 2·D_P)·D / ((Ann - 1)·D + 3·D_P)
+    pub fn d_from_xy(&self, x: u64, y: u64) -> Result<u128, CurveError> {
+        if x == 0 || y == 0 {
+            return Err(CurveError::ZeroAmount);
+        }
+        let ann = self.ann()?;
+        let (x, y) = (x as u128, y as u128);
+        let s = x + y;
+        let mut d = s;
+        for _ in 0..MAX_ITERATIONS {
+            let d_p = mul_div(
+                mul_div(d, d, x * 2).ok_or(CurveError::Overflow)?,
+                d,
+                y * 2,
+            ).ok_or(CurveError::Overflow)?;
+            let d_prev = d;
+            let numerator = ann
+                .checked_mul(s).ok_or(CurveError::Overflow)?
+                .checked_add(d_p.checked_mul(2).ok_or(CurveError::Overflow)?).ok_or(CurveError::Overflow)?;
+            let denominator = (ann - 1)
+                .checked_mul(d).ok_or(CurveError::Overflow)?
+                .checked_add(d_p.checked_mul(3).ok_or(CurveError::Overflow)?).ok_or(CurveError::Overflow)?;
+            d = mul_div(numerator, d, denominator).ok_or(CurveError::Overflow)?;
+            if d.abs_diff(d_prev) <= 1 {
+                return Ok(d);
+            }
+        }
+        Err(CurveError::Overflow)
+    }
+
+    // Solve for the balance of one token from the new balance X of the other with Newton's method
+    // c = D³ / (4·X·Ann)
+    // b = X + D / Ann
+    // Y = (Y² + c) / (2·Y + b - D)
+    pub fn y_from_x_and_d(&self, x: u128, d: u128) -> Result<u128, CurveError> {
+        if x == 0 {
+            return Err(CurveError::ZeroAmount);
+        }
+        let ann = self.ann()?;
+        let c = mul_div(
+            mul_div(d, d, x.checked_mul(2).ok_or(CurveError::Overflow)?).ok_or(CurveError::Overflow)?,
+            d,
+            ann * 2,
+        ).ok_or(CurveError::Overflow)?;
+        let b = x.checked_add(d / ann).ok_or(CurveError::Overflow)?;
+        let mut y = d;
+        for _ in 0..MAX_ITERATIONS {
+            let y_prev = y;
+            let (hi, lo) = full_mul(y, y);
+            let (lo, carry) = lo.overflowing_add(c);
+            let denominator = y
+                .checked_mul(2).ok_or(CurveError::Overflow)?
+                .checked_add(b).ok_or(CurveError::Overflow)?
+                .checked_sub(d).ok_or(CurveError::Overflow)?;
+            y = div_rem_wide(hi + carry as u128, lo, denominator).ok_or(CurveError::Overflow)?.0;
+            if y.abs_diff(y_prev) <= 1 {
+                return Ok(y);
+            }
+        }
+        Err(CurveError::Overflow)
+    }
+}
+
+impl Curve for StableSwap {
+    fn swap_out(&self, x: u64, y: u64, a: u64, fee: u16, direction: SwapDirection) -> Result<(u64, u64), CurveError> {
+        let d = self.d_from_xy(x, y)?;
+        let (reserve_in, reserve_out) = match direction {
+            SwapDirection::XToY => (x, y),
+            SwapDirection::YToX => (y, x),
+        };
+        let new_out = self.y_from_x_and_d(reserve_in as u128 + a as u128, d)?;
+        // Round the output down in the pool's favour
+        let raw_amount = u64::try_from(
+            (reserve_out as u128)
+                .checked_sub(new_out).ok_or(CurveError::Overflow)?
+                .saturating_sub(1)
+        ).map_err(|_| CurveError::Overflow)?;
+        split_fee_amount(raw_amount, fee)
+    }
+
+    fn swap_in(&self, x: u64, y: u64, a: u64, fee: u16, direction: SwapDirection) -> Result<(u64, u64), CurveError> {
+        let raw_amount = gross_up_fee_amount(a, fee)?;
+        let d = self.d_from_xy(x, y)?;
+        let (reserve_in, reserve_out) = match direction {
+            SwapDirection::XToY => (x, y),
+            SwapDirection::YToX => (y, x),
+        };
+        let new_out = reserve_out.checked_sub(raw_amount).ok_or(CurveError::Overflow)?;
+        let new_in = self.y_from_x_and_d(new_out as u128, d)?;
+        // Round the input up in the pool's favour
+        let amount = u64::try_from(
+            new_in
+                .checked_sub(reserve_in as u128).ok_or(CurveError::Overflow)?
+                .checked_add(1).ok_or(CurveError::Overflow)?
+        ).map_err(|_| CurveError::Overflow)?;
+        Ok((amount, raw_amount - a))
+    }
+
+    // Get the marginal price of the input token in the output token as Q64.64 from the invariant's partial derivatives
+    // P = (4·Ann·X·Y / D + D² / In) / (4·Ann·X·Y / D + D² / Out)
+    fn spot_price(&self, x: u64, y: u64, direction: SwapDirection) -> Result<u128, CurveError> {
+        let d = self.d_from_xy(x, y)?;
+        let (reserve_in, reserve_out) = match direction {
+            SwapDirection::XToY => (x, y),
+            SwapDirection::YToX => (y, x),
+        };
+        let ann_xy = mul_div(x as u128, y as u128, d).ok_or(CurveError::Overflow)?
+            .checked_mul(self.ann()? * 4).ok_or(CurveError::Overflow)?;
+        let numerator = ann_xy
+            .checked_add(mul_div(d, d, reserve_in as u128).ok_or(CurveError::Overflow)?).ok_or(CurveError::Overflow)?;
+        let denominator = ann_xy
+            .checked_add(mul_div(d, d, reserve_out as u128).ok_or(CurveError::Overflow)?).ok_or(CurveError::Overflow)?;
+        mul_div(numerator, Q64, denominator).ok_or(CurveError::Overflow)
+    }
+
+    fn initial_liquidity(&self, x: u64, y: u64) -> Result<u64, CurveError> {
+        u64::try_from(self.d_from_xy(x, y)?).map_err(|_| CurveError::Overflow)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{ConstantProduct, Curve, StableSwap, SwapDirection};
+
+    #[test]
+    fn invariant() {
+        let curve = StableSwap { amp: 100 };
+        // A balanced pool has D equal to the sum of its reserves
+        assert_eq!(curve.d_from_xy(1_000_000, 1_000_000).unwrap(), 2_000_000);
+        let d = curve.d_from_xy(1_500_000, 500_000).unwrap();
+        assert!(d < 2_000_000);
+        assert!(curve.y_from_x_and_d(1_500_000, d).unwrap().abs_diff(500_000) <= 1);
+        assert!(StableSwap { amp: 0 }.d_from_xy(1, 1).is_err());
+    }
+
+    #[test]
+    fn stable_swap() {
+        let curve = StableSwap { amp: 100 };
+        let (amount_out, fee) = curve.swap_out(1_000_000, 1_000_000, 10_000, 0, SwapDirection::XToY).unwrap();
+        assert_eq!(fee, 0);
+        // Far deeper than the constant product curve near the peg
+        let (cp_amount_out, _) = ConstantProduct.swap_out(1_000_000, 1_000_000, 10_000, 0, SwapDirection::XToY).unwrap();
+        assert!(amount_out > cp_amount_out);
+        assert!((9_990..10_000).contains(&amount_out));
+    }
+
+    #[test]
+    fn stable_swap_exact_out() {
+        let curve = StableSwap { amp: 85 };
+        let (amount_in, fee) = curve.swap_in(2_000_000, 1_000_000, 50_000, 30, SwapDirection::YToX).unwrap();
+        assert_eq!(fee, 151);
+        let (amount_out, _) = curve.swap_out(2_000_000, 1_000_000, amount_in, 30, SwapDirection::YToX).unwrap();
+        assert!(amount_out >= 50_000);
+    }
+
+    #[test]
+    fn stable_spot_price() {
+        let curve = StableSwap { amp: 100 };
+        // A balanced pool trades at par
+        assert!(curve.spot_price(1_000_000, 1_000_000, SwapDirection::XToY).unwrap().abs_diff(1 << 64) <= 1);
+        // The scarcer token is worth more, but far closer to par than on the constant product curve
+        let price = curve.spot_price(1_500_000, 500_000, SwapDirection::YToX).unwrap();
+        let cp_price = ConstantProduct.spot_price(1_500_000, 500_000, SwapDirection::YToX).unwrap();
+        assert!(price > 1 << 64 && price < cp_price);
+        // The marginal price matches a small swap
+        let (amount_out, _) = curve.swap_out(1_500_000, 500_000, 100, 0, SwapDirection::YToX).unwrap();
+        assert!(((price * 100) >> 64).abs_diff(amount_out as u128) <= 1);
+    }
+}
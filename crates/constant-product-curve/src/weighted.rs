@@ -0,0 +1,172 @@
+use crate::{
+    concentrated::Q64,
+    gross_up_fee_amount, split_fee_amount,
+    math::{exp_wad, ln_wad, mul_div, mul_div_ceil, mul_wad, pow_wad, WAD},
+    Curve, CurveError, SwapDirection,
+};
+
+// Weights are expressed in basis points of the pool and must sum to 10,000
+pub const MIN_WEIGHT: u64 = 100;
+pub const MAX_WEIGHT: u64 = 9_900;
+
+// Relative error bound of pow_wad, added to every power so rounding favours the pool
+const MAX_POW_RELATIVE_ERROR: i128 = 10_000;
+
+// Weighted constant product invariant for two tokens
+// K = X^Wx · Y^Wy, Wx + Wy = 1
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Weighted {
+    pub weight_x: u64,
+}
+
+impl Weighted {
+    // Get the weights of the input and output tokens for a swap direction
+    #[inline]
+    fn weights(&self, direction: SwapDirection) -> Result<(u64, u64), CurveError> {
+        if self.weight_x < MIN_WEIGHT || self.weight_x > MAX_WEIGHT {
+            return Err(CurveError::InvalidParams);
+        }
+        let weight_y = 10_000 - self.weight_x;
+        Ok(match direction {
+            SwapDirection::XToY => (self.weight_x, weight_y),
+            SwapDirection::YToX => (weight_y, self.weight_x),
+        })
+    }
+
+    // Order the reserves as input and output for a swap direction, neither of which can be empty
+    #[inline]
+    fn reserves(x: u64, y: u64, direction: SwapDirection) -> Result<(u64, u64), CurveError> {
+        if x == 0 || y == 0 {
+            return Err(CurveError::ZeroAmount);
+        }
+        Ok(match direction {
+            SwapDirection::XToY => (x, y),
+            SwapDirection::YToX => (y, x),
+        })
+    }
+
+    // Bᴱ rounded up by the maximum error of pow_wad
+    #[inline]
+    fn pow_up(base: i128, exponent: i128) -> Result<i128, CurveError> {
+        let pow = pow_wad(base, exponent).ok_or(CurveError::Overflow)?;
+        pow.checked_add(mul_wad(pow, MAX_POW_RELATIVE_ERROR).ok_or(CurveError::Overflow)? + 1)
+            .ok_or(CurveError::Overflow)
+    }
+}
+
+impl Curve for Weighted {
+    // Calculate the amount out from swapping in amount A
+    // ΔOut = Out · (1 - (In / (In + A))^(Win / Wout))
+    fn swap_out(&self, x: u64, y: u64, a: u64, fee: u16, direction: SwapDirection) -> Result<(u64, u64), CurveError> {
+        let (weight_in, weight_out) = self.weights(direction)?;
+        let (reserve_in, reserve_out) = Self::reserves(x, y, direction)?;
+        // Round the base up and the exponent down, so the power and remaining output are never underestimated
+        let base = mul_div_ceil(reserve_in as u128, WAD as u128, reserve_in as u128 + a as u128).ok_or(CurveError::Overflow)? as i128;
+        let exponent = weight_in as i128 * WAD / weight_out as i128;
+        let power = Self::pow_up(base, exponent)?.min(WAD);
+        let raw_amount = u64::try_from(
+            mul_div(reserve_out as u128, (WAD - power) as u128, WAD as u128).ok_or(CurveError::Overflow)?
+        ).map_err(|_| CurveError::Overflow)?;
+        split_fee_amount(raw_amount, fee)
+    }
+
+    // Calculate the amount in required to withdraw amount A out
+    // ΔIn = In · ((Out / (Out - A))^(Wout / Win) - 1)
+    fn swap_in(&self, x: u64, y: u64, a: u64, fee: u16, direction: SwapDirection) -> Result<(u64, u64), CurveError> {
+        let (weight_in, weight_out) = self.weights(direction)?;
+        let (reserve_in, reserve_out) = Self::reserves(x, y, direction)?;
+        let raw_amount = gross_up_fee_amount(a, fee)?;
+        let new_out = reserve_out.checked_sub(raw_amount).ok_or(CurveError::Overflow)?;
+        if new_out == 0 {
+            return Err(CurveError::Overflow);
+        }
+        // Round the base and exponent up, so the required input is never underestimated
+        let base = mul_div_ceil(reserve_out as u128, WAD as u128, new_out as u128).ok_or(CurveError::Overflow)?;
+        let base = i128::try_from(base).map_err(|_| CurveError::Overflow)?;
+        let exponent = (weight_out as u128 * WAD as u128).div_ceil(weight_in as u128) as i128;
+        let power = Self::pow_up(base, exponent)?;
+        let amount = u64::try_from(
+            mul_div_ceil(reserve_in as u128, (power - WAD) as u128, WAD as u128).ok_or(CurveError::Overflow)?
+        ).map_err(|_| CurveError::Overflow)?;
+        Ok((amount, raw_amount - a))
+    }
+
+    // Get the marginal price of the input token in the output token as Q64.64
+    // P = (Out / Wout) / (In / Win)
+    fn spot_price(&self, x: u64, y: u64, direction: SwapDirection) -> Result<u128, CurveError> {
+        let (weight_in, weight_out) = self.weights(direction)?;
+        let (reserve_in, reserve_out) = Self::reserves(x, y, direction)?;
+        mul_div(
+            reserve_out as u128 * weight_in as u128,
+            Q64,
+            reserve_in as u128 * weight_out as u128,
+        ).ok_or(CurveError::Overflow)
+    }
+
+    // Get liquidity token amount for the first deposit from the weighted geometric mean
+    // L = X^Wx · Y^Wy = e^(Wx·ln(X) + Wy·ln(Y))
+    fn initial_liquidity(&self, x: u64, y: u64) -> Result<u64, CurveError> {
+        let (weight_x, weight_y) = self.weights(SwapDirection::XToY)?;
+        if x == 0 || y == 0 {
+            return Err(CurveError::ZeroAmount);
+        }
+        let ln_x = ln_wad(x as i128 * WAD).ok_or(CurveError::Overflow)?;
+        let ln_y = ln_wad(y as i128 * WAD).ok_or(CurveError::Overflow)?;
+        let exponent = mul_wad(ln_x, weight_x as i128 * WAD / 10_000).ok_or(CurveError::Overflow)?
+            .checked_add(mul_wad(ln_y, weight_y as i128 * WAD / 10_000).ok_or(CurveError::Overflow)?)
+            .ok_or(CurveError::Overflow)?;
+        u64::try_from(exp_wad(exponent).ok_or(CurveError::Overflow)? / WAD).map_err(|_| CurveError::Overflow)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{ConstantProduct, Curve, SwapDirection, Weighted};
+
+    #[test]
+    fn weighted_swap() {
+        // An even pool matches the constant product curve
+        let curve = Weighted { weight_x: 5_000 };
+        let (amount_out, _) = curve.swap_out(1_000_000, 1_000_000, 10_000, 0, SwapDirection::XToY).unwrap();
+        let (cp_amount_out, _) = ConstantProduct.swap_out(1_000_000, 1_000_000, 10_000, 0, SwapDirection::XToY).unwrap();
+        assert!(amount_out.abs_diff(cp_amount_out) <= 1);
+
+        // An 80/20 pool prices X at four times Y for equal balances
+        let curve = Weighted { weight_x: 8_000 };
+        let (amount_out, fee) = curve.swap_out(1_000_000, 1_000_000, 10_000, 30, SwapDirection::XToY).unwrap();
+        assert_eq!(amount_out + fee, 39_019);
+        let (amount_out, _) = curve.swap_out(1_000_000, 1_000_000, 10_000, 0, SwapDirection::YToX).unwrap();
+        assert_eq!(amount_out, 2_484);
+        assert!(Weighted { weight_x: 50 }.swap_out(1, 1, 1, 0, SwapDirection::XToY).is_err());
+    }
+
+    #[test]
+    fn weighted_swap_exact_out() {
+        let curve = Weighted { weight_x: 8_000 };
+        for direction in [SwapDirection::XToY, SwapDirection::YToX] {
+            let (amount_in, _) = curve.swap_in(4_000_000, 1_000_000, 25_000, 30, direction).unwrap();
+            let (amount_out, _) = curve.swap_out(4_000_000, 1_000_000, amount_in, 30, direction).unwrap();
+            assert!(amount_out >= 25_000);
+        }
+    }
+
+    #[test]
+    fn weighted_spot_price() {
+        // An even pool prices like the constant product curve
+        let even = Weighted { weight_x: 5_000 }.spot_price(1_000_000, 2_000_000, SwapDirection::XToY).unwrap();
+        assert_eq!(even, ConstantProduct.spot_price(1_000_000, 2_000_000, SwapDirection::XToY).unwrap());
+        assert_eq!(even, 2 << 64);
+        // An 80/20 pool of equal value holds four X for every Y
+        let curve = Weighted { weight_x: 8_000 };
+        assert_eq!(curve.spot_price(4_000_000, 1_000_000, SwapDirection::XToY).unwrap(), 1 << 64);
+        assert_eq!(curve.spot_price(4_000_000, 2_000_000, SwapDirection::YToX).unwrap(), 1 << 63);
+    }
+
+    #[test]
+    fn weighted_initial_liquidity() {
+        let curve = Weighted { weight_x: 8_000 };
+        assert!(curve.initial_liquidity(1_000_000, 1_000_000).unwrap().abs_diff(1_000_000) <= 1);
+        // 16^0.8 · 1^0.2 = 9.19
+        assert!(curve.initial_liquidity(16_000_000, 1_000_000).unwrap().abs_diff(9_189_586) <= 1);
+    }
+}
@@ -0,0 +1,39 @@
+use crate::{AmmError, Config};
+use solana_program::{
+    account_info::AccountInfo, entrypoint::ProgramResult, program_error::ProgramError, pubkey::Pubkey,
+};
+
+pub fn process(accounts: &[AccountInfo<'_>], _data: &[u8]) -> ProgramResult {
+    let [pending_authority, config] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    // Ensure user is signer
+    if !pending_authority.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    // Load our config, checking we own it
+    let mut config_account = Config::load_mut(config)?;
+
+    // Assert we aren't inside a flash loan or flash swap callback
+    if config_account.reentrancy_guard == 1 {
+        return Err(AmmError::Reentrancy.into());
+    }
+
+    // Assert signer is the proposed authority
+    if pending_authority.key.ne(&config_account.pending_authority) || pending_authority.key.eq(&Pubkey::default()) {
+        return Err(AmmError::WrongAuthority.into());
+    }
+
+    // Assert status is not set to revoked (2)
+    if config_account.locked == 2 {
+        return Err(AmmError::AuthorityRevoked.into());
+    }
+
+    // Hand over the authority
+    config_account.authority = config_account.pending_authority;
+    config_account.pending_authority = Pubkey::default();
+
+    Ok(())
+}
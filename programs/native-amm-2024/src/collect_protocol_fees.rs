@@ -0,0 +1,54 @@
+use crate::{utils::{check_eq_program_derived_address_with_bump, check_token_program}, AmmError, Config};
+use solana_program::{
+    account_info::AccountInfo,
+    entrypoint::ProgramResult,
+    program_error::ProgramError,
+};
+
+/// Pay the protocol fees owed by a pool out to its treasury
+pub fn process(accounts: &[AccountInfo<'_>], _data: &[u8]) -> ProgramResult {
+    let [treasury, mint_x, mint_y, treasury_x, treasury_y, vault_x, vault_y, config, token_program_x, token_program_y, remaining_accounts @ ..] =
+        accounts
+    else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    // Ensure treasury is signer
+    if !treasury.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    // Ensure correct TokenPrograms
+    check_token_program(token_program_x, mint_x)?;
+    check_token_program(token_program_y, mint_y)?;
+
+    // Load our config account, checking we own it
+    let config_account = Config::load(config)?;
+
+    // Assert we aren't inside a flash loan or flash swap callback
+    if config_account.reentrancy_guard == 1 {
+        return Err(AmmError::Reentrancy.into());
+    }
+
+    // Assert signer is the correct treasury
+    if treasury.key.ne(&config_account.treasury) {
+        return Err(AmmError::WrongTreasury.into());
+    }
+
+    // Check vault X
+    check_eq_program_derived_address_with_bump(
+        &[config_account.mint_x.as_ref(), config.key.as_ref(), &[config_account.x_bump]],
+        &crate::ID,
+        vault_x.key,
+    )?;
+
+    // Check vault Y
+    check_eq_program_derived_address_with_bump(
+        &[config_account.mint_y.as_ref(), config.key.as_ref(), &[config_account.y_bump]],
+        &crate::ID,
+        vault_y.key,
+    )?;
+
+    Config::perform_collect_protocol_fees(&config_account, token_program_x.key, token_program_y.key, treasury_x, treasury_y, vault_x, vault_y,
+        mint_x, mint_y, config, remaining_accounts)
+}
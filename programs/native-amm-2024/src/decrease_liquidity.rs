@@ -0,0 +1,40 @@
+use crate::{utils::{check_token_program, perform_basic_checks_with_no_lp}, Config, DecreaseLiquidity};
+use solana_program::{
+    account_info::AccountInfo,
+    entrypoint::ProgramResult,
+    program_error::ProgramError,
+};
+
+pub fn process(accounts: &[AccountInfo<'_>], data: &[u8]) -> ProgramResult {
+    let DecreaseLiquidity {
+        liquidity,
+        min_x,
+        min_y,
+        expiration,
+    } = DecreaseLiquidity::try_from(data)?;
+
+    let [owner, mint_x, mint_y, owner_x, owner_y, vault_x, vault_y, config, position, tick_array_lower, tick_array_upper, token_program_x, token_program_y, remaining_accounts @ ..] =
+        accounts
+    else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    // Ensure owner is signer
+    if !owner.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    // Ensure correct TokenPrograms
+    check_token_program(token_program_x, mint_x)?;
+    check_token_program(token_program_y, mint_y)?;
+
+    // Load Config
+    let config_account = Config::load(config)?;
+
+    // Perform Basic Checks
+    perform_basic_checks_with_no_lp(&config_account, expiration, config, vault_x, vault_y)?;
+
+    // Remove liquidity from the position and collect its fees
+    Config::perform_decrease_liquidity(liquidity.into(), min_x, min_y, &config_account, token_program_x.key, token_program_y.key, owner_x,
+        owner_y, vault_x, vault_y, mint_x, mint_y, config, position, tick_array_lower, tick_array_upper, owner, remaining_accounts)
+}
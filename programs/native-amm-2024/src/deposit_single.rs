@@ -0,0 +1,40 @@
+use crate::{utils::{check_token_program, perform_basic_checks}, Config, DepositSingle};
+use solana_program::{
+    account_info::AccountInfo,
+    entrypoint::ProgramResult,
+    program_error::ProgramError,
+};
+
+/// Deposit only X or Y, swapping part of it through the pool to mint LP at the pool's ratio
+pub fn process(accounts: &[AccountInfo<'_>], data: &[u8]) -> ProgramResult {
+    let DepositSingle {
+        amount,
+        min_lp,
+        expiration,
+    } = DepositSingle::try_from(data)?;
+
+    let [user, mint_from, mint_lp, user_from, user_lp, vault_x, vault_y, config, token_program_from, token_program, remaining_accounts @ ..] =
+        accounts
+    else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    // Ensure user is signer
+    if !user.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    // Ensure correct TokenPrograms
+    check_token_program(token_program_from, mint_from)?;
+    check_token_program(token_program, mint_lp)?;
+
+    // Load Config
+    let config_account = Config::load(config)?;
+
+    // Perform Basic Checks
+    perform_basic_checks(&config_account, expiration, config, mint_lp, vault_x, vault_y)?;
+
+    // Perform User Deposit
+    Config::perform_user_deposit_single(amount, min_lp, &config_account, token_program_from.key, token_program.key, user_from,
+        user_lp, vault_x, vault_y, mint_from, mint_lp, config, user, remaining_accounts)
+}
@@ -0,0 +1,78 @@
+use constant_product_curve::CurveError;
+use solana_program::program_error::ProgramError;
+
+#[repr(u32)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AmmError {
+    // Transaction landed after its expiration
+    Expired,
+    // Amounts fell outside the caller's minimum or maximum bounds
+    SlippageExceeded,
+    // Pool is locked by its authority
+    PoolLocked,
+    // Pool authority has been renounced
+    AuthorityRevoked,
+    // Signer is not the authority the pool expects
+    WrongAuthority,
+    // Signer is not the pool's treasury
+    WrongTreasury,
+    // Account is not owned by this program or the expected token program
+    InvalidAccountOwner,
+    // Account does not match its program derived address
+    InvalidPda,
+    // Token program is neither Token nor Token-2022
+    InvalidTokenProgram,
+    // Fee is not below 100%, or protocol share is above 100%
+    InvalidFee,
+    // Fee change is larger than the pool's maximum step
+    FeeStepExceeded,
+    // Fee was updated less than the pool's minimum interval ago
+    FeeUpdateTooSoon,
+    // Lock state is neither unlocked (0) nor locked (1)
+    InvalidLockState,
+    // Pool is not in the state this instruction requires
+    InvalidPoolState,
+    // Curve parameters are out of range for the curve type
+    InvalidCurveParams,
+    // Instruction is not supported by the pool's curve type
+    WrongCurveType,
+    // Tick is out of range or not aligned to the tick spacing
+    InvalidTick,
+    // Tick array belongs to another pool or doesn't cover the ticks required
+    InvalidTickArray,
+    // Position belongs to another pool or owner
+    InvalidPosition,
+    // Concentrated liquidity ran out before the swap completed
+    InsufficientLiquidity,
+    // Account is not a pool config
+    InvalidDiscriminator,
+    // Pool config uses an older layout and must be migrated first
+    ConfigNeedsMigration,
+    // Observations account belongs to another pool or is malformed
+    InvalidObservations,
+    // Observation cardinality can only grow
+    InvalidCardinality,
+    // Flash loan was not repaid with its fee before the instruction ended
+    FlashLoanNotRepaid,
+    // Vault reserves fell short of the curve after a flash swap's callback
+    InvariantViolated,
+    // Pool reserves or deposit amounts are zero
+    EmptyPool,
+    // Pool was entered again while a flash loan or flash swap callback held control
+    Reentrancy,
+}
+
+impl From<AmmError> for ProgramError {
+    fn from(e: AmmError) -> Self {
+        ProgramError::Custom(e as u32)
+    }
+}
+
+// Surface an empty pool or zero amount from the curve math as its own error rather than an overflow
+pub fn curve_error(e: CurveError) -> ProgramError {
+    match e {
+        CurveError::Overflow => ProgramError::ArithmeticOverflow,
+        CurveError::ZeroAmount => AmmError::EmptyPool.into(),
+        CurveError::InvalidParams => AmmError::InvalidCurveParams.into(),
+    }
+}
@@ -0,0 +1,110 @@
+use bytemuck::{bytes_of, Pod, Zeroable};
+use native_amm_macros::TryFromBytes;
+use solana_program::{log::sol_log_data, program_error::ProgramError, pubkey::Pubkey};
+
+// Events are logged with sol_log_data as a single field, a one byte discriminator followed by the event.
+// Indexers read them back from the base64 "Program data:" log lines with AmmEvent::try_from
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum AmmEvent {
+    Initialize(InitializeEvent),
+    Swap(SwapEvent),
+    Deposit(DepositEvent),
+    Withdraw(WithdrawEvent),
+    Lock(LockEvent),
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, PartialEq, Eq, Pod, Zeroable, TryFromBytes)]
+pub struct InitializeEvent {
+    pub pool: Pubkey,
+    pub user: Pubkey, // Initializer who paid for the pool
+    pub authority: Pubkey,
+    pub mint_x: Pubkey,
+    pub mint_y: Pubkey,
+    pub fee: u16,
+    pub protocol_fee: u16,
+    pub curve_type: u8,
+    pub padding: [u8; 1],
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, PartialEq, Eq, Pod, Zeroable, TryFromBytes)]
+pub struct SwapEvent {
+    pub pool: Pubkey,
+    pub user: Pubkey,
+    pub amount_in: u64,  // Tokens sent by the user, before transfer fees
+    pub amount_out: u64, // Tokens sent by the vault, before transfer fees
+    pub fee: u64,        // Pool fee, in the output token, or the input token for concentrated pools
+    pub reserve_x: u64,  // X reserves after the swap
+    pub reserve_y: u64,  // Y reserves after the swap
+    pub is_x: u8,        // 1 when X was swapped for Y, 0 when Y was swapped for X
+    pub padding: [u8; 7],
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, PartialEq, Eq, Pod, Zeroable, TryFromBytes)]
+pub struct DepositEvent {
+    pub pool: Pubkey,
+    pub user: Pubkey,
+    pub amount_x: u64,  // X sent by the user, before transfer fees
+    pub amount_y: u64,  // Y sent by the user, before transfer fees
+    pub lp: u64,        // LP tokens minted
+    pub reserve_x: u64, // X reserves after the deposit
+    pub reserve_y: u64, // Y reserves after the deposit
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, PartialEq, Eq, Pod, Zeroable, TryFromBytes)]
+pub struct WithdrawEvent {
+    pub pool: Pubkey,
+    pub user: Pubkey,
+    pub amount_x: u64,  // X sent by the vault, before transfer fees
+    pub amount_y: u64,  // Y sent by the vault, before transfer fees
+    pub lp: u64,        // LP tokens burned
+    pub reserve_x: u64, // X reserves after the withdrawal
+    pub reserve_y: u64, // Y reserves after the withdrawal
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, PartialEq, Eq, Pod, Zeroable, TryFromBytes)]
+pub struct LockEvent {
+    pub pool: Pubkey,
+    pub user: Pubkey, // Authority who changed the lock state
+    pub locked: u8,   // 1 when locked, 0 when unlocked
+}
+
+impl AmmEvent {
+    pub fn serialize(&self) -> Vec<u8> {
+        let (discriminator, event) = match self {
+            Self::Initialize(event) => (0, bytes_of(event)),
+            Self::Swap(event) => (1, bytes_of(event)),
+            Self::Deposit(event) => (2, bytes_of(event)),
+            Self::Withdraw(event) => (3, bytes_of(event)),
+            Self::Lock(event) => (4, bytes_of(event)),
+        };
+        [&[discriminator], event].concat()
+    }
+
+    pub fn emit(&self) {
+        sol_log_data(&[&self.serialize()]);
+    }
+}
+
+impl TryFrom<&[u8]> for AmmEvent {
+    type Error = ProgramError;
+
+    fn try_from(data: &[u8]) -> Result<Self, Self::Error> {
+        let (discriminator, event) = data
+            .split_first()
+            .ok_or(ProgramError::InvalidInstructionData)?;
+
+        match discriminator {
+            0 => Ok(Self::Initialize(InitializeEvent::try_from(event)?)),
+            1 => Ok(Self::Swap(SwapEvent::try_from(event)?)),
+            2 => Ok(Self::Deposit(DepositEvent::try_from(event)?)),
+            3 => Ok(Self::Withdraw(WithdrawEvent::try_from(event)?)),
+            4 => Ok(Self::Lock(LockEvent::try_from(event)?)),
+            _ => Err(ProgramError::InvalidInstructionData),
+        }
+    }
+}
@@ -0,0 +1,71 @@
+use crate::{
+    utils::{check_token_program, perform_basic_checks_with_no_lp},
+    AmmError, Config, FlashLoan,
+};
+use solana_program::{
+    account_info::AccountInfo,
+    entrypoint::ProgramResult,
+    program_error::ProgramError,
+};
+
+/// Lend tokens from a vault for the length of a callback, which must repay them with the flash fee
+pub fn process(accounts: &[AccountInfo<'_>], data: &[u8]) -> ProgramResult {
+    // Anything after the loan's arguments is passed on to the callback program
+    let (args, callback_data) = data
+        .split_at_checked(core::mem::size_of::<FlashLoan>())
+        .ok_or(ProgramError::InvalidInstructionData)?;
+
+    let FlashLoan {
+        amount,     // Amount of tokens we borrow
+        expiration, // Maximum time for which the loan is valid
+        is_x,       // Whether we borrow X or Y
+        ..
+    } = FlashLoan::try_from(args)?;
+
+    let [borrower, mint, borrower_to, vault_x, vault_y, config, token_program, callback_program, remaining_accounts @ ..] =
+        accounts
+    else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    // Ensure borrower is signer
+    if !borrower.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    // Assert we are using the correct TokenProgram
+    check_token_program(token_program, mint)?;
+
+    // Assert the callback doesn't call straight back into us
+    if callback_program.key.eq(&crate::ID) {
+        return Err(AmmError::Reentrancy.into());
+    }
+
+    // Load our config account
+    let config_account = Config::load(config)?;
+
+    // Perform basic checks
+    perform_basic_checks_with_no_lp(&config_account, expiration, config, vault_x, vault_y)?;
+
+    // Lend from the vault of the mint we were asked for
+    let (pool_mint, vault) = match is_x == 1 {
+        true => (config_account.mint_x, vault_x),
+        false => (config_account.mint_y, vault_y),
+    };
+    if mint.key.ne(&pool_mint) {
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    Config::perform_flash_loan(
+        &config_account,
+        token_program.key,
+        amount,
+        mint,
+        vault,
+        borrower_to,
+        config,
+        callback_program,
+        callback_data,
+        remaining_accounts,
+    )
+}
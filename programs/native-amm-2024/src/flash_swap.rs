@@ -0,0 +1,65 @@
+use crate::{utils::{check_token_program, perform_basic_checks_with_no_lp}, AmmError, Config, Swap};
+use solana_program::{
+    account_info::AccountInfo,
+    entrypoint::ProgramResult,
+    program_error::ProgramError,
+};
+
+/// Swap with the output paid out first, leaving a callback to pay the input before the reserves are checked
+pub fn process(accounts: &[AccountInfo<'_>], data: &[u8]) -> ProgramResult {
+    // Anything after the swap's arguments is passed on to the callback program
+    let (args, callback_data) = data
+        .split_at_checked(core::mem::size_of::<Swap>())
+        .ok_or(ProgramError::InvalidInstructionData)?;
+
+    let Swap {
+        amount,     // Amount of tokens the callback will deposit
+        min,        // Minimum amount of tokens I'd be willing to withdraw
+        expiration, // Maximum time for which the swap is valid
+    } = Swap::try_from(args)?;
+
+    // User from is only read for the input mint, the callback pays the vault however it likes
+    let [user, mint_x, mint_y, user_from, user_to, vault_x, vault_y, config, token_program_x, token_program_y, callback_program, remaining_accounts @ ..] =
+        accounts
+    else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    // Ensure user is signer
+    if !user.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    // Assert we are using the correct TokenPrograms
+    check_token_program(token_program_x, mint_x)?;
+    check_token_program(token_program_y, mint_y)?;
+
+    // Assert the callback doesn't call straight back into us
+    if callback_program.key.eq(&crate::ID) {
+        return Err(AmmError::Reentrancy.into());
+    }
+
+    // Load our config account
+    let config_account = Config::load(config)?;
+
+    // Perform basic checks
+    perform_basic_checks_with_no_lp(&config_account, expiration, config, vault_x, vault_y)?;
+
+    Config::perform_swap(
+        &config_account,
+        token_program_x.key,
+        token_program_y.key,
+        amount,
+        min,
+        mint_x,
+        mint_y,
+        vault_x,
+        vault_y,
+        user,
+        user_from,
+        user_to,
+        config,
+        Some((callback_program, callback_data)),
+        remaining_accounts,
+    )
+}
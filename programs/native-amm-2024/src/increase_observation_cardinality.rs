@@ -0,0 +1,90 @@
+use crate::{
+    state::{Observation, Observations, OBSERVATIONS_DISCRIMINATOR},
+    utils::{check_eq_program_derived_address_and_get_bump, check_eq_program_derived_address_with_bump, create_program_account, resize_program_account},
+    AmmError, Config, IncreaseObservationCardinality,
+};
+use core::mem;
+use solana_program::{
+    account_info::AccountInfo,
+    entrypoint::ProgramResult,
+    program_error::ProgramError,
+};
+
+/// Create a pool's observations account or grow the number of observations it holds
+pub fn process(accounts: &[AccountInfo<'_>], data: &[u8]) -> ProgramResult {
+    let IncreaseObservationCardinality { cardinality_next } = IncreaseObservationCardinality::try_from(data)?;
+
+    let [payer, config, observations, _system_program] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    // Ensure payer is signer
+    if !payer.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    // Load our config account, checking we own it
+    let config_account = Config::load(config)?;
+
+    if observations.owner.ne(&crate::ID) {
+        if cardinality_next == 0 {
+            return Err(AmmError::InvalidCardinality.into());
+        }
+
+        // Get the bump and check the PDA
+        let bump = check_eq_program_derived_address_and_get_bump(
+            &[b"observations", config.key.as_ref()],
+            &crate::ID,
+            observations.key,
+        )?;
+
+        // Create the observations account
+        create_program_account(
+            &[b"observations", config.key.as_ref(), &[bump]],
+            payer,
+            observations,
+            Observations::space(cardinality_next),
+        )?;
+
+        // Seed the ring with the pool's accumulators as of their last update
+        let mut data = observations.try_borrow_mut_data()?;
+        let (header, entries) = data.split_at_mut(mem::size_of::<Observations>());
+        *bytemuck::from_bytes_mut::<Observations>(header) = Observations {
+            discriminator: OBSERVATIONS_DISCRIMINATOR,
+            config: *config.key,
+            index: 0,
+            cardinality: 1,
+            cardinality_next,
+            bump,
+            padding: [0; 1],
+        };
+        bytemuck::cast_slice_mut::<u8, Observation>(entries)[0] = Observation {
+            timestamp: config_account.price_last_update,
+            price_x_cumulative: config_account.price_x_cumulative,
+            price_y_cumulative: config_account.price_y_cumulative,
+            liquidity_cumulative: config_account.liquidity_cumulative,
+        };
+    } else {
+        {
+            let (mut observations_account, _) = Observations::load_mut(observations, config.key)?;
+
+            // Check the PDA
+            check_eq_program_derived_address_with_bump(
+                &[b"observations", config.key.as_ref(), &[observations_account.bump]],
+                &crate::ID,
+                observations.key,
+            )?;
+
+            // Ensure the ring only grows
+            if cardinality_next <= observations_account.cardinality_next {
+                return Err(AmmError::InvalidCardinality.into());
+            }
+            observations_account.cardinality_next = cardinality_next;
+        }
+
+        // Allocate the new slots, which join the ring once it next wraps
+        resize_program_account(payer, observations, Observations::space(cardinality_next))?;
+    }
+
+    Ok(())
+}
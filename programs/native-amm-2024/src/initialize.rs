@@ -1,7 +1,10 @@
 use crate::{
-    utils::{check_eq_program_derived_address_and_get_bump, create_token_account, create_mint}, 
+    utils::{check_eq_program_derived_address_and_get_bump, check_token_program, create_token_account, create_mint, create_mint_with_metadata, get_mint_symbol}, 
+    AmmError,
+    AmmEvent,
     Config, 
-    Initialize
+    Initialize,
+    InitializeEvent,
 };
 use solana_program::{
     account_info::AccountInfo,
@@ -14,17 +17,36 @@ pub fn process(accounts: &[AccountInfo<'_>], data: &[u8]) -> ProgramResult {
     let Initialize {
         seed,
         fee,
+        protocol_fee,
         authority,
-        padding,
+        lp_decimals,
+        curve_type,
+        fee_max_step,
+        uri,
+        curve_params,
+        treasury,
+        fee_update_interval,
+        flash_fee,
+        ..
     } = Initialize::try_from(data)?;
 
-    let [initializer, mint_x, mint_y, mint_lp, vault_x, vault_y, config, token_program, _system_program] =
+    // Read the zero-padded LP metadata URI
+    let uri = String::from_utf8(uri.into_iter().take_while(|b| *b != 0).collect())
+        .map_err(|_| ProgramError::InvalidInstructionData)?;
+
+    let [initializer, mint_x, mint_y, mint_lp, vault_x, vault_y, config, token_program_x, token_program_y, token_program, _system_program] =
         accounts
     else {
         return Err(ProgramError::NotEnoughAccountKeys);
     };
 
     // Get the bump and check PDAs
+    let config_bump = check_eq_program_derived_address_and_get_bump(
+        &[b"config", seed.to_le_bytes().as_ref()],
+        &crate::ID,
+        config.key,
+    )?;
+
     let x_bump = check_eq_program_derived_address_and_get_bump(
         &[mint_x.key.as_ref(), config.key.as_ref()],
         &crate::ID,
@@ -47,7 +69,15 @@ pub fn process(accounts: &[AccountInfo<'_>], data: &[u8]) -> ProgramResult {
     Config::initialize(
         seed,
         authority,
+        treasury,
         fee,
+        protocol_fee,
+        fee_max_step,
+        fee_update_interval,
+        flash_fee,
+        curve_type,
+        curve_params,
+        config_bump,
         lp_bump,
         x_bump,
         y_bump,
@@ -57,12 +87,19 @@ pub fn process(accounts: &[AccountInfo<'_>], data: &[u8]) -> ProgramResult {
         config,
     )?;
 
-    assert_eq!(spl_token::ID, *token_program.key);
+    // Ensure each mint is owned by its token program
+    check_token_program(token_program_x, mint_x)?;
+    check_token_program(token_program_y, mint_y)?;
+
+    // Ensure the LP mint is created under a valid token program
+    if token_program.key.ne(&spl_token::ID) && token_program.key.ne(&spl_token_2022::ID) {
+        return Err(AmmError::InvalidTokenProgram.into());
+    }
 
     // Create the x_vault
     create_token_account(
         &[mint_x.key.as_ref(), config.key.as_ref(), &[x_bump]],
-        token_program.key,
+        token_program_x.key,
         initializer,
         vault_x,
         mint_x,
@@ -72,19 +109,53 @@ pub fn process(accounts: &[AccountInfo<'_>], data: &[u8]) -> ProgramResult {
     // Create the y_vault
     create_token_account(
         &[mint_y.key.as_ref(), config.key.as_ref(), &[y_bump]],
-        token_program.key,
+        token_program_y.key,
         initializer,
         vault_y,
         mint_y,
         config,
     )?;
 
-    // Create the lp_mint
-    create_mint(
-        &[config.key.as_ref(), &[lp_bump]], 
-        token_program.key,
-        initializer,
-        mint_lp,
-        config,
-    )
+    // Create the lp_mint, with on-chain metadata when it lives under Token-2022
+    if token_program.key.eq(&spl_token_2022::ID) {
+        let symbol_x = get_mint_symbol(mint_x)?;
+        let symbol_y = get_mint_symbol(mint_y)?;
+
+        create_mint_with_metadata(
+            &[config.key.as_ref(), &[lp_bump]],
+            &[b"config", seed.to_le_bytes().as_ref(), &[config_bump]],
+            token_program.key,
+            initializer,
+            mint_lp,
+            config,
+            lp_decimals,
+            format!("{}-{} LP", symbol_x, symbol_y),
+            format!("{}-{}", symbol_x, symbol_y),
+            uri,
+        )?;
+    } else {
+        create_mint(
+            &[config.key.as_ref(), &[lp_bump]], 
+            token_program.key,
+            initializer,
+            mint_lp,
+            config,
+            lp_decimals,
+        )?;
+    }
+
+    AmmEvent::Initialize(InitializeEvent {
+        pool: *config.key,
+        user: *initializer.key,
+        authority,
+        mint_x: *mint_x.key,
+        mint_y: *mint_y.key,
+        fee,
+        protocol_fee,
+        curve_type,
+        padding: [0; 1],
+    })
+    .emit();
+
+    Ok(())
 }
@@ -0,0 +1,58 @@
+use crate::{
+    state::{load_account_mut, CurveType, TickArray},
+    utils::{check_eq_program_derived_address_and_get_bump, create_program_account},
+    AmmError, Config, InitializeTickArray,
+};
+use constant_product_curve::concentrated::{MAX_TICK, MIN_TICK};
+use solana_program::{
+    account_info::AccountInfo,
+    entrypoint::ProgramResult,
+    program_error::ProgramError,
+};
+
+/// Create an empty tick array for a concentrated liquidity pool
+pub fn process(accounts: &[AccountInfo<'_>], data: &[u8]) -> ProgramResult {
+    let InitializeTickArray { start_tick_index } = InitializeTickArray::try_from(data)?;
+
+    let [payer, config, tick_array, _system_program] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    // Load our config account, checking we own it
+    let config_account = Config::load(config)?;
+
+    // Ensure the pool uses concentrated liquidity
+    if config_account.curve_type != CurveType::ConcentratedLiquidity as u8 {
+        return Err(AmmError::WrongCurveType.into());
+    }
+
+    // Ensure the array starts on a boundary and holds valid ticks
+    if TickArray::start_tick_index(start_tick_index, config_account.tick_spacing) != start_tick_index
+        || start_tick_index < TickArray::start_tick_index(MIN_TICK, config_account.tick_spacing)
+        || start_tick_index > MAX_TICK
+    {
+        return Err(AmmError::InvalidTick.into());
+    }
+
+    // Get the bump and check the PDA
+    let bump = check_eq_program_derived_address_and_get_bump(
+        &[b"tick_array", config.key.as_ref(), start_tick_index.to_le_bytes().as_ref()],
+        &crate::ID,
+        tick_array.key,
+    )?;
+
+    // Create the tick array
+    create_program_account(
+        &[b"tick_array", config.key.as_ref(), start_tick_index.to_le_bytes().as_ref(), &[bump]],
+        payer,
+        tick_array,
+        core::mem::size_of::<TickArray>(),
+    )?;
+
+    let mut tick_array_account = load_account_mut::<TickArray>(tick_array)?;
+    tick_array_account.config = *config.key;
+    tick_array_account.start_tick_index = start_tick_index;
+    tick_array_account.bump = bump;
+
+    Ok(())
+}
@@ -1,6 +1,7 @@
 use bytemuck::{Pod, Zeroable};
 use native_amm_macros::TryFromBytes;
 use solana_program::{program_error::ProgramError, pubkey::Pubkey};
+use spl_pod::primitives::PodU128;
 
 #[derive(Clone)]
 pub enum AMMInstructions {
@@ -9,6 +10,25 @@ pub enum AMMInstructions {
     Withdraw,
     Swap,
     Lock,
+    SwapExactOut,
+    InitializeTickArray,
+    OpenPosition,
+    IncreaseLiquidity,
+    DecreaseLiquidity,
+    CollectProtocolFees,
+    UpdateFee,
+    ProposeAuthority,
+    AcceptAuthority,
+    RenounceAuthority,
+    MigrateConfig,
+    Observe,
+    IncreaseObservationCardinality,
+    Quote,
+    FlashLoan,
+    FlashSwap,
+    DepositSingle,
+    WithdrawSingle,
+    RouteSwap,
 }
 
 impl TryFrom<&u8> for AMMInstructions {
@@ -21,6 +41,25 @@ impl TryFrom<&u8> for AMMInstructions {
             2 => Ok(Self::Withdraw),
             3 => Ok(Self::Swap),
             4 => Ok(Self::Lock),
+            5 => Ok(Self::SwapExactOut),
+            6 => Ok(Self::InitializeTickArray),
+            7 => Ok(Self::OpenPosition),
+            8 => Ok(Self::IncreaseLiquidity),
+            9 => Ok(Self::DecreaseLiquidity),
+            10 => Ok(Self::CollectProtocolFees),
+            11 => Ok(Self::UpdateFee),
+            12 => Ok(Self::ProposeAuthority),
+            13 => Ok(Self::AcceptAuthority),
+            14 => Ok(Self::RenounceAuthority),
+            15 => Ok(Self::MigrateConfig),
+            16 => Ok(Self::Observe),
+            17 => Ok(Self::IncreaseObservationCardinality),
+            18 => Ok(Self::Quote),
+            19 => Ok(Self::FlashLoan),
+            20 => Ok(Self::FlashSwap),
+            21 => Ok(Self::DepositSingle),
+            22 => Ok(Self::WithdrawSingle),
+            23 => Ok(Self::RouteSwap),
             _ => Err(ProgramError::InvalidInstructionData),
         }
     }
@@ -40,7 +79,16 @@ impl AMMInstructions {
 pub struct Initialize {
     pub seed: u64,
     pub fee: u16,
+    pub protocol_fee: u16, // Share of the swap fee owed to the treasury, in basis points
     pub authority: Pubkey,
+    pub lp_decimals: u8,
+    pub curve_type: u8,
+    pub fee_max_step: u16, // Largest change to the fee allowed in a single UpdateFee, in basis points
+    pub uri: [u8; 128], // LP metadata URI, zero-padded. Only used for Token-2022 LP mints
+    pub curve_params: [u8; 32],
+    pub treasury: Pubkey, // Authority allowed to collect protocol fees
+    pub fee_update_interval: i64, // Minimum seconds between fee updates
+    pub flash_fee: u16, // Fee on flash loans in basis points
     pub padding: [u8; 6],
 }
 
@@ -69,3 +117,106 @@ pub struct Swap {
     pub min: u64,    // Minimum amount of tokens I'd be willing to withdraw
     pub expiration: i64,
 }
+
+#[repr(C)]
+#[derive(Clone, Copy, PartialEq, Eq, Pod, Zeroable, TryFromBytes)]
+pub struct SwapExactOut {
+    pub amount_out: u64, // Amount of tokens we want to receive
+    pub max_in: u64,     // Maximum amount of tokens I'd be willing to deposit
+    pub expiration: i64,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, PartialEq, Eq, Pod, Zeroable, TryFromBytes)]
+pub struct InitializeTickArray {
+    pub start_tick_index: i32, // First tick of the array, a multiple of the tick spacing times TICK_ARRAY_SIZE
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, PartialEq, Eq, Pod, Zeroable, TryFromBytes)]
+pub struct OpenPosition {
+    pub tick_lower: i32, // Lowest tick of the position's price range
+    pub tick_upper: i32, // Tick above the position's price range
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, PartialEq, Pod, Zeroable, TryFromBytes)]
+pub struct IncreaseLiquidity {
+    pub liquidity: PodU128, // Amount of liquidity to add to the position
+    pub max_x: u64,         // Max amount of X we are willing to deposit
+    pub max_y: u64,         // Max amount of Y we are willing to deposit
+    pub expiration: i64,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, PartialEq, Pod, Zeroable, TryFromBytes)]
+pub struct DecreaseLiquidity {
+    pub liquidity: PodU128, // Amount of liquidity to remove from the position, zero to only collect fees
+    pub min_x: u64,         // Min amount of X we are willing to withdraw, fees included
+    pub min_y: u64,         // Min amount of Y we are willing to withdraw, fees included
+    pub expiration: i64,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, PartialEq, Eq, Pod, Zeroable, TryFromBytes)]
+pub struct UpdateFee {
+    pub fee: u16, // New swap fee in basis points
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, PartialEq, Eq, Pod, Zeroable, TryFromBytes)]
+pub struct ProposeAuthority {
+    pub new_authority: Pubkey, // Authority that must accept the transfer, or the default pubkey to cancel
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, PartialEq, Eq, Pod, Zeroable, TryFromBytes)]
+pub struct IncreaseObservationCardinality {
+    pub cardinality_next: u16, // Number of observations the pool should be able to hold
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, PartialEq, Eq, Pod, Zeroable, TryFromBytes)]
+pub struct Quote {
+    pub amount: u64, // Amount in for swaps, amount out for exact out swaps, LP tokens for deposits and withdrawals
+    pub max_x: u64,  // X offered to an empty pool's first deposit
+    pub max_y: u64,  // Y offered to an empty pool's first deposit
+    pub kind: u8,    // 0 swap, 1 swap exact out, 2 deposit, 3 withdraw
+    pub is_x: u8,    // 1 to swap X for Y, 0 to swap Y for X
+    pub padding: [u8; 6],
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, PartialEq, Eq, Pod, Zeroable, TryFromBytes)]
+pub struct FlashLoan {
+    pub amount: u64, // Amount of tokens to borrow
+    pub expiration: i64,
+    pub is_x: u8, // 1 to borrow X, 0 to borrow Y
+    pub padding: [u8; 7],
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, PartialEq, Eq, Pod, Zeroable, TryFromBytes)]
+pub struct DepositSingle {
+    pub amount: u64, // Amount of X or Y to deposit
+    pub min_lp: u64, // Min amount of LP token we are willing to claim
+    pub expiration: i64,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, PartialEq, Eq, Pod, Zeroable, TryFromBytes)]
+pub struct WithdrawSingle {
+    pub amount: u64,  // Amount of LP token to burn
+    pub min_out: u64, // Min amount of X or Y we are willing to withdraw
+    pub expiration: i64,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, PartialEq, Eq, Pod, Zeroable, TryFromBytes)]
+pub struct RouteSwap {
+    pub amount: u64,  // Amount of tokens we deposit into the first pool
+    pub min_out: u64, // Minimum amount of tokens I'd be willing to withdraw from the last pool
+    pub expiration: i64,
+    pub hops: u8, // Number of pools on the route
+    pub padding: [u8; 7],
+}
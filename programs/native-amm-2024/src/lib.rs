@@ -1,3 +1,9 @@
+mod errors;
+pub use errors::*;
+
+mod events;
+pub use events::*;
+
 mod instructions;
 use instructions::*;
 
@@ -8,12 +14,31 @@ use state::*;
 #[cfg(test)]
 mod tests;
 
+mod accept_authority;
+mod collect_protocol_fees;
+mod decrease_liquidity;
 mod deposit;
+mod deposit_single;
+mod flash_loan;
+mod flash_swap;
+mod increase_liquidity;
+mod increase_observation_cardinality;
 mod initialize;
+mod initialize_tick_array;
 mod lock;
+mod migrate_config;
+mod observe;
+mod open_position;
+mod propose_authority;
+mod quote;
+mod renounce_authority;
+mod route_swap;
 mod swap;
+mod swap_exact_out;
+mod update_fee;
 mod utils;
 mod withdraw;
+mod withdraw_single;
 
 use solana_program::{
     account_info::AccountInfo, entrypoint, entrypoint::ProgramResult, program_error::ProgramError,
@@ -43,5 +68,24 @@ pub fn process_instruction(
         AMMInstructions::Withdraw => withdraw::process(accounts, data),
         AMMInstructions::Swap => swap::process(accounts, data),
         AMMInstructions::Lock => lock::process(accounts, data),
+        AMMInstructions::SwapExactOut => swap_exact_out::process(accounts, data),
+        AMMInstructions::InitializeTickArray => initialize_tick_array::process(accounts, data),
+        AMMInstructions::OpenPosition => open_position::process(accounts, data),
+        AMMInstructions::IncreaseLiquidity => increase_liquidity::process(accounts, data),
+        AMMInstructions::DecreaseLiquidity => decrease_liquidity::process(accounts, data),
+        AMMInstructions::CollectProtocolFees => collect_protocol_fees::process(accounts, data),
+        AMMInstructions::UpdateFee => update_fee::process(accounts, data),
+        AMMInstructions::ProposeAuthority => propose_authority::process(accounts, data),
+        AMMInstructions::AcceptAuthority => accept_authority::process(accounts, data),
+        AMMInstructions::RenounceAuthority => renounce_authority::process(accounts, data),
+        AMMInstructions::MigrateConfig => migrate_config::process(accounts, data),
+        AMMInstructions::Observe => observe::process(accounts, data),
+        AMMInstructions::IncreaseObservationCardinality => increase_observation_cardinality::process(accounts, data),
+        AMMInstructions::Quote => quote::process(accounts, data),
+        AMMInstructions::FlashLoan => flash_loan::process(accounts, data),
+        AMMInstructions::FlashSwap => flash_swap::process(accounts, data),
+        AMMInstructions::DepositSingle => deposit_single::process(accounts, data),
+        AMMInstructions::WithdrawSingle => withdraw_single::process(accounts, data),
+        AMMInstructions::RouteSwap => route_swap::process(accounts, data),
     }
 }
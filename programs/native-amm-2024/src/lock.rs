@@ -1,4 +1,4 @@
-use crate::{Config, Withdraw};
+use crate::{AmmError, AmmEvent, Config, LockEvent};
 use solana_program::{
     account_info::AccountInfo, entrypoint::ProgramResult, program_error::ProgramError,
 };
@@ -9,17 +9,27 @@ pub fn process(accounts: &[AccountInfo<'_>], data: &[u8]) -> ProgramResult {
     };
 
     // Ensure user is signer
-    assert!(authority.is_signer);
+    if !authority.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
 
-    // Assert we own config
-    assert_eq!(config.owner, &crate::ID);
-    let mut config_account = Config::try_from(config.data.borrow_mut().as_ref())?;
+    // Load our config, checking we own it
+    let mut config_account = Config::load_mut(config)?;
+
+    // Assert we aren't inside a flash loan or flash swap callback
+    if config_account.reentrancy_guard == 1 {
+        return Err(AmmError::Reentrancy.into());
+    }
 
     // Assert signer is the correct authority
-    assert_eq!(authority.key, &config_account.authority);
+    if authority.key.ne(&config_account.authority) {
+        return Err(AmmError::WrongAuthority.into());
+    }
 
     // Assert status is not set to revoked (2)
-    assert_ne!(config_account.locked, 2);
+    if config_account.locked == 2 {
+        return Err(AmmError::AuthorityRevoked.into());
+    }
 
     // Get the first byte of our IX data
     let (state, _) = data
@@ -27,10 +37,19 @@ pub fn process(accounts: &[AccountInfo<'_>], data: &[u8]) -> ProgramResult {
         .ok_or(ProgramError::InvalidInstructionData)?;
 
     // Ensure state has a valid value
-    assert!(*state < 2);
+    if *state > 1 {
+        return Err(AmmError::InvalidLockState.into());
+    }
 
     // Update lock state
     config_account.locked = *state;
 
+    AmmEvent::Lock(LockEvent {
+        pool: *config.key,
+        user: *authority.key,
+        locked: *state,
+    })
+    .emit();
+
     Ok(())
 }
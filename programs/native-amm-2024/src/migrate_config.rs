@@ -0,0 +1,39 @@
+use crate::{
+    state::{load_account_mut, ConfigV0},
+    utils::resize_program_account,
+    AmmError, Config,
+};
+use core::mem;
+use solana_program::{
+    account_info::AccountInfo, entrypoint::ProgramResult, program_error::ProgramError,
+};
+
+/// Move a pool config created with an older layout onto the current one
+pub fn process(accounts: &[AccountInfo<'_>], _data: &[u8]) -> ProgramResult {
+    let [payer, config, _system_program] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    // Ensure payer is signer
+    if !payer.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    // Assert we own config
+    if config.owner.ne(&crate::ID) {
+        return Err(AmmError::InvalidAccountOwner.into());
+    }
+
+    // Upgrade from the layout the account currently holds. Only current-layout pools can swap or lend, so an
+    // older config can never be mid-callback and carries no reentrancy guard to check
+    let config_account: Config = match config.data_len() {
+        len if len == mem::size_of::<ConfigV0>() => ConfigV0::try_from(config.data.borrow().as_ref())?.into(),
+        _ => return Err(AmmError::InvalidPoolState.into()),
+    };
+
+    // Grow the account and write the migrated config
+    resize_program_account(payer, config, mem::size_of::<Config>())?;
+    *load_account_mut::<Config>(config)? = config_account;
+
+    Ok(())
+}
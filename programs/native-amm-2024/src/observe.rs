@@ -0,0 +1,57 @@
+use crate::{
+    state::CumulativePrices,
+    utils::{check_eq_program_derived_address_with_bump, unpack_token_account},
+    Config,
+};
+use solana_program::{
+    account_info::AccountInfo, clock::Clock, entrypoint::ProgramResult, program::set_return_data,
+    program_error::ProgramError, sysvar::Sysvar,
+};
+
+/// Return the time-weighted price accumulators of a pool as of now
+pub fn process(accounts: &[AccountInfo<'_>], _data: &[u8]) -> ProgramResult {
+    let [config, vault_x, vault_y] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    // Load our config account, checking we own it
+    let config_account = Config::load(config)?;
+
+    // Check vault X
+    check_eq_program_derived_address_with_bump(
+        &[
+            config_account.mint_x.as_ref(),
+            config.key.as_ref(),
+            &[config_account.x_bump],
+        ],
+        &crate::ID,
+        vault_x.key,
+    )?;
+
+    // Check vault Y
+    check_eq_program_derived_address_with_bump(
+        &[
+            config_account.mint_y.as_ref(),
+            config.key.as_ref(),
+            &[config_account.y_bump],
+        ],
+        &crate::ID,
+        vault_y.key,
+    )?;
+
+    // Carry the accumulators forward to now at the current prices
+    let timestamp = Clock::get()?.unix_timestamp;
+    let (price_x_cumulative, price_y_cumulative) = config_account.cumulative_prices(
+        unpack_token_account(vault_x)?.amount,
+        unpack_token_account(vault_y)?.amount,
+        timestamp,
+    )?;
+
+    set_return_data(bytemuck::bytes_of(&CumulativePrices {
+        timestamp,
+        price_x_cumulative: price_x_cumulative.into(),
+        price_y_cumulative: price_y_cumulative.into(),
+    }));
+
+    Ok(())
+}
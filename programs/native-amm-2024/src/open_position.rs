@@ -0,0 +1,69 @@
+use crate::{
+    state::{load_account_mut, CurveType, Position},
+    utils::{check_eq_program_derived_address_and_get_bump, create_program_account},
+    AmmError, Config, OpenPosition,
+};
+use constant_product_curve::concentrated::{MAX_TICK, MIN_TICK};
+use solana_program::{
+    account_info::AccountInfo,
+    entrypoint::ProgramResult,
+    program_error::ProgramError,
+};
+
+/// Open an empty concentrated liquidity position over a tick range
+pub fn process(accounts: &[AccountInfo<'_>], data: &[u8]) -> ProgramResult {
+    let OpenPosition {
+        tick_lower,
+        tick_upper,
+    } = OpenPosition::try_from(data)?;
+
+    let [owner, config, position, _system_program] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    // Ensure owner is signer
+    if !owner.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    // Load our config account, checking we own it
+    let config_account = Config::load(config)?;
+
+    // Ensure the pool uses concentrated liquidity
+    if config_account.curve_type != CurveType::ConcentratedLiquidity as u8 {
+        return Err(AmmError::WrongCurveType.into());
+    }
+
+    // Ensure the range is valid and its ticks are initializable
+    let tick_spacing = config_account.tick_spacing as i32;
+    if tick_lower < MIN_TICK || tick_lower >= tick_upper || tick_upper > MAX_TICK {
+        return Err(AmmError::InvalidTick.into());
+    }
+    if tick_lower % tick_spacing != 0 || tick_upper % tick_spacing != 0 {
+        return Err(AmmError::InvalidTick.into());
+    }
+
+    // Get the bump and check the PDA
+    let bump = check_eq_program_derived_address_and_get_bump(
+        &[b"position", config.key.as_ref(), owner.key.as_ref(), tick_lower.to_le_bytes().as_ref(), tick_upper.to_le_bytes().as_ref()],
+        &crate::ID,
+        position.key,
+    )?;
+
+    // Create the position
+    create_program_account(
+        &[b"position", config.key.as_ref(), owner.key.as_ref(), tick_lower.to_le_bytes().as_ref(), tick_upper.to_le_bytes().as_ref(), &[bump]],
+        owner,
+        position,
+        core::mem::size_of::<Position>(),
+    )?;
+
+    let mut position_account = load_account_mut::<Position>(position)?;
+    position_account.config = *config.key;
+    position_account.owner = *owner.key;
+    position_account.tick_lower = tick_lower;
+    position_account.tick_upper = tick_upper;
+    position_account.bump = bump;
+
+    Ok(())
+}
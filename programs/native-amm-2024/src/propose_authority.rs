@@ -0,0 +1,40 @@
+use crate::{AmmError, Config, ProposeAuthority};
+use solana_program::{
+    account_info::AccountInfo, entrypoint::ProgramResult, program_error::ProgramError,
+};
+
+pub fn process(accounts: &[AccountInfo<'_>], data: &[u8]) -> ProgramResult {
+    let ProposeAuthority { new_authority } = ProposeAuthority::try_from(data)?;
+
+    let [authority, config] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    // Ensure user is signer
+    if !authority.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    // Load our config, checking we own it
+    let mut config_account = Config::load_mut(config)?;
+
+    // Assert we aren't inside a flash loan or flash swap callback
+    if config_account.reentrancy_guard == 1 {
+        return Err(AmmError::Reentrancy.into());
+    }
+
+    // Assert signer is the correct authority
+    if authority.key.ne(&config_account.authority) {
+        return Err(AmmError::WrongAuthority.into());
+    }
+
+    // Assert status is not set to revoked (2)
+    if config_account.locked == 2 {
+        return Err(AmmError::AuthorityRevoked.into());
+    }
+
+    // Propose the new authority, replacing any earlier proposal
+    config_account.pending_authority = new_authority;
+
+    Ok(())
+}
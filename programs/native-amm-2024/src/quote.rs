@@ -0,0 +1,49 @@
+use crate::{state::QuoteKind, utils::perform_basic_checks, Config, Quote};
+use solana_program::{
+    account_info::AccountInfo,
+    entrypoint::ProgramResult,
+    program_error::ProgramError,
+};
+
+/// Price a swap, deposit or withdrawal against a pool without moving any tokens, returning the amounts
+pub fn process(accounts: &[AccountInfo<'_>], data: &[u8]) -> ProgramResult {
+    let Quote {
+        amount, // Amount in, amount out or LP tokens, depending on the kind of quote
+        max_x,  // X offered to an empty pool's first deposit
+        max_y,  // Y offered to an empty pool's first deposit
+        kind,   // Swap, swap exact out, deposit or withdraw
+        is_x,   // Whether a swap takes X and sends Y
+        ..
+    } = Quote::try_from(data)?;
+
+    let [mint_x, mint_y, mint_lp, vault_x, vault_y, config, remaining_accounts @ ..] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    // Load our config account
+    let config_account = Config::load(config)?;
+
+    // Perform basic checks. Quotes don't move funds, so they never expire
+    perform_basic_checks(&config_account, i64::MAX, config, mint_lp, vault_x, vault_y)?;
+
+    // Transfer fees are read from the mints, so make sure they are the pool's
+    if mint_x.key.ne(&config_account.mint_x) || mint_y.key.ne(&config_account.mint_y) {
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    Config::perform_quote(
+        &config_account,
+        QuoteKind::try_from(kind)?,
+        amount,
+        max_x,
+        max_y,
+        is_x == 1,
+        mint_x,
+        mint_y,
+        mint_lp,
+        vault_x,
+        vault_y,
+        config,
+        remaining_accounts,
+    )
+}
@@ -0,0 +1,40 @@
+use crate::{AmmError, Config};
+use solana_program::{
+    account_info::AccountInfo, entrypoint::ProgramResult, program_error::ProgramError, pubkey::Pubkey,
+};
+
+pub fn process(accounts: &[AccountInfo<'_>], _data: &[u8]) -> ProgramResult {
+    let [authority, config] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    // Ensure user is signer
+    if !authority.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    // Load our config, checking we own it
+    let mut config_account = Config::load_mut(config)?;
+
+    // Assert we aren't inside a flash loan or flash swap callback
+    if config_account.reentrancy_guard == 1 {
+        return Err(AmmError::Reentrancy.into());
+    }
+
+    // Assert signer is the correct authority
+    if authority.key.ne(&config_account.authority) {
+        return Err(AmmError::WrongAuthority.into());
+    }
+
+    // Assert pool isn't locked, so renouncing can't unlock it as a side effect
+    if config_account.locked == 1 {
+        return Err(AmmError::PoolLocked.into());
+    }
+
+    // Permanently revoke the authority, leaving the pool unlocked and immutable
+    config_account.locked = 2;
+    config_account.authority = Pubkey::default();
+    config_account.pending_authority = Pubkey::default();
+
+    Ok(())
+}
@@ -0,0 +1,77 @@
+use crate::{
+    utils::{check_token_program, perform_basic_checks_with_no_lp, unpack_token_account},
+    AmmError, Config, RouteSwap,
+};
+use solana_program::{
+    account_info::AccountInfo,
+    entrypoint::ProgramResult,
+    program_error::ProgramError,
+};
+
+// Accounts each pool on a route takes
+const HOP_ACCOUNTS: usize = 8;
+
+/// Swap through several pools in turn, each hop swapping everything the last one delivered
+pub fn process(accounts: &[AccountInfo<'_>], data: &[u8]) -> ProgramResult {
+    let RouteSwap {
+        amount,     // Amount of tokens we deposit into the first pool
+        min_out,    // Minimum amount of tokens we're willing to withdraw from the last pool
+        expiration, // Maximum time for which the route is valid
+        hops,       // Number of pools on the route
+        ..
+    } = RouteSwap::try_from(data)?;
+
+    if hops == 0 {
+        return Err(ProgramError::InvalidInstructionData);
+    }
+
+    // Each hop takes its pool's accounts and the token account it pays into, which the next hop pays from.
+    // Tick arrays, observations and transfer hook accounts for every pool follow in the remaining accounts
+    let [user, user_from, accounts @ ..] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+    let (hop_accounts, remaining_accounts) = accounts
+        .split_at_checked(hops as usize * HOP_ACCOUNTS)
+        .ok_or(ProgramError::NotEnoughAccountKeys)?;
+
+    // Ensure user is signer
+    if !user.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    let mut user_from = user_from;
+    let mut amount_in = amount;
+    for hop in hop_accounts.chunks_exact(HOP_ACCOUNTS) {
+        let [config, mint_x, mint_y, vault_x, vault_y, token_program_x, token_program_y, user_to] = hop else {
+            return Err(ProgramError::NotEnoughAccountKeys);
+        };
+
+        // Assert we are using the correct TokenPrograms
+        check_token_program(token_program_x, mint_x)?;
+        check_token_program(token_program_y, mint_y)?;
+
+        // Load our config account
+        let config_account = Config::load(config)?;
+
+        // Perform basic checks
+        perform_basic_checks_with_no_lp(&config_account, expiration, config, vault_x, vault_y)?;
+
+        // Only the last hop's output is checked against the slippage bound
+        let balance = unpack_token_account(user_to)?.amount;
+        Config::perform_swap(&config_account, token_program_x.key, token_program_y.key, amount_in, 0, mint_x, mint_y, vault_x, vault_y, user, user_from, user_to, config, None, remaining_accounts)?;
+
+        // The next hop swaps what this one delivered, after any transfer fees
+        amount_in = unpack_token_account(user_to)?
+            .amount
+            .checked_sub(balance)
+            .ok_or(ProgramError::ArithmeticOverflow)?;
+        user_from = user_to;
+    }
+
+    // Slippage check on what the route delivered
+    if amount_in < min_out {
+        return Err(AmmError::SlippageExceeded.into());
+    }
+
+    Ok(())
+}
@@ -1,23 +1,196 @@
-use crate::utils::{check_eq_program_derived_address_and_get_bump, deposit, mint, withdraw, burn, execute_swap};
-use constant_product_curve::{xy_deposit_amounts_from_l, xy_withdraw_amounts_from_l, delta_x_from_y_swap_amount_with_fee, delta_y_from_x_swap_amount_with_fee};
+use crate::errors::{curve_error, AmmError};
+use crate::events::{AmmEvent, DepositEvent, SwapEvent, WithdrawEvent};
+use crate::utils::{deposit, mint, withdraw, burn, execute_swap, invoke_callback, get_transfer_fee, get_transfer_inverse_fee, unpack_mint, unpack_token_account};
+use constant_product_curve::{
+    concentrated::{compute_swap_step, spot_price_from_sqrt_price, sqrt_price_from_tick, tick_from_sqrt_price, xy_amounts_from_liquidity, MAX_SQRT_PRICE, MAX_TICK, MIN_SQRT_PRICE, MIN_TICK, Q64},
+    math::mul_div,
+    sqrt_u128, ConstantProduct, Curve, StableSwap, SwapDirection, Weighted, MAX_AMP, MAX_WEIGHT, MIN_WEIGHT,
+};
 use bytemuck::{Pod, Zeroable};
+use core::cell::RefMut;
 use native_amm_macros::TryFromBytes;
 use solana_program::{
     account_info::AccountInfo,
+    clock::Clock,
     entrypoint::ProgramResult,
-    program::invoke_signed,
+    program::{invoke_signed, set_return_data},
     program_error::ProgramError,
-    program_pack::Pack,
     pubkey::Pubkey,
     rent::Rent,
     system_instruction::create_account,
     sysvar::Sysvar,
 };
-use spl_token::state::{Mint, GenericTokenAccount};
+use spl_pod::primitives::PodU128;
+use spl_token_2022::{generic_token_account::GenericTokenAccount, state::Account};
+
+// LP permanently locked in the pool on the first deposit, counted in the supply but never minted
+pub const MINIMUM_LIQUIDITY: u64 = 1_000;
+
+// Ticks held by each tick array account
+pub const TICK_ARRAY_SIZE: usize = 64;
+
+// Upper bound on the tick spacing of a concentrated liquidity pool
+pub const MAX_TICK_SPACING: u16 = 16_384;
+
+// Leading bytes identifying a pool config account
+pub const CONFIG_DISCRIMINATOR: [u8; 8] = *b"AMMCONFG";
+
+// Current version of the pool config layout
+pub const CONFIG_VERSION: u8 = 1;
+
+// Leading bytes identifying a pool observations account
+pub const OBSERVATIONS_DISCRIMINATOR: [u8; 8] = *b"AMMOBSRV";
+
+#[repr(u8)]
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum CurveType {
+    ConstantProduct,
+    StableSwap,
+    Weighted,
+    ConcentratedLiquidity,
+}
+
+impl TryFrom<u8> for CurveType {
+    type Error = ProgramError;
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        match value {
+            0 => Ok(Self::ConstantProduct),
+            1 => Ok(Self::StableSwap),
+            2 => Ok(Self::Weighted),
+            3 => Ok(Self::ConcentratedLiquidity),
+            _ => Err(ProgramError::InvalidAccountData),
+        }
+    }
+}
+
+impl CurveType {
+    // Build the curve from its parameter blob
+    pub fn curve(&self, params: &[u8; 32]) -> Result<Box<dyn Curve>, ProgramError> {
+        match self {
+            // Constant product takes no parameters
+            Self::ConstantProduct => {
+                if params.ne(&[0u8; 32]) {
+                    return Err(AmmError::InvalidCurveParams.into());
+                }
+                Ok(Box::new(ConstantProduct))
+            }
+            // StableSwap takes its amplification coefficient as a little-endian u64
+            Self::StableSwap => {
+                let (amp, rest) = params.split_at(8);
+                let amp = u64::from_le_bytes(amp.try_into().map_err(|_| ProgramError::InvalidAccountData)?);
+                if amp == 0 || amp > MAX_AMP || rest.ne(&[0u8; 24]) {
+                    return Err(AmmError::InvalidCurveParams.into());
+                }
+                Ok(Box::new(StableSwap { amp }))
+            }
+            // Weighted takes the weight of X in basis points as a little-endian u64
+            Self::Weighted => {
+                let (weight_x, rest) = params.split_at(8);
+                let weight_x = u64::from_le_bytes(weight_x.try_into().map_err(|_| ProgramError::InvalidAccountData)?);
+                if !(MIN_WEIGHT..=MAX_WEIGHT).contains(&weight_x) || rest.ne(&[0u8; 24]) {
+                    return Err(AmmError::InvalidCurveParams.into());
+                }
+                Ok(Box::new(Weighted { weight_x }))
+            }
+            // Concentrated liquidity pools price through their ticks rather than a single curve
+            Self::ConcentratedLiquidity => Err(ProgramError::InvalidAccountData),
+        }
+    }
+
+    // Concentrated liquidity takes its tick spacing as a little-endian u16 and initial √P as a little-endian u128 at byte 16
+    pub fn concentrated_params(params: &[u8; 32]) -> Result<(u16, u128), ProgramError> {
+        let (tick_spacing, rest) = params.split_at(2);
+        let (padding, sqrt_price) = rest.split_at(14);
+        let tick_spacing = u16::from_le_bytes(tick_spacing.try_into().map_err(|_| ProgramError::InvalidAccountData)?);
+        let sqrt_price = u128::from_le_bytes(sqrt_price.try_into().map_err(|_| ProgramError::InvalidAccountData)?);
+        if tick_spacing == 0 || tick_spacing > MAX_TICK_SPACING || padding.ne(&[0u8; 14]) {
+            return Err(AmmError::InvalidCurveParams.into());
+        }
+        if !(MIN_SQRT_PRICE..=MAX_SQRT_PRICE).contains(&sqrt_price) {
+            return Err(AmmError::InvalidCurveParams.into());
+        }
+        Ok((tick_spacing, sqrt_price))
+    }
+}
+
+#[repr(u8)]
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum QuoteKind {
+    Swap,
+    SwapExactOut,
+    Deposit,
+    Withdraw,
+}
+
+impl TryFrom<u8> for QuoteKind {
+    type Error = ProgramError;
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        match value {
+            0 => Ok(Self::Swap),
+            1 => Ok(Self::SwapExactOut),
+            2 => Ok(Self::Deposit),
+            3 => Ok(Self::Withdraw),
+            _ => Err(ProgramError::InvalidInstructionData),
+        }
+    }
+}
 
 #[repr(C)]
 #[derive(Clone, Copy, Pod, Zeroable, TryFromBytes)]
 pub struct Config {
+    pub discriminator: [u8; 8],
+    pub seed: u64,
+    pub authority: Pubkey,
+    // Authority proposed by the current one, which takes over once it accepts
+    pub pending_authority: Pubkey,
+    pub mint_x: Pubkey,
+    pub mint_y: Pubkey,
+    pub fee: u16,
+    pub locked: u8,
+    pub config_bump: u8,
+    pub lp_bump: u8,
+    pub x_bump: u8,
+    pub y_bump: u8,
+    pub curve_type: u8,
+    pub curve_params: [u8; 32],
+    // Concentrated liquidity state, unused by other curves
+    pub sqrt_price: PodU128,
+    pub liquidity: PodU128,
+    pub fee_growth_global_x: PodU128,
+    pub fee_growth_global_y: PodU128,
+    pub tick_current: i32,
+    pub tick_spacing: u16,
+    // Share of the swap fee in basis points set aside for the treasury, and the amounts owed so far
+    pub protocol_fee: u16,
+    pub treasury: Pubkey,
+    pub protocol_fees_x: u64,
+    pub protocol_fees_y: u64,
+    // Bounds on fee updates by the authority, and when the fee last changed
+    pub last_fee_update: i64,
+    pub fee_update_interval: i64,
+    // Time-weighted price accumulators of X in Y and Y in X as Q64.64 prices times seconds, pool liquidity times seconds,
+    // and when they last changed
+    pub price_x_cumulative: PodU128,
+    pub price_y_cumulative: PodU128,
+    pub liquidity_cumulative: PodU128,
+    pub price_last_update: i64,
+    // LP supply locked away at the first deposit and counted in every LP price. Legacy pools never locked any
+    pub locked_liquidity: u64,
+    pub fee_max_step: u16,
+    // Fee on flash loans in basis points, paid to LPs
+    pub flash_fee: u16,
+    pub version: u8,
+    // Set while a flash loan or flash swap callback holds control, so the pool can't be entered again until it returns
+    pub reentrancy_guard: u8,
+    pub padding: [u8; 2],
+}
+
+// Layout of pool configs created before the discriminator and version were added
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable, TryFromBytes)]
+pub struct ConfigV0 {
     pub seed: u64,
     pub authority: Pubkey,
     pub mint_x: Pubkey,
@@ -31,11 +204,319 @@ pub struct Config {
     pub padding: [u8; 1],
 }
 
+// Time-weighted price accumulators of a pool as of a timestamp, returned by Observe
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+pub struct CumulativePrices {
+    pub timestamp: i64,
+    pub price_x_cumulative: PodU128,
+    pub price_y_cumulative: PodU128,
+}
+
+// Amounts a swap, deposit or withdrawal would move, returned by Quote. Token amounts are as sent or received by the user
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+pub struct QuoteAmounts {
+    pub amount_in: u64,  // Tokens a swap takes from the user
+    pub amount_out: u64, // Tokens a swap sends to the user
+    pub fee: u64,        // Pool fee kept by a swap
+    pub amount_x: u64,   // X a deposit takes from the user, or a withdrawal sends to them
+    pub amount_y: u64,   // Y a deposit takes from the user, or a withdrawal sends to them
+    pub lp: u64,         // LP tokens a deposit mints, or a withdrawal burns
+}
+
+impl From<ConfigV0> for Config {
+    // Legacy pools are constant product with no protocol fee, a fixed swap fee and no locked liquidity
+    fn from(legacy: ConfigV0) -> Self {
+        Config {
+            discriminator: CONFIG_DISCRIMINATOR,
+            seed: legacy.seed,
+            authority: legacy.authority,
+            pending_authority: Pubkey::default(),
+            mint_x: legacy.mint_x,
+            mint_y: legacy.mint_y,
+            fee: legacy.fee,
+            locked: legacy.locked,
+            config_bump: legacy.config_bump,
+            lp_bump: legacy.lp_bump,
+            x_bump: legacy.x_bump,
+            y_bump: legacy.y_bump,
+            curve_type: CurveType::ConstantProduct as u8,
+            curve_params: [0; 32],
+            sqrt_price: 0.into(),
+            liquidity: 0.into(),
+            fee_growth_global_x: 0.into(),
+            fee_growth_global_y: 0.into(),
+            tick_current: 0,
+            tick_spacing: 0,
+            protocol_fee: 0,
+            treasury: legacy.authority,
+            protocol_fees_x: 0,
+            protocol_fees_y: 0,
+            last_fee_update: 0,
+            fee_update_interval: 0,
+            price_x_cumulative: 0.into(),
+            price_y_cumulative: 0.into(),
+            liquidity_cumulative: 0.into(),
+            price_last_update: 0,
+            locked_liquidity: 0,
+            fee_max_step: 0,
+            flash_fee: 0,
+            version: CONFIG_VERSION,
+            reentrancy_guard: 0,
+            padding: [0; 2],
+        }
+    }
+}
+
 impl Config {
+    // Copy a pool's config out of its account, checking its owner, size, discriminator and version
+    pub fn load(config: &AccountInfo) -> Result<Config, ProgramError> {
+        if config.owner.ne(&crate::ID) {
+            return Err(AmmError::InvalidAccountOwner.into());
+        }
+        let config_account = Config::try_from(config.data.borrow().as_ref())?;
+        config_account.check_header()?;
+        Ok(config_account)
+    }
+
+    // Borrow a pool's config from its account with the same checks, so changes persist
+    pub fn load_mut<'a>(config: &'a AccountInfo) -> Result<RefMut<'a, Config>, ProgramError> {
+        let config_account = load_account_mut::<Config>(config)?;
+        config_account.check_header()?;
+        Ok(config_account)
+    }
+
+    fn check_header(&self) -> ProgramResult {
+        if self.discriminator.ne(&CONFIG_DISCRIMINATOR) {
+            return Err(AmmError::InvalidDiscriminator.into());
+        }
+        if self.version != CONFIG_VERSION {
+            return Err(AmmError::ConfigNeedsMigration.into());
+        }
+        Ok(())
+    }
+
+    pub fn curve(&self) -> Result<Box<dyn Curve>, ProgramError> {
+        CurveType::try_from(self.curve_type)?.curve(&self.curve_params)
+    }
+
+    // Vault balances less the protocol fees owed, which do not belong to LPs
+    pub fn reserves(&self, vault_x: u64, vault_y: u64) -> Result<(u64, u64), ProgramError> {
+        Ok((
+            vault_x.checked_sub(self.protocol_fees_x).ok_or(ProgramError::ArithmeticOverflow)?,
+            vault_y.checked_sub(self.protocol_fees_y).ok_or(ProgramError::ArithmeticOverflow)?,
+        ))
+    }
+
+    // Reserves as they stand in the vaults, for reporting a pool's state after a trade
+    pub fn vault_reserves(&self, vault_x: &AccountInfo, vault_y: &AccountInfo) -> Result<(u64, u64), ProgramError> {
+        self.reserves(unpack_token_account(vault_x)?.amount, unpack_token_account(vault_y)?.amount)
+    }
+
+    // Prices of X in Y and Y in X as Q64.64, zero for an empty pool and saturating at extreme prices
+    pub fn spot_prices(&self, vault_x: u64, vault_y: u64) -> Result<(u128, u128), ProgramError> {
+        let spot_price = |direction| -> Result<u128, ProgramError> {
+            Ok(match CurveType::try_from(self.curve_type)? {
+                CurveType::ConcentratedLiquidity => spot_price_from_sqrt_price(self.sqrt_price.into(), direction).unwrap_or(u128::MAX),
+                curve => match self.reserves(vault_x, vault_y)? {
+                    (0, _) | (_, 0) => 0,
+                    (x, y) => curve.curve(&self.curve_params)?.spot_price(x, y, direction).unwrap_or(u128::MAX),
+                },
+            })
+        };
+        Ok((spot_price(SwapDirection::XToY)?, spot_price(SwapDirection::YToX)?))
+    }
+
+    // Cumulative prices carried forward from the last update to now at the current prices
+    // C = C_last + P·(now - t_last)
+    pub fn cumulative_prices(&self, vault_x: u64, vault_y: u64, now: i64) -> Result<(u128, u128), ProgramError> {
+        let (price_x_cumulative, price_y_cumulative) = (self.price_x_cumulative.into(), self.price_y_cumulative.into());
+        let elapsed = now.saturating_sub(self.price_last_update).max(0) as u128;
+        if elapsed == 0 {
+            return Ok((price_x_cumulative, price_y_cumulative));
+        }
+        // Accumulators wrap, consumers only ever take differences between two observations
+        let (price_x, price_y) = self.spot_prices(vault_x, vault_y)?;
+        Ok((
+            price_x_cumulative.wrapping_add(price_x.wrapping_mul(elapsed)),
+            price_y_cumulative.wrapping_add(price_y.wrapping_mul(elapsed)),
+        ))
+    }
+
+    // Accumulate the current prices and liquidity up to now. Called before a trade, so only prices that held for a whole
+    // block count
+    pub fn update_cumulative_prices(&mut self, vault_x: u64, vault_y: u64, now: i64) -> ProgramResult {
+        let (price_x_cumulative, price_y_cumulative) = self.cumulative_prices(vault_x, vault_y, now)?;
+        let elapsed = now.saturating_sub(self.price_last_update).max(0) as u128;
+        let liquidity = self.pool_liquidity(vault_x, vault_y)?;
+        self.price_x_cumulative = price_x_cumulative.into();
+        self.price_y_cumulative = price_y_cumulative.into();
+        self.liquidity_cumulative = u128::from(self.liquidity_cumulative).wrapping_add(liquidity.wrapping_mul(elapsed)).into();
+        self.price_last_update = now;
+        Ok(())
+    }
+
+    // Liquidity in range for concentrated pools, otherwise the geometric mean of the reserves
+    // L = √(X·Y)
+    pub fn pool_liquidity(&self, vault_x: u64, vault_y: u64) -> Result<u128, ProgramError> {
+        Ok(match CurveType::try_from(self.curve_type)? {
+            CurveType::ConcentratedLiquidity => self.liquidity.into(),
+            _ => {
+                let (x, y) = self.reserves(vault_x, vault_y)?;
+                sqrt_u128(x as u128 * y as u128)
+            }
+        })
+    }
+
+    // Accumulate prices up to now from the pre-trade state, and record them in the pool's observations if passed
+    pub fn record_observation(
+        &mut self,
+        config: &Pubkey,
+        vault_x: u64,
+        vault_y: u64,
+        remaining_accounts: &[AccountInfo],
+    ) -> ProgramResult {
+        let now = Clock::get()?.unix_timestamp;
+        self.update_cumulative_prices(vault_x, vault_y, now)?;
+        if let Some(observations) = remaining_accounts.iter().find(|account| Observations::is_observations_of(account, config)) {
+            let (mut observations, mut entries) = Observations::load_mut(observations, config)?;
+            observations.write(
+                &mut entries,
+                now,
+                self.price_x_cumulative.into(),
+                self.price_y_cumulative.into(),
+                self.liquidity_cumulative.into(),
+            );
+        }
+        Ok(())
+    }
+
+    // Set aside the protocol's share of a swap fee paid in X or Y, returning the share left to LPs
+    pub fn accrue_protocol_fee(&mut self, fee: u64, is_x: bool) -> Result<u64, ProgramError> {
+        let protocol_fee = (fee as u128 * self.protocol_fee as u128 / 10_000) as u64;
+        let protocol_fees = if is_x { &mut self.protocol_fees_x } else { &mut self.protocol_fees_y };
+        *protocol_fees = protocol_fees.checked_add(protocol_fee).ok_or(ProgramError::ArithmeticOverflow)?;
+        Ok(fee - protocol_fee)
+    }
+
+    // Price a swap of amount in, or amount out when not exact_in, returning the vault amounts in and out and the pool fee.
+    // The fee is in the output token, or the input token for concentrated pools. Simulated swaps don't write to tick arrays
+    pub fn swap_amounts(
+        &mut self,
+        config: &Pubkey,
+        amount: u64,
+        is_x: bool,
+        exact_in: bool,
+        vault_x: u64,
+        vault_y: u64,
+        remaining_accounts: &[AccountInfo],
+        simulate: bool,
+    ) -> Result<(u64, u64, u64), ProgramError> {
+        let direction = if is_x { SwapDirection::XToY } else { SwapDirection::YToX };
+        match CurveType::try_from(self.curve_type)? {
+            CurveType::ConcentratedLiquidity => {
+                self.swap_concentrated(config, amount, direction, exact_in, remaining_accounts, simulate)
+            }
+            curve => {
+                let (reserve_x, reserve_y) = self.reserves(vault_x, vault_y)?;
+                let curve = curve.curve(&self.curve_params)?;
+                let (amount_in, amount_out, fee) = match exact_in {
+                    true => {
+                        let (amount_out, fee) = curve
+                            .swap_out(reserve_x, reserve_y, amount, self.fee, direction)
+                            .map_err(curve_error)?;
+                        (amount, amount_out, fee)
+                    }
+                    false => {
+                        let (amount_in, fee) = curve
+                            .swap_in(reserve_x, reserve_y, amount, self.fee, direction)
+                            .map_err(curve_error)?;
+                        (amount_in, amount, fee)
+                    }
+                };
+                // The fee is kept from the output token
+                self.accrue_protocol_fee(fee, !is_x)?;
+                Ok((amount_in, amount_out, fee))
+            }
+        }
+    }
+
+    // X and Y a user sends for amount LP tokens, or max_x and max_y into an empty pool, and the LP tokens minted
+    pub fn user_deposit_amounts(
+        &self,
+        amount: u64,
+        max_x: u64,
+        max_y: u64,
+        mint_x: &AccountInfo,
+        mint_y: &AccountInfo,
+        vault_x: u64,
+        vault_y: u64,
+        lp_supply: u64,
+    ) -> Result<(u64, u64, u64), ProgramError> {
+        let curve = self.curve()?;
+        let (reserve_x, reserve_y) = self.reserves(vault_x, vault_y)?;
+
+        // A pool is only seeded afresh when nothing prices it: no LP supply, and either a vault is empty or no liquidity
+        // was ever locked. Reserves left behind the locked liquidity after every LP has withdrawn stay with it
+        let first_deposit = lp_supply == 0 && (self.locked_liquidity == 0 || reserve_x == 0 || reserve_y == 0);
+
+        Ok(match first_deposit {
+            true => {
+                // Mint the curve's initial liquidity for what the vaults hold once the amounts arrive, less the locked
+                // liquidity. Tokens donated to a vault beforehand go to the first depositor rather than leaving the
+                // pool priced on one side only
+                let l = curve.initial_liquidity(
+                    reserve_x
+                        .checked_add(max_x - get_transfer_fee(mint_x, max_x)?)
+                        .ok_or(ProgramError::ArithmeticOverflow)?,
+                    reserve_y
+                        .checked_add(max_y - get_transfer_fee(mint_y, max_y)?)
+                        .ok_or(ProgramError::ArithmeticOverflow)?,
+                )
+                .map_err(curve_error)?;
+
+                (max_x, max_y, l.checked_sub(self.locked_liquidity).ok_or(ProgramError::InsufficientFunds)?)
+            },
+            false => {
+                let (x, y) = curve.deposit_amounts(
+                    reserve_x,
+                    reserve_y,
+                    lp_supply + self.locked_liquidity,
+                    amount,
+                )
+                .map_err(curve_error)?;
+
+                // Gross up so the vaults receive X and Y after transfer fees
+                (
+                    x.checked_add(get_transfer_inverse_fee(mint_x, x)?).ok_or(ProgramError::ArithmeticOverflow)?,
+                    y.checked_add(get_transfer_inverse_fee(mint_y, y)?).ok_or(ProgramError::ArithmeticOverflow)?,
+                    amount,
+                )
+            }
+        })
+    }
+
+    // X and Y the vaults send for burning amount LP tokens, before transfer fees
+    pub fn user_withdraw_amounts(&self, amount: u64, vault_x: u64, vault_y: u64, lp_supply: u64) -> Result<(u64, u64), ProgramError> {
+        let (reserve_x, reserve_y) = self.reserves(vault_x, vault_y)?;
+        self.curve()?
+            .withdraw_amounts(reserve_x, reserve_y, lp_supply + self.locked_liquidity, amount)
+            .map_err(curve_error)
+    }
+
     pub fn initialize<'a>(
         seed: u64,
         authority: Pubkey,
+        treasury: Pubkey,
         fee: u16,
+        protocol_fee: u16,
+        fee_max_step: u16,
+        fee_update_interval: i64,
+        flash_fee: u16,
+        curve_type: u8,
+        curve_params: [u8; 32],
+        config_bump: u8,
         lp_bump: u8,
         x_bump: u8,
         y_bump: u8,
@@ -44,18 +525,27 @@ impl Config {
         initializer: &AccountInfo<'a>,
         config: &AccountInfo<'a>,
     ) -> ProgramResult {
-        let config_bump = check_eq_program_derived_address_and_get_bump(
-            &[b"config", seed.to_le_bytes().as_ref()],
-            &crate::ID,
-            config.key,
-        )?;
+        // Check that the fees are less than 100% and the protocol takes at most all of the swap fee
+        if fee >= 10_000 || flash_fee >= 10_000 || protocol_fee > 10_000 || fee_update_interval < 0 {
+            return Err(AmmError::InvalidFee.into());
+        }
 
-        // Check that the fee is less than 100%
-        assert!(fee < 10_000);
+        // Check the curve type and its parameters are valid
+        let (tick_spacing, sqrt_price) = match CurveType::try_from(curve_type)? {
+            CurveType::ConcentratedLiquidity => CurveType::concentrated_params(&curve_params)?,
+            curve => {
+                curve.curve(&curve_params)?;
+                (0, 0)
+            }
+        };
+        let tick_current = match sqrt_price {
+            0 => 0,
+            _ => tick_from_sqrt_price(sqrt_price).map_err(|_| ProgramError::ArithmeticOverflow)?,
+        };
 
-        // Check Mint and Token Program are valid
-        let _ = spl_token::state::Mint::unpack(&mint_x.try_borrow_data()?);
-        let _ = spl_token::state::Mint::unpack(&mint_y.try_borrow_data()?);
+        // Check Mints are valid
+        unpack_mint(mint_x)?;
+        unpack_mint(mint_y)?;
 
         // Initialize the Config Account
         let config_space = core::mem::size_of::<Config>();
@@ -74,13 +564,11 @@ impl Config {
             &[&[b"config", seed.to_le_bytes().as_ref(), &[config_bump]]],
         )?;
         
-        let mut config_data: Config =
-            *bytemuck::try_from_bytes_mut::<Config>(*config.data.borrow_mut())
-            .map_err(|_| ProgramError::InvalidAccountData)?;
-        
-        config_data.clone_from(&Config {
+        *load_account_mut::<Config>(config)? = Config {
+            discriminator: CONFIG_DISCRIMINATOR,
             seed,
             authority,
+            pending_authority: Pubkey::default(),
             mint_x: *mint_x.key,
             mint_y: *mint_y.key,
             fee,
@@ -89,8 +577,31 @@ impl Config {
             lp_bump,
             x_bump,
             y_bump,
-            padding: [0; 1],
-        });
+            curve_type,
+            curve_params,
+            sqrt_price: sqrt_price.into(),
+            liquidity: 0.into(),
+            fee_growth_global_x: 0.into(),
+            fee_growth_global_y: 0.into(),
+            tick_current,
+            tick_spacing,
+            protocol_fee,
+            treasury,
+            protocol_fees_x: 0,
+            protocol_fees_y: 0,
+            last_fee_update: Clock::get()?.unix_timestamp,
+            fee_update_interval,
+            price_x_cumulative: 0.into(),
+            price_y_cumulative: 0.into(),
+            liquidity_cumulative: 0.into(),
+            price_last_update: Clock::get()?.unix_timestamp,
+            locked_liquidity: MINIMUM_LIQUIDITY,
+            fee_max_step,
+            flash_fee,
+            version: CONFIG_VERSION,
+            reentrancy_guard: 0,
+            padding: [0; 2],
+        };
 
         Ok(())
     }
@@ -100,6 +611,8 @@ impl Config {
         max_x: u64,
         max_y: u64,
         config_account: &Config,
+        token_program_x: &Pubkey,
+        token_program_y: &Pubkey,
         token_program: &Pubkey,
         user_x: &AccountInfo<'a>,
         user_y: &AccountInfo<'a>,
@@ -111,53 +624,57 @@ impl Config {
         mint_lp: &AccountInfo<'a>,
         config: &AccountInfo<'a>,
         user: &AccountInfo<'a>,
+        remaining_accounts: &[AccountInfo<'a>],
     ) -> ProgramResult {
-        let vault_x_account = spl_token::state::Account::unpack(&vault_x.try_borrow_data()?)?;
-        let vault_y_account = spl_token::state::Account::unpack(&vault_y.try_borrow_data()?)?;
-        let mint_lp_account = spl_token::state::Mint::unpack(&mint_lp.try_borrow_data()?)?;
+        let vault_x_account = unpack_token_account(vault_x)?;
+        let vault_y_account = unpack_token_account(vault_y)?;
+        let mint_lp_account = unpack_mint(mint_lp)?;
 
-        let (x,y) = match mint_lp_account.supply == 0 && vault_x_account.amount == 0 && vault_y_account.amount == 0 {
-            true => (max_x, max_y),
-            false => {
-                xy_deposit_amounts_from_l(
-                    vault_x_account.amount,
-                    vault_y_account.amount,
-                    mint_lp_account.supply,
-                    amount,
-                    1_000_000_000,
-                )
-                .map_err(|_| ProgramError::ArithmeticOverflow)?
-            }
-        };
+        // Record an observation from the pre-deposit state
+        Config::load_mut(config)?.record_observation(config.key, vault_x_account.amount, vault_y_account.amount, remaining_accounts)?;
 
-        // Slippage check
-        assert!(x <= max_x);
-        assert!(y <= max_y);
+        let (x, y, lp) = config_account.user_deposit_amounts(
+            amount,
+            max_x,
+            max_y,
+            mint_x,
+            mint_y,
+            vault_x_account.amount,
+            vault_y_account.amount,
+            mint_lp_account.supply,
+        )?;
+
+        // Slippage check. On the first deposit, amount is the minimum LP we are willing to receive
+        if x > max_x || y > max_y || lp < amount {
+            return Err(AmmError::SlippageExceeded.into());
+        }
 
         // Get decimals
-        let mint_x_decimals = Mint::unpack(mint_x.data.borrow().as_ref())?.decimals;
-        let mint_y_decimals = Mint::unpack(mint_y.data.borrow().as_ref())?.decimals;
+        let mint_x_decimals = unpack_mint(mint_x)?.decimals;
+        let mint_y_decimals = unpack_mint(mint_y)?.decimals;
 
         // Transfer the funds from the users's token X account to the vault
         deposit(
-            token_program,
+            token_program_x,
             user_x,
             mint_x,
             vault_x,
             user,
             x,
             mint_x_decimals,
+            remaining_accounts,
         )?;
 
         // Transfer the funds from the users's token Y account to the vault
         deposit(
-            token_program,
+            token_program_y,
             user_y,
             mint_y,
             vault_y,
             user,
             y,
             mint_y_decimals,
+            remaining_accounts,
         )?;
 
         // Mint LP tokens
@@ -166,10 +683,121 @@ impl Config {
             mint_lp,
             user_lp,
             config,
+            lp,
+            mint_lp_account.decimals,
+            &[b"config", config_account.seed.to_le_bytes().as_ref(), &[config_account.config_bump]],
+        )?;
+
+        let (reserve_x, reserve_y) = Config::load(config)?.vault_reserves(vault_x, vault_y)?;
+        AmmEvent::Deposit(DepositEvent {
+            pool: *config.key,
+            user: *user.key,
+            amount_x: x,
+            amount_y: y,
+            lp,
+            reserve_x,
+            reserve_y,
+        })
+        .emit();
+
+        Ok(())
+    }
+
+    pub fn perform_user_deposit_single<'a>(
+        amount: u64,
+        min_lp: u64,
+        config_account: &Config,
+        token_program_from: &Pubkey,
+        token_program: &Pubkey,
+        user_from: &AccountInfo<'a>,
+        user_lp: &AccountInfo<'a>,
+        vault_x: &AccountInfo<'a>,
+        vault_y: &AccountInfo<'a>,
+        mint_from: &AccountInfo<'a>,
+        mint_lp: &AccountInfo<'a>,
+        config: &AccountInfo<'a>,
+        user: &AccountInfo<'a>,
+        remaining_accounts: &[AccountInfo<'a>],
+    ) -> ProgramResult {
+        let vault_x_account = unpack_token_account(vault_x)?;
+        let vault_y_account = unpack_token_account(vault_y)?;
+        let mint_lp_account = unpack_mint(mint_lp)?;
+
+        // Deposit into the vault of the mint we were given
+        let is_x = match *mint_from.key {
+            mint if mint.eq(&config_account.mint_x) => true,
+            mint if mint.eq(&config_account.mint_y) => false,
+            _ => return Err(ProgramError::InvalidAccountData),
+        };
+        let vault_from = if is_x { vault_x } else { vault_y };
+
+        // There must be liquidity to swap against and a ratio to deposit at
+        if mint_lp_account.supply == 0 {
+            return Err(AmmError::InvalidPoolState.into());
+        }
+
+        // Price on the amount that reaches the vault after transfer fees
+        let amount_in = amount - get_transfer_fee(mint_from, amount)?;
+
+        // Swap part of the deposit internally, so the rest and the swap's output go in at the post-swap ratio
+        let direction = if is_x { SwapDirection::XToY } else { SwapDirection::YToX };
+        let lp = {
+            let mut config_account = Config::load_mut(config)?;
+            // Record an observation from the pre-deposit state
+            config_account.record_observation(config.key, vault_x_account.amount, vault_y_account.amount, remaining_accounts)?;
+            let (reserve_x, reserve_y) = config_account.reserves(vault_x_account.amount, vault_y_account.amount)?;
+            // Price LP on the reserves without the protocol's cut of the swap's fee. What the smaller of the two shares leaves
+            // unminted is bounded by rounding and one more unit swapped, and stays with existing LPs
+            let (_, _, fee, lp) = config_account
+                .curve()?
+                .deposit_single_amounts(reserve_x, reserve_y, mint_lp_account.supply + config_account.locked_liquidity, amount_in, config_account.fee, config_account.protocol_fee, direction)
+                .map_err(curve_error)?;
+            // The fee is kept from the output token
+            config_account.accrue_protocol_fee(fee, !is_x)?;
+            lp
+        };
+
+        // Slippage check, and never take a deposit too small to mint anything
+        if lp == 0 || lp < min_lp {
+            return Err(AmmError::SlippageExceeded.into());
+        }
+
+        // Transfer the whole deposit from the user's token account to the vault
+        deposit(
+            token_program_from,
+            user_from,
+            mint_from,
+            vault_from,
+            user,
             amount,
+            unpack_mint(mint_from)?.decimals,
+            remaining_accounts,
+        )?;
+
+        // Mint LP tokens
+        mint(
+            token_program,
+            mint_lp,
+            user_lp,
+            config,
+            lp,
             mint_lp_account.decimals,
             &[b"config", config_account.seed.to_le_bytes().as_ref(), &[config_account.config_bump]],
-        )
+        )?;
+
+        let (reserve_x, reserve_y) = Config::load(config)?.vault_reserves(vault_x, vault_y)?;
+        AmmEvent::Deposit(DepositEvent {
+            pool: *config.key,
+            user: *user.key,
+            amount_x: if is_x { amount } else { 0 },
+            amount_y: if is_x { 0 } else { amount },
+            lp,
+            reserve_x,
+            reserve_y,
+        })
+        .emit();
+
+        Ok(())
     }
 
     pub fn perform_user_withdraw<'a>(
@@ -177,6 +805,8 @@ impl Config {
         min_x: u64,
         min_y: u64,
         config_account: &Config,
+        token_program_x: &Pubkey,
+        token_program_y: &Pubkey,
         token_program: &Pubkey,
         user_x: &AccountInfo<'a>,
         user_y: &AccountInfo<'a>,
@@ -188,31 +818,29 @@ impl Config {
         mint_lp: &AccountInfo<'a>,
         config: &AccountInfo<'a>,
         user: &AccountInfo<'a>,
+        remaining_accounts: &[AccountInfo<'a>],
     ) -> ProgramResult {
-        let vault_x_account = spl_token::state::Account::unpack(&vault_x.try_borrow_data()?)?;
-        let vault_y_account = spl_token::state::Account::unpack(&vault_y.try_borrow_data()?)?;
-        let mint_lp_account = spl_token::state::Mint::unpack(&mint_lp.try_borrow_data()?)?;
+        let vault_x_account = unpack_token_account(vault_x)?;
+        let vault_y_account = unpack_token_account(vault_y)?;
+        let mint_lp_account = unpack_mint(mint_lp)?;
 
-        let (x, y) = xy_withdraw_amounts_from_l(
-            vault_x_account.amount,
-            vault_y_account.amount,
-            mint_lp_account.supply,
-            amount,
-            1_000_000_000,
-        )
-        .map_err(|_| ProgramError::ArithmeticOverflow)?;
+        // Record an observation from the pre-withdrawal state
+        Config::load_mut(config)?.record_observation(config.key, vault_x_account.amount, vault_y_account.amount, remaining_accounts)?;
 
-        // Slippage check
-        assert!(x >= min_x);
-        assert!(y >= min_y);
+        let (x, y) = config_account.user_withdraw_amounts(amount, vault_x_account.amount, vault_y_account.amount, mint_lp_account.supply)?;
+
+        // Slippage check on the amounts received after transfer fees
+        if x - get_transfer_fee(mint_x, x)? < min_x || y - get_transfer_fee(mint_y, y)? < min_y {
+            return Err(AmmError::SlippageExceeded.into());
+        }
 
         // Get decimals
-        let mint_x_decimals = Mint::unpack(mint_x.data.borrow().as_ref())?.decimals;
-        let mint_y_decimals = Mint::unpack(mint_y.data.borrow().as_ref())?.decimals;
+        let mint_x_decimals = unpack_mint(mint_x)?.decimals;
+        let mint_y_decimals = unpack_mint(mint_y)?.decimals;
 
         // Transfer the funds from the users's token X account to the vault
         withdraw(
-            token_program,
+            token_program_x,
             user_x,
             mint_x,
             vault_x,
@@ -220,11 +848,12 @@ impl Config {
             x,
             mint_x_decimals,
             &[b"config", config_account.seed.to_le_bytes().as_ref(), &[config_account.config_bump]],
+            remaining_accounts,
         )?;
 
         // Transfer the funds from the users's token Y account to the vault
         withdraw(
-            token_program,
+            token_program_y,
             user_y,
             mint_y,
             vault_y,
@@ -232,10 +861,99 @@ impl Config {
             y,
             mint_y_decimals,
             &[b"config", config_account.seed.to_le_bytes().as_ref(), &[config_account.config_bump]],
+            remaining_accounts,
+        )?;
 
+        // Burn LP tokens
+        burn(
+            token_program,
+            user_lp,
+            mint_lp,
+            user,
+            amount,
+            mint_lp_account.decimals,
         )?;
 
-        // Mint LP tokens
+        let (reserve_x, reserve_y) = Config::load(config)?.vault_reserves(vault_x, vault_y)?;
+        AmmEvent::Withdraw(WithdrawEvent {
+            pool: *config.key,
+            user: *user.key,
+            amount_x: x,
+            amount_y: y,
+            lp: amount,
+            reserve_x,
+            reserve_y,
+        })
+        .emit();
+
+        Ok(())
+    }
+
+    pub fn perform_user_withdraw_single<'a>(
+        amount: u64,
+        min_out: u64,
+        config_account: &Config,
+        token_program_to: &Pubkey,
+        token_program: &Pubkey,
+        user_to: &AccountInfo<'a>,
+        user_lp: &AccountInfo<'a>,
+        vault_x: &AccountInfo<'a>,
+        vault_y: &AccountInfo<'a>,
+        mint_to: &AccountInfo<'a>,
+        mint_lp: &AccountInfo<'a>,
+        config: &AccountInfo<'a>,
+        user: &AccountInfo<'a>,
+        remaining_accounts: &[AccountInfo<'a>],
+    ) -> ProgramResult {
+        let vault_x_account = unpack_token_account(vault_x)?;
+        let vault_y_account = unpack_token_account(vault_y)?;
+        let mint_lp_account = unpack_mint(mint_lp)?;
+
+        // Withdraw from the vault of the mint we were given
+        let is_x = match *mint_to.key {
+            mint if mint.eq(&config_account.mint_x) => true,
+            mint if mint.eq(&config_account.mint_y) => false,
+            _ => return Err(ProgramError::InvalidAccountData),
+        };
+        let vault_to = if is_x { vault_x } else { vault_y };
+
+        // Withdraw both sides pro-rata, then swap the side we don't want back into the pool at the post-withdrawal reserves
+        let direction = if is_x { SwapDirection::YToX } else { SwapDirection::XToY };
+        let amount_out = {
+            let mut config_account = Config::load_mut(config)?;
+            // Record an observation from the pre-withdrawal state
+            config_account.record_observation(config.key, vault_x_account.amount, vault_y_account.amount, remaining_accounts)?;
+            let (reserve_x, reserve_y) = config_account.reserves(vault_x_account.amount, vault_y_account.amount)?;
+            let (x, y) = config_account.user_withdraw_amounts(amount, vault_x_account.amount, vault_y_account.amount, mint_lp_account.supply)?;
+            let (amount_kept, amount_swapped) = if is_x { (x, y) } else { (y, x) };
+            let (amount_out, fee) = config_account
+                .curve()?
+                .swap_out(reserve_x - x, reserve_y - y, amount_swapped, config_account.fee, direction)
+                .map_err(curve_error)?;
+            // The fee is kept from the output token
+            config_account.accrue_protocol_fee(fee, is_x)?;
+            amount_kept.checked_add(amount_out).ok_or(ProgramError::ArithmeticOverflow)?
+        };
+
+        // Slippage check on the amount received after transfer fees
+        if amount_out - get_transfer_fee(mint_to, amount_out)? < min_out {
+            return Err(AmmError::SlippageExceeded.into());
+        }
+
+        // Transfer everything from the one vault to the user's token account
+        withdraw(
+            token_program_to,
+            user_to,
+            mint_to,
+            vault_to,
+            config,
+            amount_out,
+            unpack_mint(mint_to)?.decimals,
+            &[b"config", config_account.seed.to_le_bytes().as_ref(), &[config_account.config_bump]],
+            remaining_accounts,
+        )?;
+
+        // Burn LP tokens
         burn(
             token_program,
             user_lp,
@@ -243,82 +961,1067 @@ impl Config {
             user,
             amount,
             mint_lp_account.decimals,
-        )
+        )?;
+
+        let (reserve_x, reserve_y) = Config::load(config)?.vault_reserves(vault_x, vault_y)?;
+        AmmEvent::Withdraw(WithdrawEvent {
+            pool: *config.key,
+            user: *user.key,
+            amount_x: if is_x { amount_out } else { 0 },
+            amount_y: if is_x { 0 } else { amount_out },
+            lp: amount,
+            reserve_x,
+            reserve_y,
+        })
+        .emit();
+
+        Ok(())
     }
 
     pub fn perform_swap<'a>(
         config_account: &Config,
-        token_program: &Pubkey,
+        token_program_x: &Pubkey,
+        token_program_y: &Pubkey,
         amount: u64,
         min: u64,
         mint_x: &AccountInfo<'a>,
         mint_y: &AccountInfo<'a>, 
         vault_x: &AccountInfo<'a>,
         vault_y: &AccountInfo<'a>,
+        user: &AccountInfo<'a>,
         user_from: &AccountInfo<'a>,
         user_to: &AccountInfo<'a>,
         config  : &AccountInfo<'a>,
+        callback: Option<(&AccountInfo<'a>, &[u8])>,
+        remaining_accounts: &[AccountInfo<'a>],
     ) -> ProgramResult {
         // Unpack our vault accounts
-        let vault_x_account = spl_token::state::Account::unpack(vault_x.data.borrow().as_ref())?;
-        let vault_y_account = spl_token::state::Account::unpack(vault_y.data.borrow().as_ref())?;
+        let vault_x_account = unpack_token_account(vault_x)?;
+        let vault_y_account = unpack_token_account(vault_y)?;
 
         // Get our mint decimals
-        let mint_x_decimals = Mint::unpack(mint_x.data.borrow().as_ref())?.decimals;
-        let mint_y_decimals = Mint::unpack(mint_y.data.borrow().as_ref())?.decimals;
+        let mint_x_decimals = unpack_mint(mint_x)?.decimals;
+        let mint_y_decimals = unpack_mint(mint_y)?.decimals;
 
         // No need for additional checks as token transfer will fail for an invalid mint
         let is_x = config_account.mint_x.eq(
-            <spl_token::state::Account as GenericTokenAccount>::unpack_account_mint(
+            Account::unpack_account_mint(
                 user_from.data.borrow().as_ref(),
             )
             .ok_or(ProgramError::InvalidAccountData)?,
         );
 
-        // Determine swap direction and fee calculation
-        let (amount_out, _) = if is_x {
-            delta_y_from_x_swap_amount_with_fee(vault_x_account.amount, vault_y_account.amount, amount, config_account.fee)
-        } else {
-            delta_x_from_y_swap_amount_with_fee(vault_y_account.amount, vault_x_account.amount, amount, config_account.fee)
+        let (mint_from, mint_to) = if is_x { (mint_x, mint_y) } else { (mint_y, mint_x) };
+
+        // Price on the amount that reaches the vault after transfer fees
+        let amount_in = amount - get_transfer_fee(mint_from, amount)?;
+
+        // Determine the output and fee
+        let (_, amount_out, fee) = {
+            let mut config_account = Config::load_mut(config)?;
+            // Record an observation from the pre-trade state
+            config_account.record_observation(config.key, vault_x_account.amount, vault_y_account.amount, remaining_accounts)?;
+            config_account.swap_amounts(config.key, amount_in, is_x, true, vault_x_account.amount, vault_y_account.amount, remaining_accounts, false)?
+        };
+
+        // Slippage check on the amount the user receives after transfer fees
+        let amount_received = amount_out - get_transfer_fee(mint_to, amount_out)?;
+        if amount_received < min {
+            return Err(AmmError::SlippageExceeded.into());
         }
-        .map_err(|_| ProgramError::ArithmeticOverflow)?;
 
-        // Slippage check
-        assert!(amount_out >= min);
+        // Execute the swap. Flash swaps pay out first, then the callback must pay the vault before we check the reserves
+        if let Some((callback_program, callback_data)) = callback {
+            let (token_program_to, decimals_to, vault_from, vault_to, balance_from, balance_to) = match is_x {
+                true => (token_program_y, mint_y_decimals, vault_x, vault_y, vault_x_account.amount, vault_y_account.amount),
+                false => (token_program_x, mint_x_decimals, vault_y, vault_x, vault_y_account.amount, vault_x_account.amount),
+            };
 
-        // Execute the swap
-        if is_x {
+            withdraw(
+                token_program_to,
+                user_to,
+                mint_to,
+                vault_to,
+                config,
+                amount_out,
+                decimals_to,
+                &[b"config", config_account.seed.to_le_bytes().as_ref(), &[config_account.config_bump]],
+                remaining_accounts,
+            )?;
+
+            // Hold the guard while the callback has control, so it can't trade against the pool before paying it
+            Config::load_mut(config)?.reentrancy_guard = 1;
+            invoke_callback(callback_program, callback_data, remaining_accounts)?;
+            Config::load_mut(config)?.reentrancy_guard = 0;
+
+            // The vaults must end up at or beyond the point the swap was priced at, so the curve's invariant holds with the fee included
+            let required_from = balance_from.checked_add(amount_in).ok_or(ProgramError::ArithmeticOverflow)?;
+            let required_to = balance_to.checked_sub(amount_out).ok_or(ProgramError::ArithmeticOverflow)?;
+            if unpack_token_account(vault_from)?.amount < required_from || unpack_token_account(vault_to)?.amount < required_to {
+                return Err(AmmError::InvariantViolated.into());
+            }
+        } else if is_x {
             execute_swap(
-                token_program,
+                token_program_x,
+                token_program_y,
                 amount,
                 amount_out,
                 config_account,
                 mint_x_decimals,
                 mint_y_decimals,
                 config,
+                user,
                 user_from,
                 user_to,
                 mint_x,
                 mint_y,
                 vault_x,
                 vault_y,
-            )
+                remaining_accounts,
+            )?;
         } else {
             execute_swap(
-                token_program,
+                token_program_y,
+                token_program_x,
                 amount,
                 amount_out,
                 config_account,
-                mint_x_decimals,
                 mint_y_decimals,
+                mint_x_decimals,
                 config,
+                user,
                 user_from,
                 user_to,
                 mint_y,
                 mint_x,
                 vault_y,
                 vault_x,
+                remaining_accounts,
+            )?;
+        }
+
+        let (reserve_x, reserve_y) = Config::load(config)?.vault_reserves(vault_x, vault_y)?;
+        AmmEvent::Swap(SwapEvent {
+            pool: *config.key,
+            user: *user.key,
+            amount_in: amount,
+            amount_out,
+            fee,
+            reserve_x,
+            reserve_y,
+            is_x: is_x as u8,
+            padding: [0; 7],
+        })
+        .emit();
+
+        Ok(())
+    }
+
+    pub fn perform_swap_exact_out<'a>(
+        config_account: &Config,
+        token_program_x: &Pubkey,
+        token_program_y: &Pubkey,
+        amount_out: u64,
+        max_in: u64,
+        mint_x: &AccountInfo<'a>,
+        mint_y: &AccountInfo<'a>,
+        vault_x: &AccountInfo<'a>,
+        vault_y: &AccountInfo<'a>,
+        user: &AccountInfo<'a>,
+        user_from: &AccountInfo<'a>,
+        user_to: &AccountInfo<'a>,
+        config: &AccountInfo<'a>,
+        remaining_accounts: &[AccountInfo<'a>],
+    ) -> ProgramResult {
+        // Unpack our vault accounts
+        let vault_x_account = unpack_token_account(vault_x)?;
+        let vault_y_account = unpack_token_account(vault_y)?;
+
+        // Get our mint decimals
+        let mint_x_decimals = unpack_mint(mint_x)?.decimals;
+        let mint_y_decimals = unpack_mint(mint_y)?.decimals;
+
+        // No need for additional checks as token transfer will fail for an invalid mint
+        let is_x = config_account.mint_x.eq(
+            Account::unpack_account_mint(
+                user_from.data.borrow().as_ref(),
             )
+            .ok_or(ProgramError::InvalidAccountData)?,
+        );
+
+        let (mint_from, mint_to) = if is_x { (mint_x, mint_y) } else { (mint_y, mint_x) };
+
+        // Gross up the output so the user receives amount_out after transfer fees
+        let amount_sent = amount_out
+            .checked_add(get_transfer_inverse_fee(mint_to, amount_out)?)
+            .ok_or(ProgramError::ArithmeticOverflow)?;
+
+        // Determine the input the vault must receive, rounded in the pool's favour
+        let (amount_in, _, fee) = {
+            let mut config_account = Config::load_mut(config)?;
+            // Record an observation from the pre-trade state
+            config_account.record_observation(config.key, vault_x_account.amount, vault_y_account.amount, remaining_accounts)?;
+            config_account.swap_amounts(config.key, amount_sent, is_x, false, vault_x_account.amount, vault_y_account.amount, remaining_accounts, false)?
+        };
+
+        // Gross up the input so the vault receives amount_in after transfer fees
+        let amount = amount_in
+            .checked_add(get_transfer_inverse_fee(mint_from, amount_in)?)
+            .ok_or(ProgramError::ArithmeticOverflow)?;
+
+        // Slippage check
+        if amount > max_in {
+            return Err(AmmError::SlippageExceeded.into());
         }
+
+        // Execute the swap
+        if is_x {
+            execute_swap(
+                token_program_x,
+                token_program_y,
+                amount,
+                amount_sent,
+                config_account,
+                mint_x_decimals,
+                mint_y_decimals,
+                config,
+                user,
+                user_from,
+                user_to,
+                mint_x,
+                mint_y,
+                vault_x,
+                vault_y,
+                remaining_accounts,
+            )?;
+        } else {
+            execute_swap(
+                token_program_y,
+                token_program_x,
+                amount,
+                amount_sent,
+                config_account,
+                mint_y_decimals,
+                mint_x_decimals,
+                config,
+                user,
+                user_from,
+                user_to,
+                mint_y,
+                mint_x,
+                vault_y,
+                vault_x,
+                remaining_accounts,
+            )?;
+        }
+
+        let (reserve_x, reserve_y) = Config::load(config)?.vault_reserves(vault_x, vault_y)?;
+        AmmEvent::Swap(SwapEvent {
+            pool: *config.key,
+            user: *user.key,
+            amount_in: amount,
+            amount_out: amount_sent,
+            fee,
+            reserve_x,
+            reserve_y,
+            is_x: is_x as u8,
+            padding: [0; 7],
+        })
+        .emit();
+
+        Ok(())
     }
+
+    pub fn perform_flash_loan<'a>(
+        config_account: &Config,
+        token_program: &Pubkey,
+        amount: u64,
+        mint: &AccountInfo<'a>,
+        vault: &AccountInfo<'a>,
+        borrower_to: &AccountInfo<'a>,
+        config: &AccountInfo<'a>,
+        callback_program: &AccountInfo<'a>,
+        callback_data: &[u8],
+        remaining_accounts: &[AccountInfo<'a>],
+    ) -> ProgramResult {
+        let balance = unpack_token_account(vault)?.amount;
+
+        // Flash fee, rounded up in the pool's favour
+        let fee = (amount as u128 * config_account.flash_fee as u128).div_ceil(10_000) as u64;
+
+        // Lend the tokens to the borrower
+        withdraw(
+            token_program,
+            borrower_to,
+            mint,
+            vault,
+            config,
+            amount,
+            unpack_mint(mint)?.decimals,
+            &[b"config", config_account.seed.to_le_bytes().as_ref(), &[config_account.config_bump]],
+            remaining_accounts,
+        )?;
+
+        // Hand control to the borrower, who must pay back the vault before returning, holding the guard until it does
+        Config::load_mut(config)?.reentrancy_guard = 1;
+        invoke_callback(callback_program, callback_data, remaining_accounts)?;
+        Config::load_mut(config)?.reentrancy_guard = 0;
+
+        // The vault must hold at least the principal and fee on top of its balance before the loan
+        let repaid = unpack_token_account(vault)?
+            .amount
+            .checked_sub(balance)
+            .filter(|repaid| *repaid >= fee)
+            .ok_or(AmmError::FlashLoanNotRepaid)?;
+
+        // Concentrated LPs earn fees through fee growth rather than a share of the reserves
+        if CurveType::try_from(config_account.curve_type)? == CurveType::ConcentratedLiquidity {
+            let mut config_account = Config::load_mut(config)?;
+            let liquidity = u128::from(config_account.liquidity);
+            if liquidity > 0 {
+                let fee_growth = mul_div(repaid as u128, Q64, liquidity).ok_or(ProgramError::ArithmeticOverflow)?;
+                match mint.key.eq(&config_account.mint_x) {
+                    true => config_account.fee_growth_global_x = u128::from(config_account.fee_growth_global_x).wrapping_add(fee_growth).into(),
+                    false => config_account.fee_growth_global_y = u128::from(config_account.fee_growth_global_y).wrapping_add(fee_growth).into(),
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn perform_quote<'a>(
+        config_account: &Config,
+        kind: QuoteKind,
+        amount: u64,
+        max_x: u64,
+        max_y: u64,
+        is_x: bool,
+        mint_x: &AccountInfo<'a>,
+        mint_y: &AccountInfo<'a>,
+        mint_lp: &AccountInfo<'a>,
+        vault_x: &AccountInfo<'a>,
+        vault_y: &AccountInfo<'a>,
+        config: &AccountInfo<'a>,
+        remaining_accounts: &[AccountInfo<'a>],
+    ) -> ProgramResult {
+        // Unpack our vault accounts
+        let vault_x_account = unpack_token_account(vault_x)?;
+        let vault_y_account = unpack_token_account(vault_y)?;
+
+        let (mint_from, mint_to) = if is_x { (mint_x, mint_y) } else { (mint_y, mint_x) };
+
+        // Swaps are priced on a copy of the config, so nothing is written back
+        let mut pool = *config_account;
+        let mut quote = QuoteAmounts::zeroed();
+        match kind {
+            QuoteKind::Swap => {
+                let amount_in = amount - get_transfer_fee(mint_from, amount)?;
+                let (_, amount_out, fee) = pool.swap_amounts(config.key, amount_in, is_x, true, vault_x_account.amount, vault_y_account.amount, remaining_accounts, true)?;
+                quote.amount_in = amount;
+                quote.amount_out = amount_out - get_transfer_fee(mint_to, amount_out)?;
+                quote.fee = fee;
+            }
+            QuoteKind::SwapExactOut => {
+                let amount_sent = amount
+                    .checked_add(get_transfer_inverse_fee(mint_to, amount)?)
+                    .ok_or(ProgramError::ArithmeticOverflow)?;
+                let (amount_in, _, fee) = pool.swap_amounts(config.key, amount_sent, is_x, false, vault_x_account.amount, vault_y_account.amount, remaining_accounts, true)?;
+                quote.amount_in = amount_in
+                    .checked_add(get_transfer_inverse_fee(mint_from, amount_in)?)
+                    .ok_or(ProgramError::ArithmeticOverflow)?;
+                quote.amount_out = amount;
+                quote.fee = fee;
+            }
+            QuoteKind::Deposit => {
+                let (x, y, lp) = config_account.user_deposit_amounts(
+                    amount,
+                    max_x,
+                    max_y,
+                    mint_x,
+                    mint_y,
+                    vault_x_account.amount,
+                    vault_y_account.amount,
+                    unpack_mint(mint_lp)?.supply,
+                )?;
+                (quote.amount_x, quote.amount_y, quote.lp) = (x, y, lp);
+            }
+            QuoteKind::Withdraw => {
+                let (x, y) = config_account.user_withdraw_amounts(amount, vault_x_account.amount, vault_y_account.amount, unpack_mint(mint_lp)?.supply)?;
+                quote.amount_x = x - get_transfer_fee(mint_x, x)?;
+                quote.amount_y = y - get_transfer_fee(mint_y, y)?;
+                quote.lp = amount;
+            }
+        }
+
+        set_return_data(bytemuck::bytes_of(&quote));
+        Ok(())
+    }
+
+    pub fn perform_increase_liquidity<'a>(
+        liquidity: u128,
+        max_x: u64,
+        max_y: u64,
+        token_program_x: &Pubkey,
+        token_program_y: &Pubkey,
+        owner_x: &AccountInfo<'a>,
+        owner_y: &AccountInfo<'a>,
+        vault_x: &AccountInfo<'a>,
+        vault_y: &AccountInfo<'a>,
+        mint_x: &AccountInfo<'a>,
+        mint_y: &AccountInfo<'a>,
+        config: &AccountInfo<'a>,
+        position: &AccountInfo<'a>,
+        tick_array_lower: &AccountInfo<'a>,
+        tick_array_upper: &AccountInfo<'a>,
+        owner: &AccountInfo<'a>,
+        remaining_accounts: &[AccountInfo<'a>],
+    ) -> ProgramResult {
+        let liquidity_delta = i128::try_from(liquidity).map_err(|_| ProgramError::ArithmeticOverflow)?;
+
+        // Add the liquidity to the position, rounding the amounts owed up in the pool's favour
+        let (x, y) = {
+            let mut config_account = Config::load_mut(config)?;
+            let (vault_x_amount, vault_y_amount) = (unpack_token_account(vault_x)?.amount, unpack_token_account(vault_y)?.amount);
+            config_account.record_observation(config.key, vault_x_amount, vault_y_amount, remaining_accounts)?;
+            let mut position_account = Position::load_mut(position, config.key)?;
+            if position_account.owner.ne(owner.key) {
+                return Err(AmmError::InvalidPosition.into());
+            }
+            config_account.modify_position(config.key, &mut position_account, tick_array_lower, tick_array_upper, liquidity_delta)?
+        };
+
+        // Gross up so the vaults receive X and Y after transfer fees
+        let x = x.checked_add(get_transfer_inverse_fee(mint_x, x)?).ok_or(ProgramError::ArithmeticOverflow)?;
+        let y = y.checked_add(get_transfer_inverse_fee(mint_y, y)?).ok_or(ProgramError::ArithmeticOverflow)?;
+
+        // Slippage check
+        if x > max_x || y > max_y {
+            return Err(AmmError::SlippageExceeded.into());
+        }
+
+        // Transfer the funds from the owner's token X account to the vault
+        deposit(
+            token_program_x,
+            owner_x,
+            mint_x,
+            vault_x,
+            owner,
+            x,
+            unpack_mint(mint_x)?.decimals,
+            remaining_accounts,
+        )?;
+
+        // Transfer the funds from the owner's token Y account to the vault
+        deposit(
+            token_program_y,
+            owner_y,
+            mint_y,
+            vault_y,
+            owner,
+            y,
+            unpack_mint(mint_y)?.decimals,
+            remaining_accounts,
+        )
+    }
+
+    pub fn perform_decrease_liquidity<'a>(
+        liquidity: u128,
+        min_x: u64,
+        min_y: u64,
+        config_account: &Config,
+        token_program_x: &Pubkey,
+        token_program_y: &Pubkey,
+        owner_x: &AccountInfo<'a>,
+        owner_y: &AccountInfo<'a>,
+        vault_x: &AccountInfo<'a>,
+        vault_y: &AccountInfo<'a>,
+        mint_x: &AccountInfo<'a>,
+        mint_y: &AccountInfo<'a>,
+        config: &AccountInfo<'a>,
+        position: &AccountInfo<'a>,
+        tick_array_lower: &AccountInfo<'a>,
+        tick_array_upper: &AccountInfo<'a>,
+        owner: &AccountInfo<'a>,
+        remaining_accounts: &[AccountInfo<'a>],
+    ) -> ProgramResult {
+        let liquidity_delta = i128::try_from(liquidity).map_err(|_| ProgramError::ArithmeticOverflow)?;
+
+        // Remove the liquidity from the position, rounding the amounts returned down, and pay out the fees owed with it
+        let (x, y) = {
+            let mut config_account = Config::load_mut(config)?;
+            let (vault_x_amount, vault_y_amount) = (unpack_token_account(vault_x)?.amount, unpack_token_account(vault_y)?.amount);
+            config_account.record_observation(config.key, vault_x_amount, vault_y_amount, remaining_accounts)?;
+            let mut position_account = Position::load_mut(position, config.key)?;
+            if position_account.owner.ne(owner.key) {
+                return Err(AmmError::InvalidPosition.into());
+            }
+            let (x, y) = config_account.modify_position(config.key, &mut position_account, tick_array_lower, tick_array_upper, -liquidity_delta)?;
+            let x = x.checked_add(position_account.tokens_owed_x).ok_or(ProgramError::ArithmeticOverflow)?;
+            let y = y.checked_add(position_account.tokens_owed_y).ok_or(ProgramError::ArithmeticOverflow)?;
+            position_account.tokens_owed_x = 0;
+            position_account.tokens_owed_y = 0;
+            (x, y)
+        };
+
+        // Slippage check on the amounts received after transfer fees
+        if x - get_transfer_fee(mint_x, x)? < min_x || y - get_transfer_fee(mint_y, y)? < min_y {
+            return Err(AmmError::SlippageExceeded.into());
+        }
+
+        // Transfer the funds from the vault to the owner's token X account
+        withdraw(
+            token_program_x,
+            owner_x,
+            mint_x,
+            vault_x,
+            config,
+            x,
+            unpack_mint(mint_x)?.decimals,
+            &[b"config", config_account.seed.to_le_bytes().as_ref(), &[config_account.config_bump]],
+            remaining_accounts,
+        )?;
+
+        // Transfer the funds from the vault to the owner's token Y account
+        withdraw(
+            token_program_y,
+            owner_y,
+            mint_y,
+            vault_y,
+            config,
+            y,
+            unpack_mint(mint_y)?.decimals,
+            &[b"config", config_account.seed.to_le_bytes().as_ref(), &[config_account.config_bump]],
+            remaining_accounts,
+        )
+    }
+
+    pub fn perform_collect_protocol_fees<'a>(
+        config_account: &Config,
+        token_program_x: &Pubkey,
+        token_program_y: &Pubkey,
+        treasury_x: &AccountInfo<'a>,
+        treasury_y: &AccountInfo<'a>,
+        vault_x: &AccountInfo<'a>,
+        vault_y: &AccountInfo<'a>,
+        mint_x: &AccountInfo<'a>,
+        mint_y: &AccountInfo<'a>,
+        config: &AccountInfo<'a>,
+        remaining_accounts: &[AccountInfo<'a>],
+    ) -> ProgramResult {
+        // Clear the amounts owed before paying them out
+        let (x, y) = {
+            let mut config_account = Config::load_mut(config)?;
+            let owed = (config_account.protocol_fees_x, config_account.protocol_fees_y);
+            config_account.protocol_fees_x = 0;
+            config_account.protocol_fees_y = 0;
+            owed
+        };
+
+        // Transfer the protocol fees in X from the vault to the treasury
+        withdraw(
+            token_program_x,
+            treasury_x,
+            mint_x,
+            vault_x,
+            config,
+            x,
+            unpack_mint(mint_x)?.decimals,
+            &[b"config", config_account.seed.to_le_bytes().as_ref(), &[config_account.config_bump]],
+            remaining_accounts,
+        )?;
+
+        // Transfer the protocol fees in Y from the vault to the treasury
+        withdraw(
+            token_program_y,
+            treasury_y,
+            mint_y,
+            vault_y,
+            config,
+            y,
+            unpack_mint(mint_y)?.decimals,
+            &[b"config", config_account.seed.to_le_bytes().as_ref(), &[config_account.config_bump]],
+            remaining_accounts,
+        )
+    }
+
+    // Apply a liquidity change to a position and the ticks bounding it, returning the X and Y it is worth
+    pub fn modify_position(
+        &mut self,
+        config: &Pubkey,
+        position: &mut Position,
+        tick_array_lower: &AccountInfo,
+        tick_array_upper: &AccountInfo,
+        liquidity_delta: i128,
+    ) -> Result<(u64, u64), ProgramError> {
+        if self.curve_type != CurveType::ConcentratedLiquidity as u8 {
+            return Err(AmmError::WrongCurveType.into());
+        }
+        let (tick_lower, tick_upper, tick_spacing) = (position.tick_lower, position.tick_upper, self.tick_spacing);
+        let fee_growth_global_x = u128::from(self.fee_growth_global_x);
+        let fee_growth_global_y = u128::from(self.fee_growth_global_y);
+
+        // Both bounding ticks may live in the same tick array
+        let mut lower_array = TickArray::load_mut(tick_array_lower, config)?;
+        let mut upper_array = match tick_array_lower.key.eq(tick_array_upper.key) {
+            true => None,
+            false => Some(TickArray::load_mut(tick_array_upper, config)?),
+        };
+
+        let lower = lower_array.tick_mut(tick_lower, tick_spacing)?;
+        lower.update(tick_lower, self.tick_current, liquidity_delta, fee_growth_global_x, fee_growth_global_y, false)?;
+        let lower = *lower;
+
+        let upper = upper_array.as_mut().unwrap_or(&mut lower_array).tick_mut(tick_upper, tick_spacing)?;
+        upper.update(tick_upper, self.tick_current, liquidity_delta, fee_growth_global_x, fee_growth_global_y, true)?;
+
+        // Settle the fees earned since the last checkpoint on the liquidity held until now
+        let (fee_growth_inside_x, fee_growth_inside_y) = fee_growth_inside(
+            &lower,
+            upper,
+            tick_lower,
+            tick_upper,
+            self.tick_current,
+            fee_growth_global_x,
+            fee_growth_global_y,
+        );
+        let liquidity = u128::from(position.liquidity);
+        position.tokens_owed_x = position.tokens_owed_x
+            .checked_add(fees_owed(fee_growth_inside_x, position.fee_growth_inside_x_last.into(), liquidity)?)
+            .ok_or(ProgramError::ArithmeticOverflow)?;
+        position.tokens_owed_y = position.tokens_owed_y
+            .checked_add(fees_owed(fee_growth_inside_y, position.fee_growth_inside_y_last.into(), liquidity)?)
+            .ok_or(ProgramError::ArithmeticOverflow)?;
+        position.fee_growth_inside_x_last = fee_growth_inside_x.into();
+        position.fee_growth_inside_y_last = fee_growth_inside_y.into();
+        position.liquidity = add_liquidity_delta(liquidity, liquidity_delta)?.into();
+
+        // Clear ticks no position references any more
+        if u128::from(upper.liquidity_gross) == 0 {
+            *upper = Tick::zeroed();
+        }
+        let lower = lower_array.tick_mut(tick_lower, tick_spacing)?;
+        if u128::from(lower.liquidity_gross) == 0 {
+            *lower = Tick::zeroed();
+        }
+
+        // Only liquidity in range at the current price is active
+        if tick_lower <= self.tick_current && self.tick_current < tick_upper {
+            self.liquidity = add_liquidity_delta(self.liquidity.into(), liquidity_delta)?.into();
+        }
+
+        xy_amounts_from_liquidity(
+            self.sqrt_price.into(),
+            sqrt_price_from_tick(tick_lower).map_err(|_| ProgramError::ArithmeticOverflow)?,
+            sqrt_price_from_tick(tick_upper).map_err(|_| ProgramError::ArithmeticOverflow)?,
+            liquidity_delta.unsigned_abs(),
+            liquidity_delta > 0,
+        )
+        .map_err(|_| ProgramError::ArithmeticOverflow)
+    }
+
+    // Swap through the ticks of a concentrated liquidity pool, returning the amounts in and out
+    // The fee is charged on the input and, less the protocol's share, accrues to in-range liquidity as fee growth
+    pub fn swap_concentrated(
+        &mut self,
+        config: &Pubkey,
+        amount: u64,
+        direction: SwapDirection,
+        exact_in: bool,
+        remaining_accounts: &[AccountInfo],
+        simulate: bool,
+    ) -> Result<(u64, u64, u64), ProgramError> {
+        let x_to_y = direction == SwapDirection::XToY;
+        let tick_spacing = self.tick_spacing;
+        let ticks_in_array = tick_spacing as i32 * TICK_ARRAY_SIZE as i32;
+
+        // Tick arrays are passed alongside any transfer hook accounts, observations and other pools' tick arrays on a route,
+        // and must cover every tick crossed
+        let mut tick_arrays = remaining_accounts
+            .iter()
+            .filter(|account| {
+                account.owner.eq(&crate::ID)
+                    && account.data_len() == core::mem::size_of::<TickArray>()
+                    && !Observations::is_observations(account)
+                    && account.try_borrow_data().is_ok_and(|data| data.starts_with(config.as_ref()))
+            })
+            .map(|account| TickArray::load_mut(account, config))
+            .collect::<Result<Vec<_>, _>>()?;
+        tick_arrays.sort_by_key(|tick_array| tick_array.start_tick_index);
+        if tick_arrays.windows(2).any(|pair| pair[1].start_tick_index != pair[0].start_tick_index + ticks_in_array) {
+            return Err(AmmError::InvalidTickArray.into());
+        }
+        let first_tick = tick_arrays.first().ok_or(ProgramError::NotEnoughAccountKeys)?.start_tick_index;
+        let end_tick = tick_arrays.last().ok_or(ProgramError::NotEnoughAccountKeys)?.start_tick_index + ticks_in_array;
+        if self.tick_current < first_tick || self.tick_current >= end_tick {
+            return Err(AmmError::InvalidTickArray.into());
+        }
+
+        let mut amount_remaining = amount;
+        let mut amount_calculated = 0u64;
+        let mut fee_amount = 0u64;
+        let mut sqrt_price = u128::from(self.sqrt_price);
+        let mut liquidity = u128::from(self.liquidity);
+        let mut fee_growth_global = u128::from(if x_to_y { self.fee_growth_global_x } else { self.fee_growth_global_y });
+
+        while amount_remaining > 0 {
+            // Step to the next initialized tick, or to the edge of the tick arrays we were given
+            let next_tick = next_initialized_tick(&tick_arrays, self.tick_current, tick_spacing, x_to_y);
+            let tick_next = match (next_tick, x_to_y) {
+                (Some(tick), _) => tick,
+                (None, true) => first_tick.max(MIN_TICK),
+                (None, false) => end_tick.min(MAX_TICK),
+            };
+            let sqrt_price_target = sqrt_price_from_tick(tick_next).map_err(|_| ProgramError::ArithmeticOverflow)?;
+
+            let step = compute_swap_step(sqrt_price, sqrt_price_target, liquidity, amount_remaining, self.fee, exact_in)
+                .map_err(|_| ProgramError::ArithmeticOverflow)?;
+            sqrt_price = step.sqrt_price_next;
+            if exact_in {
+                amount_remaining -= step.amount_in + step.fee_amount;
+                amount_calculated = amount_calculated.checked_add(step.amount_out).ok_or(ProgramError::ArithmeticOverflow)?;
+            } else {
+                amount_remaining -= step.amount_out;
+                amount_calculated = amount_calculated
+                    .checked_add(step.amount_in + step.fee_amount)
+                    .ok_or(ProgramError::ArithmeticOverflow)?;
+            }
+
+            // The fee is kept from the input token, and the LPs' share grows fees per unit of in-range liquidity
+            fee_amount = fee_amount.checked_add(step.fee_amount).ok_or(ProgramError::ArithmeticOverflow)?;
+            let lp_fee = self.accrue_protocol_fee(step.fee_amount, x_to_y)?;
+            if liquidity > 0 {
+                fee_growth_global = fee_growth_global.wrapping_add(
+                    mul_div(lp_fee as u128, Q64, liquidity).ok_or(ProgramError::ArithmeticOverflow)?,
+                );
+            }
+
+            if sqrt_price == sqrt_price_target {
+                // Stopping at the edge of the tick arrays is fine, swapping past it would skip ticks we cannot see
+                if next_tick.is_none() && amount_remaining != 0 {
+                    return Err(AmmError::InsufficientLiquidity.into());
+                }
+                if next_tick.is_some() {
+                    let (fee_growth_global_x, fee_growth_global_y) = match x_to_y {
+                        true => (fee_growth_global, self.fee_growth_global_y.into()),
+                        false => (self.fee_growth_global_x.into(), fee_growth_global),
+                    };
+                    // Simulated swaps leave the ticks' fee growth untouched
+                    let tick = find_tick_mut(&mut tick_arrays, tick_next, tick_spacing)?;
+                    let liquidity_net = match simulate {
+                        true => tick.liquidity_net(),
+                        false => tick.cross(fee_growth_global_x, fee_growth_global_y),
+                    };
+                    // Moving down crosses the tick in reverse
+                    liquidity = match x_to_y {
+                        true => add_liquidity_delta(liquidity, liquidity_net.checked_neg().ok_or(ProgramError::ArithmeticOverflow)?)?,
+                        false => add_liquidity_delta(liquidity, liquidity_net)?,
+                    };
+                }
+                self.tick_current = match next_tick.is_some() && !x_to_y {
+                    true => tick_next,
+                    false => tick_next - 1,
+                };
+            } else {
+                self.tick_current = tick_from_sqrt_price(sqrt_price).map_err(|_| ProgramError::ArithmeticOverflow)?;
+            }
+        }
+
+        match x_to_y {
+            true => self.fee_growth_global_x = fee_growth_global.into(),
+            false => self.fee_growth_global_y = fee_growth_global.into(),
+        }
+        self.sqrt_price = sqrt_price.into();
+        self.liquidity = liquidity.into();
+
+        Ok(match exact_in {
+            true => (amount, amount_calculated, fee_amount),
+            false => (amount_calculated, amount, fee_amount),
+        })
+    }
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+pub struct Tick {
+    pub liquidity_net: [u8; 16], // Little-endian i128 added to the pool's liquidity when the price crosses upwards
+    pub liquidity_gross: PodU128,
+    pub fee_growth_outside_x: PodU128,
+    pub fee_growth_outside_y: PodU128,
+}
+
+impl Tick {
+    pub fn liquidity_net(&self) -> i128 {
+        i128::from_le_bytes(self.liquidity_net)
+    }
+
+    // Add a position's liquidity change to one of its bounding ticks
+    pub fn update(
+        &mut self,
+        tick: i32,
+        tick_current: i32,
+        liquidity_delta: i128,
+        fee_growth_global_x: u128,
+        fee_growth_global_y: u128,
+        upper: bool,
+    ) -> ProgramResult {
+        let liquidity_gross = u128::from(self.liquidity_gross);
+
+        // By convention, all fee growth before a tick is initialized happened below it
+        if liquidity_gross == 0 && tick <= tick_current {
+            self.fee_growth_outside_x = fee_growth_global_x.into();
+            self.fee_growth_outside_y = fee_growth_global_y.into();
+        }
+
+        self.liquidity_gross = add_liquidity_delta(liquidity_gross, liquidity_delta)?.into();
+        self.liquidity_net = match upper {
+            true => self.liquidity_net().checked_sub(liquidity_delta),
+            false => self.liquidity_net().checked_add(liquidity_delta),
+        }
+        .ok_or(ProgramError::ArithmeticOverflow)?
+        .to_le_bytes();
+
+        Ok(())
+    }
+
+    // Flip the fee growth outside the tick as the price crosses it, returning its net liquidity
+    pub fn cross(&mut self, fee_growth_global_x: u128, fee_growth_global_y: u128) -> i128 {
+        self.fee_growth_outside_x = fee_growth_global_x.wrapping_sub(self.fee_growth_outside_x.into()).into();
+        self.fee_growth_outside_y = fee_growth_global_y.wrapping_sub(self.fee_growth_outside_y.into()).into();
+        self.liquidity_net()
+    }
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+pub struct TickArray {
+    pub config: Pubkey,
+    pub start_tick_index: i32,
+    pub bump: u8,
+    pub padding: [u8; 3],
+    pub ticks: [Tick; TICK_ARRAY_SIZE],
+}
+
+impl TickArray {
+    // Borrow a tick array of a pool from its account
+    pub fn load_mut<'a>(tick_array: &'a AccountInfo, config: &Pubkey) -> Result<RefMut<'a, TickArray>, ProgramError> {
+        let tick_array = load_account_mut::<TickArray>(tick_array)?;
+        if tick_array.config.ne(config) {
+            return Err(AmmError::InvalidTickArray.into());
+        }
+        Ok(tick_array)
+    }
+
+    // Get the first tick of the tick array holding a tick
+    pub fn start_tick_index(tick: i32, tick_spacing: u16) -> i32 {
+        let ticks_in_array = tick_spacing as i32 * TICK_ARRAY_SIZE as i32;
+        tick.div_euclid(ticks_in_array) * ticks_in_array
+    }
+
+    pub fn tick_mut(&mut self, tick: i32, tick_spacing: u16) -> Result<&mut Tick, ProgramError> {
+        let offset = tick - self.start_tick_index;
+        if offset < 0 || offset % tick_spacing as i32 != 0 {
+            return Err(AmmError::InvalidTick.into());
+        }
+        self.ticks
+            .get_mut((offset / tick_spacing as i32) as usize)
+            .ok_or(ProgramError::InvalidArgument)
+    }
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+pub struct Position {
+    pub config: Pubkey,
+    pub owner: Pubkey,
+    pub tick_lower: i32,
+    pub tick_upper: i32,
+    pub liquidity: PodU128,
+    pub fee_growth_inside_x_last: PodU128,
+    pub fee_growth_inside_y_last: PodU128,
+    pub tokens_owed_x: u64,
+    pub tokens_owed_y: u64,
+    pub bump: u8,
+    pub padding: [u8; 7],
+}
+
+impl Position {
+    // Borrow a position in a pool from its account
+    pub fn load_mut<'a>(position: &'a AccountInfo, config: &Pubkey) -> Result<RefMut<'a, Position>, ProgramError> {
+        let position = load_account_mut::<Position>(position)?;
+        if position.config.ne(config) {
+            return Err(AmmError::InvalidPosition.into());
+        }
+        Ok(position)
+    }
+}
+
+// Accumulators of a pool as of a timestamp
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+pub struct Observation {
+    pub timestamp: i64,
+    pub price_x_cumulative: PodU128,
+    pub price_y_cumulative: PodU128,
+    // Pool liquidity times seconds
+    pub liquidity_cumulative: PodU128,
+}
+
+// Header of a pool's observations account, followed by a ring buffer of cardinality_next observations
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+pub struct Observations {
+    pub discriminator: [u8; 8],
+    pub config: Pubkey,
+    // Slot of the most recent observation
+    pub index: u16,
+    // Slots in use, growing to cardinality_next once the ring wraps
+    pub cardinality: u16,
+    pub cardinality_next: u16,
+    pub bump: u8,
+    pub padding: [u8; 1],
+}
+
+impl Observations {
+    // Account size holding a number of observations
+    pub fn space(cardinality: u16) -> usize {
+        core::mem::size_of::<Observations>() + cardinality as usize * core::mem::size_of::<Observation>()
+    }
+
+    pub fn is_observations(account: &AccountInfo) -> bool {
+        account.owner.eq(&crate::ID)
+            && account.try_borrow_data().is_ok_and(|data| data.starts_with(&OBSERVATIONS_DISCRIMINATOR))
+    }
+
+    // Whether an account holds the observations of a given pool, as routes may pass those of several pools
+    pub fn is_observations_of(account: &AccountInfo, config: &Pubkey) -> bool {
+        Observations::is_observations(account)
+            && account.try_borrow_data().is_ok_and(|data| data.get(8..40).is_some_and(|key| key.eq(config.as_ref())))
+    }
+
+    // Borrow the observations of a pool from its account, split into the header and ring buffer
+    pub fn load_mut<'a>(
+        account: &'a AccountInfo,
+        config: &Pubkey,
+    ) -> Result<(RefMut<'a, Observations>, RefMut<'a, [Observation]>), ProgramError> {
+        if account.owner.ne(&crate::ID) {
+            return Err(AmmError::InvalidAccountOwner.into());
+        }
+        let data = account.try_borrow_mut_data()?;
+        let header = core::mem::size_of::<Observations>();
+        if data.len() < header || !(data.len() - header).is_multiple_of(core::mem::size_of::<Observation>()) {
+            return Err(AmmError::InvalidObservations.into());
+        }
+        let (observations, entries) = RefMut::map_split(data, |data| {
+            let (observations, entries) = data.split_at_mut(header);
+            (bytemuck::from_bytes_mut::<Observations>(observations), bytemuck::cast_slice_mut::<u8, Observation>(entries))
+        });
+        if observations.discriminator.ne(&OBSERVATIONS_DISCRIMINATOR)
+            || observations.config.ne(config)
+            || entries.len() != observations.cardinality_next as usize
+        {
+            return Err(AmmError::InvalidObservations.into());
+        }
+        Ok((observations, entries))
+    }
+
+    // Write the pool's accumulators as of now into the next slot, at most once per second
+    pub fn write(
+        &mut self,
+        entries: &mut [Observation],
+        now: i64,
+        price_x_cumulative: u128,
+        price_y_cumulative: u128,
+        liquidity_cumulative: u128,
+    ) {
+        let last = entries[self.index as usize];
+        if last.timestamp >= now {
+            return;
+        }
+        // Grow into newly allocated slots once the ring reaches its end
+        if self.index + 1 == self.cardinality && self.cardinality_next > self.cardinality {
+            self.cardinality = self.cardinality_next;
+        }
+        self.index = (self.index + 1) % self.cardinality;
+        entries[self.index as usize] = Observation {
+            timestamp: now,
+            price_x_cumulative: price_x_cumulative.into(),
+            price_y_cumulative: price_y_cumulative.into(),
+            liquidity_cumulative: liquidity_cumulative.into(),
+        };
+    }
+}
+
+// Borrow an account owned by this program as T, checking its size
+pub fn load_account_mut<'a, T: Pod>(account: &'a AccountInfo) -> Result<RefMut<'a, T>, ProgramError> {
+    if account.owner.ne(&crate::ID) {
+        return Err(AmmError::InvalidAccountOwner.into());
+    }
+    RefMut::filter_map(account.try_borrow_mut_data()?, |data| bytemuck::try_from_bytes_mut::<T>(data).ok())
+        .map_err(|_| ProgramError::InvalidAccountData)
+}
+
+// L ± ΔL
+fn add_liquidity_delta(liquidity: u128, liquidity_delta: i128) -> Result<u128, ProgramError> {
+    match liquidity_delta < 0 {
+        true => liquidity.checked_sub(liquidity_delta.unsigned_abs()),
+        false => liquidity.checked_add(liquidity_delta as u128),
+    }
+    .ok_or(ProgramError::ArithmeticOverflow)
+}
+
+// Fee growth per unit of liquidity between two ticks
+// Inside = Global - Below - Above
+fn fee_growth_inside(
+    lower: &Tick,
+    upper: &Tick,
+    tick_lower: i32,
+    tick_upper: i32,
+    tick_current: i32,
+    fee_growth_global_x: u128,
+    fee_growth_global_y: u128,
+) -> (u128, u128) {
+    let inside = |global: u128, lower_outside: u128, upper_outside: u128| {
+        let below = if tick_current >= tick_lower { lower_outside } else { global.wrapping_sub(lower_outside) };
+        let above = if tick_current < tick_upper { upper_outside } else { global.wrapping_sub(upper_outside) };
+        global.wrapping_sub(below).wrapping_sub(above)
+    };
+    (
+        inside(fee_growth_global_x, lower.fee_growth_outside_x.into(), upper.fee_growth_outside_x.into()),
+        inside(fee_growth_global_y, lower.fee_growth_outside_y.into(), upper.fee_growth_outside_y.into()),
+    )
+}
+
+// Fees earned by liquidity L since its last checkpoint
+// Owed = (Inside - Last)·L
+fn fees_owed(fee_growth_inside: u128, fee_growth_inside_last: u128, liquidity: u128) -> Result<u64, ProgramError> {
+    u64::try_from(
+        mul_div(fee_growth_inside.wrapping_sub(fee_growth_inside_last), liquidity, Q64).ok_or(ProgramError::ArithmeticOverflow)?,
+    )
+    .map_err(|_| ProgramError::ArithmeticOverflow)
+}
+
+// Find the next initialized tick in the swap direction within the loaded tick arrays
+// Moving down includes the current tick, moving up starts above it
+fn next_initialized_tick(tick_arrays: &[RefMut<TickArray>], tick_current: i32, tick_spacing: u16, x_to_y: bool) -> Option<i32> {
+    let mut ticks = tick_arrays.iter().flat_map(|tick_array| {
+        tick_array.ticks
+            .iter()
+            .enumerate()
+            .filter(|(_, tick)| u128::from(tick.liquidity_gross) > 0)
+            .map(move |(i, _)| tick_array.start_tick_index + i as i32 * tick_spacing as i32)
+    });
+    match x_to_y {
+        true => ticks.rev().find(|tick| *tick <= tick_current),
+        false => ticks.find(|tick| *tick > tick_current),
+    }
+}
+
+fn find_tick_mut<'b>(tick_arrays: &'b mut [RefMut<TickArray>], tick: i32, tick_spacing: u16) -> Result<&'b mut Tick, ProgramError> {
+    let start_tick_index = TickArray::start_tick_index(tick, tick_spacing);
+    tick_arrays
+        .iter_mut()
+        .find(|tick_array| tick_array.start_tick_index == start_tick_index)
+        .ok_or(ProgramError::InvalidArgument)?
+        .tick_mut(tick, tick_spacing)
 }
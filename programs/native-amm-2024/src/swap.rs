@@ -1,4 +1,4 @@
-use crate::{utils::perform_basic_checks_with_no_lp, Config, Swap};
+use crate::{utils::{check_token_program, perform_basic_checks_with_no_lp}, Config, Swap};
 use solana_program::{
     account_info::AccountInfo,
     entrypoint::ProgramResult,
@@ -12,23 +12,26 @@ pub fn process(accounts: &[AccountInfo<'_>], data: &[u8]) -> ProgramResult {
         expiration, // Maximum time for white a swap is valid
     } = Swap::try_from(data)?;
 
-    let [user, mint_x, mint_y, user_from, user_to, vault_x, vault_y, config, token_program] =
+    let [user, mint_x, mint_y, user_from, user_to, vault_x, vault_y, config, token_program_x, token_program_y, remaining_accounts @ ..] =
         accounts
     else {
         return Err(ProgramError::NotEnoughAccountKeys);
     };
 
     // Ensure user is signer
-    assert!(user.is_signer);
+    if !user.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
 
-    // Assert we are using the correct TokenProgram
-    assert_eq!(token_program.key, &spl_token::ID);
+    // Assert we are using the correct TokenPrograms
+    check_token_program(token_program_x, mint_x)?;
+    check_token_program(token_program_y, mint_y)?;
 
     // Load our config account
-    let config_account = Config::try_from(config.data.borrow().as_ref())?;
+    let config_account = Config::load(config)?;
 
     // Perform basic checks
     perform_basic_checks_with_no_lp(&config_account, expiration, config, vault_x, vault_y)?;
 
-    Config::perform_swap(&config_account, token_program.key, amount, min, mint_x, mint_y, vault_x, vault_y, user_from, user_to, config)
+    Config::perform_swap(&config_account, token_program_x.key, token_program_y.key, amount, min, mint_x, mint_y, vault_x, vault_y, user, user_from, user_to, config, None, remaining_accounts)
 }
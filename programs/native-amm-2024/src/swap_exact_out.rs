@@ -0,0 +1,37 @@
+use crate::{utils::{check_token_program, perform_basic_checks_with_no_lp}, Config, SwapExactOut};
+use solana_program::{
+    account_info::AccountInfo,
+    entrypoint::ProgramResult,
+    program_error::ProgramError,
+};
+
+pub fn process(accounts: &[AccountInfo<'_>], data: &[u8]) -> ProgramResult {
+    let SwapExactOut {
+        amount_out, // Amount of tokens we want to receive
+        max_in,     // Maximum amount of tokens we're willing to deposit
+        expiration, // Maximum time for white a swap is valid
+    } = SwapExactOut::try_from(data)?;
+
+    let [user, mint_x, mint_y, user_from, user_to, vault_x, vault_y, config, token_program_x, token_program_y, remaining_accounts @ ..] =
+        accounts
+    else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    // Ensure user is signer
+    if !user.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    // Assert we are using the correct TokenPrograms
+    check_token_program(token_program_x, mint_x)?;
+    check_token_program(token_program_y, mint_y)?;
+
+    // Load our config account
+    let config_account = Config::load(config)?;
+
+    // Perform basic checks
+    perform_basic_checks_with_no_lp(&config_account, expiration, config, vault_x, vault_y)?;
+
+    Config::perform_swap_exact_out(&config_account, token_program_x.key, token_program_y.key, amount_out, max_in, mint_x, mint_y, vault_x, vault_y, user, user_from, user_to, config, remaining_accounts)
+}
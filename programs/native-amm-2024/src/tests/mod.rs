@@ -1,5 +1,6 @@
-use crate::{AMMInstructions, Config, Deposit, Initialize, Withdraw, Swap};
+use crate::{AMMInstructions, AmmError, AmmEvent, SwapEvent, Config, ConfigV0, CumulativePrices, DecreaseLiquidity, IncreaseLiquidity, IncreaseObservationCardinality, Position, TickArray, load_account_mut, Observation, Observations, ProposeAuthority, CONFIG_DISCRIMINATOR, MINIMUM_LIQUIDITY, OBSERVATIONS_DISCRIMINATOR, CONFIG_VERSION, Deposit, DepositSingle, FlashLoan, Initialize, InitializeTickArray, OpenPosition, Quote, QuoteAmounts, QuoteKind, RouteSwap, Withdraw, WithdrawSingle, Swap, SwapExactOut, UpdateFee};
 use bytemuck::bytes_of;
+use constant_product_curve::sqrt_u128;
 use core::mem;
 use std::i64;
 use mollusk_svm::{
@@ -8,10 +9,246 @@ use mollusk_svm::{
     Mollusk,
 };
 use solana_program::{
-    instruction::AccountMeta, instruction::Instruction, program_option::COption,
-    program_pack::Pack, pubkey::Pubkey,
+    account_info::AccountInfo, clock::Clock, entrypoint::{MAX_PERMITTED_DATA_INCREASE, SUCCESS}, instruction::AccountMeta,
+    instruction::Instruction, program_error::ProgramError, program_option::COption,
+    program_pack::Pack, program_stubs::{set_syscall_stubs, SyscallStubs},
+    program_utils::limited_deserialize, pubkey::Pubkey, rent::Rent, system_instruction::SystemInstruction,
+    system_program,
 };
-use solana_sdk::account::{AccountSharedData, WritableAccount};
+use solana_sdk::account::{AccountSharedData, ReadableAccount, WritableAccount};
+use spl_token_2022::extension::{
+    transfer_fee::{TransferFee, TransferFeeAmount, TransferFeeConfig},
+    BaseStateWithExtensionsMut, ExtensionType, StateWithExtensionsMut,
+};
+use std::cell::RefCell;
+
+// Mollusk doesn't hand back return data and we have no Token-2022 binary to load into it, so some tests run the
+// processor natively. Sysvars and return data live on the test's thread, and CPIs run through the native processors
+struct NativeSyscalls;
+
+thread_local! {
+    static NATIVE_CLOCK: RefCell<Clock> = RefCell::new(Clock::default());
+    static NATIVE_RETURN_DATA: RefCell<Option<(Pubkey, Vec<u8>)>> = const { RefCell::new(None) };
+}
+
+impl SyscallStubs for NativeSyscalls {
+    fn sol_get_clock_sysvar(&self, var_addr: *mut u8) -> u64 {
+        NATIVE_CLOCK.with(|clock| unsafe { *(var_addr as *mut Clock) = clock.borrow().clone() });
+        SUCCESS
+    }
+
+    fn sol_get_rent_sysvar(&self, var_addr: *mut u8) -> u64 {
+        unsafe { *(var_addr as *mut Rent) = Rent::default() };
+        SUCCESS
+    }
+
+    fn sol_set_return_data(&self, data: &[u8]) {
+        NATIVE_RETURN_DATA.with(|return_data| *return_data.borrow_mut() = Some((crate::ID, data.to_vec())));
+    }
+
+    fn sol_get_return_data(&self) -> Option<(Pubkey, Vec<u8>)> {
+        NATIVE_RETURN_DATA.with(|return_data| return_data.borrow().clone())
+    }
+
+    fn sol_invoke_signed(
+        &self,
+        instruction: &Instruction,
+        account_infos: &[AccountInfo],
+        _signers_seeds: &[&[&[u8]]],
+    ) -> Result<(), ProgramError> {
+        // Signer seeds aren't derived natively, so the callee sees the signers the instruction asks for
+        let accounts = instruction
+            .accounts
+            .iter()
+            .map(|meta| {
+                account_infos
+                    .iter()
+                    .find(|account| account.key.eq(&meta.pubkey))
+                    .map(|account| AccountInfo { is_signer: account.is_signer || meta.is_signer, ..account.clone() })
+                    .ok_or(ProgramError::NotEnoughAccountKeys)
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        if instruction.program_id.eq(&spl_token::ID) {
+            spl_token::processor::Processor::process(&instruction.program_id, &accounts, &instruction.data)
+        } else if instruction.program_id.eq(&spl_token_2022::ID) {
+            spl_token_2022::processor::Processor::process(&instruction.program_id, &accounts, &instruction.data)
+        } else if instruction.program_id.eq(&crate::ID) {
+            crate::process_instruction(&instruction.program_id, &accounts, &instruction.data)
+        } else if instruction.program_id.eq(&system_program::ID) {
+            process_native_system(&accounts, &instruction.data)
+        } else {
+            Err(ProgramError::IncorrectProgramId)
+        }
+    }
+}
+
+// Accounts can't be resized natively, so the system program only creates accounts passed in at their final size
+fn process_native_system(accounts: &[AccountInfo], data: &[u8]) -> Result<(), ProgramError> {
+    let move_lamports = |from: &AccountInfo, to: &AccountInfo, lamports: u64| -> Result<(), ProgramError> {
+        **from.try_borrow_mut_lamports()? = from.lamports().checked_sub(lamports).ok_or(ProgramError::InsufficientFunds)?;
+        **to.try_borrow_mut_lamports()? += lamports;
+        Ok(())
+    };
+    match limited_deserialize::<SystemInstruction>(data, solana_sdk::packet::PACKET_DATA_SIZE as u64).map_err(|_| ProgramError::InvalidInstructionData)? {
+        // Like the system program, refuse to create an account that already holds lamports
+        SystemInstruction::CreateAccount { lamports, space, owner } if accounts[1].lamports() == 0 && accounts[1].data_len() == space as usize => {
+            move_lamports(&accounts[0], &accounts[1], lamports)?;
+            accounts[1].assign(&owner);
+        }
+        SystemInstruction::Transfer { lamports } => move_lamports(&accounts[0], &accounts[1], lamports)?,
+        SystemInstruction::Allocate { space } if accounts[0].data_len() == space as usize => {}
+        SystemInstruction::Assign { owner } => accounts[0].assign(&owner),
+        _ => return Err(ProgramError::InvalidInstructionData),
+    }
+    Ok(())
+}
+
+// An account for the system program to create, with no lamports and zeroed data of its final size
+fn native_uninitialized_account(key: Pubkey, space: usize) -> AccountInfo<'static> {
+    AccountInfo::new(
+        Box::leak(Box::new(key)),
+        false,
+        true,
+        Box::leak(Box::new(0)),
+        Box::leak(vec![0; space].into_boxed_slice()),
+        Box::leak(Box::new(system_program::ID)),
+        false,
+        0,
+    )
+}
+
+// Leak an account's key, lamports and data so its AccountInfo outlives the test's setup
+fn native_account(key: Pubkey, owner: Pubkey, data: Vec<u8>, is_signer: bool, is_writable: bool) -> AccountInfo<'static> {
+    AccountInfo::new(
+        Box::leak(Box::new(key)),
+        is_signer,
+        is_writable,
+        Box::leak(Box::new(1_000_000_000)),
+        Box::leak(data.into_boxed_slice()),
+        Box::leak(Box::new(owner)),
+        false,
+        0,
+    )
+}
+
+// Program account laid out like the runtime's serialized input, so realloc can grow it in place natively
+fn native_resizable_account(key: Pubkey, owner: Pubkey, data: Vec<u8>) -> AccountInfo<'static> {
+    // The original data length sits in the 4 bytes before the key
+    let key_bytes: &'static mut [u8; 36] = Box::leak(Box::new([0; 36]));
+    key_bytes[..4].copy_from_slice(&(data.len() as u32).to_le_bytes());
+    key_bytes[4..].copy_from_slice(key.as_ref());
+    let key: &'static Pubkey = unsafe { &*(key_bytes[4..].as_ptr() as *const Pubkey) };
+    // The current data length sits in the 8 bytes before the data, with room to grow after it
+    let buffer: &'static mut [u64] =
+        Box::leak(vec![0; 1 + (data.len() + MAX_PERMITTED_DATA_INCREASE).div_ceil(8)].into_boxed_slice());
+    buffer[0] = data.len() as u64;
+    let (_, bytes) = bytemuck::cast_slice_mut::<u64, u8>(buffer).split_at_mut(8);
+    bytes[..data.len()].copy_from_slice(&data);
+    AccountInfo::new(
+        key,
+        false,
+        true,
+        Box::leak(Box::new(1_000_000_000)),
+        &mut bytes[..data.len()],
+        Box::leak(Box::new(owner)),
+        false,
+        0,
+    )
+}
+
+// Process an instruction natively, returning its result and any return data
+fn process_native(data: &[u8], accounts: &[AccountInfo<'static>]) -> (Result<(), ProgramError>, Option<Vec<u8>>) {
+    set_syscall_stubs(Box::new(NativeSyscalls));
+    NATIVE_RETURN_DATA.with(|return_data| *return_data.borrow_mut() = None);
+    let result = crate::process_instruction(&crate::ID, accounts, data);
+    (result, NATIVE_RETURN_DATA.with(|return_data| return_data.borrow_mut().take().map(|(_, data)| data)))
+}
+
+// Token amount held by a token account after a native call
+fn native_amount(account: &AccountInfo) -> u64 {
+    crate::utils::unpack_token_account(account).unwrap().amount
+}
+
+// Token program mint with 6 decimals
+fn token_mint(supply: u64, mint_authority: COption<Pubkey>) -> Vec<u8> {
+    let mut data = vec![0; spl_token::state::Mint::LEN];
+    solana_program::program_pack::Pack::pack(
+        spl_token::state::Mint {
+            mint_authority,
+            supply,
+            decimals: 6,
+            is_initialized: true,
+            freeze_authority: COption::None,
+        },
+        &mut data,
+    )
+    .unwrap();
+    data
+}
+
+// Token program account
+fn token_account(mint: Pubkey, owner: Pubkey, amount: u64) -> Vec<u8> {
+    let mut data = vec![0; spl_token::state::Account::LEN];
+    solana_program::program_pack::Pack::pack(
+        spl_token::state::Account {
+            mint,
+            owner,
+            amount,
+            delegate: COption::None,
+            state: spl_token::state::AccountState::Initialized,
+            is_native: COption::None,
+            delegated_amount: 0,
+            close_authority: COption::None,
+        },
+        &mut data,
+    )
+    .unwrap();
+    data
+}
+
+// Token-2022 mint withholding transfer_fee_basis_points of every transfer, with no maximum
+fn token_2022_mint(supply: u64, transfer_fee_basis_points: u16) -> Vec<u8> {
+    let mut data = vec![0; ExtensionType::try_calculate_account_len::<spl_token_2022::state::Mint>(&[ExtensionType::TransferFeeConfig]).unwrap()];
+    let mut mint = StateWithExtensionsMut::<spl_token_2022::state::Mint>::unpack_uninitialized(&mut data).unwrap();
+    let transfer_fee = TransferFee {
+        epoch: 0.into(),
+        maximum_fee: u64::MAX.into(),
+        transfer_fee_basis_points: transfer_fee_basis_points.into(),
+    };
+    let transfer_fee_config = mint.init_extension::<TransferFeeConfig>(true).unwrap();
+    transfer_fee_config.older_transfer_fee = transfer_fee;
+    transfer_fee_config.newer_transfer_fee = transfer_fee;
+    mint.base = spl_token_2022::state::Mint {
+        mint_authority: COption::None,
+        supply,
+        decimals: 6,
+        is_initialized: true,
+        freeze_authority: COption::None,
+    };
+    mint.pack_base();
+    mint.init_account_type().unwrap();
+    data
+}
+
+// Token-2022 account of a mint with transfer fees, which must carry the fees withheld from it
+fn token_2022_account(mint: Pubkey, owner: Pubkey, amount: u64) -> Vec<u8> {
+    let mut data = vec![0; ExtensionType::try_calculate_account_len::<spl_token_2022::state::Account>(&[ExtensionType::TransferFeeAmount]).unwrap()];
+    let mut account = StateWithExtensionsMut::<spl_token_2022::state::Account>::unpack_uninitialized(&mut data).unwrap();
+    account.init_extension::<TransferFeeAmount>(true).unwrap();
+    account.base = spl_token_2022::state::Account {
+        mint,
+        owner,
+        amount,
+        delegate: COption::None,
+        state: spl_token_2022::state::AccountState::Initialized,
+        is_native: COption::None,
+        delegated_amount: 0,
+        close_authority: COption::None,
+    };
+    account.pack_base();
+    account.init_account_type().unwrap();
+    data
+}
 
 #[test]
 fn initialize() {
@@ -88,7 +325,16 @@ fn initialize() {
                 Initialize {
                 seed,
                 fee: 100,
+                protocol_fee: 0,
                 authority: initializer,
+                lp_decimals: 6,
+                curve_type: 0,
+                fee_max_step: 0,
+                uri: [0; 128],
+                curve_params: [0; 32],
+                treasury: initializer,
+                fee_update_interval: 0,
+                flash_fee: 0,
                 padding: [0; 6],
             }
         ),
@@ -101,6 +347,8 @@ fn initialize() {
             AccountMeta::new(vault_y, false),
             AccountMeta::new(config, false),
             AccountMeta::new_readonly(token_program, false),
+            AccountMeta::new_readonly(token_program, false),
+            AccountMeta::new_readonly(token_program, false),
             AccountMeta::new_readonly(system_program, false),
         ],
     );
@@ -338,8 +586,10 @@ fn deposit() {
         &crate::ID,
     );
     config_account.set_data_from_slice(bytes_of::<Config>(&Config {
+        discriminator: CONFIG_DISCRIMINATOR,
         seed,
         authority: crate::ID,
+        pending_authority: Pubkey::default(),
         mint_x,
         mint_y,
         fee: 100u16,
@@ -348,7 +598,30 @@ fn deposit() {
         lp_bump,
         x_bump,
         y_bump,
-        padding: [0],
+        curve_type: 0,
+        curve_params: [0; 32],
+        sqrt_price: 0.into(),
+        liquidity: 0.into(),
+        fee_growth_global_x: 0.into(),
+        fee_growth_global_y: 0.into(),
+        tick_current: 0,
+        tick_spacing: 0,
+        protocol_fee: 0,
+        treasury: crate::ID,
+        protocol_fees_x: 0,
+        protocol_fees_y: 0,
+        last_fee_update: 0,
+        fee_update_interval: 0,
+        price_x_cumulative: 0.into(),
+        price_y_cumulative: 0.into(),
+        liquidity_cumulative: 0.into(),
+        price_last_update: 0,
+        locked_liquidity: MINIMUM_LIQUIDITY,
+        fee_max_step: 0,
+        flash_fee: 0,
+        version: CONFIG_VERSION,
+        reentrancy_guard: 0,
+        padding: [0; 2],
     }));
 
     // Create our instruction
@@ -356,7 +629,7 @@ fn deposit() {
         crate::ID,
         &AMMInstructions::Deposit.serialize::<Deposit>(
             Deposit {
-                amount: 1_000_000,
+                amount: 999_000,
                 max_x: 1_000_000,
                 max_y: 1_000_000,
                 expiration: i64::MAX,
@@ -372,7 +645,9 @@ fn deposit() {
             AccountMeta::new(user_lp, false),
             AccountMeta::new(vault_x, false),
             AccountMeta::new(vault_y, false),
-            AccountMeta::new_readonly(config, false),
+            AccountMeta::new(config, false),
+            AccountMeta::new_readonly(token_program, false),
+            AccountMeta::new_readonly(token_program, false),
             AccountMeta::new_readonly(token_program, false),
             AccountMeta::new_readonly(system_program, false),
         ],
@@ -400,9 +675,8 @@ fn deposit() {
     );
     assert!(matches!(result.program_result, ProgramResult::Success))
 }
-
 #[test]
-fn withdraw() {
+fn deposit_single() {
     // Add our built program binary
     let mut mollusk: Mollusk = Mollusk::new(&crate::ID, "target/deploy/native_amm_2024");
 
@@ -419,7 +693,6 @@ fn withdraw() {
     let mint_x = Pubkey::new_from_array([0x02; 32]);
     let mint_y = Pubkey::new_from_array([0x03; 32]);
     let user_x = Pubkey::new_from_array([0x04; 32]);
-    let user_y = Pubkey::new_from_array([0x05; 32]);
     let user_lp = Pubkey::new_from_array([0x06; 32]);
     let (config, config_bump) =
         Pubkey::find_program_address(&[b"config", &seed.to_le_bytes()], &crate::ID);
@@ -450,26 +723,6 @@ fn withdraw() {
     )
     .unwrap();
 
-    let mut mint_y_account = AccountSharedData::new(
-        mollusk
-            .sysvars
-            .rent
-            .minimum_balance(spl_token::state::Mint::LEN),
-        spl_token::state::Mint::LEN,
-        &token_program,
-    );
-    solana_program::program_pack::Pack::pack(
-        spl_token::state::Mint {
-            mint_authority: COption::None,
-            supply: 100_000_000_000,
-            decimals: 6,
-            is_initialized: true,
-            freeze_authority: COption::None,
-        },
-        mint_y_account.data_as_mut_slice(),
-    )
-    .unwrap();
-
     let mut mint_lp_account = AccountSharedData::new(
         mollusk
             .sysvars
@@ -481,7 +734,7 @@ fn withdraw() {
     solana_program::program_pack::Pack::pack(
         spl_token::state::Mint {
             mint_authority: COption::Some(config),
-            supply: 1_000_000,
+            supply: 999_000,
             decimals: 6,
             is_initialized: true,
             freeze_authority: COption::None,
@@ -548,7 +801,7 @@ fn withdraw() {
         spl_token::state::Account {
             mint: mint_x,
             owner: user,
-            amount: 0,
+            amount: 1_000_000,
             delegate: COption::None,
             state: spl_token::state::AccountState::Initialized,
             is_native: COption::None,
@@ -559,29 +812,6 @@ fn withdraw() {
     )
     .unwrap();
 
-    let mut user_y_account = AccountSharedData::new(
-        mollusk
-            .sysvars
-            .rent
-            .minimum_balance(spl_token::state::Account::LEN),
-        spl_token::state::Account::LEN,
-        &token_program,
-    );
-    solana_program::program_pack::Pack::pack(
-        spl_token::state::Account {
-            mint: mint_y,
-            owner: user,
-            amount: 0,
-            delegate: COption::None,
-            state: spl_token::state::AccountState::Initialized,
-            is_native: COption::None,
-            delegated_amount: 0,
-            close_authority: COption::None,
-        },
-        user_y_account.data_as_mut_slice(),
-    )
-    .unwrap();
-
     let mut user_lp_account = AccountSharedData::new(
         mollusk
             .sysvars
@@ -594,7 +824,7 @@ fn withdraw() {
         spl_token::state::Account {
             mint: mint_lp,
             owner: user,
-            amount: 1_000_000,
+            amount: 0,
             delegate: COption::None,
             state: spl_token::state::AccountState::Initialized,
             is_native: COption::None,
@@ -614,8 +844,10 @@ fn withdraw() {
         &crate::ID,
     );
     config_account.set_data_from_slice(bytes_of::<Config>(&Config {
+        discriminator: CONFIG_DISCRIMINATOR,
         seed,
         authority: crate::ID,
+        pending_authority: Pubkey::default(),
         mint_x,
         mint_y,
         fee: 100u16,
@@ -624,31 +856,52 @@ fn withdraw() {
         lp_bump,
         x_bump,
         y_bump,
-        padding: [0],
+        curve_type: 0,
+        curve_params: [0; 32],
+        sqrt_price: 0.into(),
+        liquidity: 0.into(),
+        fee_growth_global_x: 0.into(),
+        fee_growth_global_y: 0.into(),
+        tick_current: 0,
+        tick_spacing: 0,
+        protocol_fee: 0,
+        treasury: crate::ID,
+        protocol_fees_x: 0,
+        protocol_fees_y: 0,
+        last_fee_update: 0,
+        fee_update_interval: 0,
+        price_x_cumulative: 0.into(),
+        price_y_cumulative: 0.into(),
+        liquidity_cumulative: 0.into(),
+        price_last_update: 0,
+        locked_liquidity: MINIMUM_LIQUIDITY,
+        fee_max_step: 0,
+        flash_fee: 0,
+        version: CONFIG_VERSION,
+        reentrancy_guard: 0,
+        padding: [0; 2],
     }));
 
     // Create our instruction
     let instruction = Instruction::new_with_bytes(
         crate::ID,
-        &AMMInstructions::Withdraw.serialize::<Withdraw>(
-            Withdraw {
-                amount: 1_000_000,
-                min_x: 1_000_000,
-                min_y: 1_000_000,
+        &AMMInstructions::DepositSingle.serialize::<DepositSingle>(
+            DepositSingle {
+                amount: 2_000,
+                min_lp: 990,
                 expiration: i64::MAX,
             }
         ),
         vec![
             AccountMeta::new(user, true),
             AccountMeta::new_readonly(mint_x, false),
-            AccountMeta::new_readonly(mint_y, false),
             AccountMeta::new(mint_lp, false),
             AccountMeta::new(user_x, false),
-            AccountMeta::new(user_y, false),
             AccountMeta::new(user_lp, false),
             AccountMeta::new(vault_x, false),
             AccountMeta::new(vault_y, false),
-            AccountMeta::new_readonly(config, false),
+            AccountMeta::new(config, false),
+            AccountMeta::new_readonly(token_program, false),
             AccountMeta::new_readonly(token_program, false),
         ],
     );
@@ -661,10 +914,8 @@ fn withdraw() {
                 AccountSharedData::new(1_000_000_000, 0, &Pubkey::default()),
             ),
             (mint_x, mint_x_account),
-            (mint_y, mint_y_account),
             (mint_lp, mint_lp_account),
             (user_x, user_x_account),
-            (user_y, user_y_account),
             (user_lp, user_lp_account),
             (vault_x, vault_x_account),
             (vault_y, vault_y_account),
@@ -677,7 +928,7 @@ fn withdraw() {
 }
 
 #[test]
-fn swap() {
+fn withdraw() {
     // Add our built program binary
     let mut mollusk: Mollusk = Mollusk::new(&crate::ID, "target/deploy/native_amm_2024");
 
@@ -693,10 +944,12 @@ fn swap() {
     let user = Pubkey::new_from_array([0x01; 32]);
     let mint_x = Pubkey::new_from_array([0x02; 32]);
     let mint_y = Pubkey::new_from_array([0x03; 32]);
-    let user_from = Pubkey::new_from_array([0x04; 32]);
-    let user_to = Pubkey::new_from_array([0x05; 32]);
+    let user_x = Pubkey::new_from_array([0x04; 32]);
+    let user_y = Pubkey::new_from_array([0x05; 32]);
+    let user_lp = Pubkey::new_from_array([0x06; 32]);
     let (config, config_bump) =
         Pubkey::find_program_address(&[b"config", &seed.to_le_bytes()], &crate::ID);
+    let (mint_lp, lp_bump) = Pubkey::find_program_address(&[config.as_ref()], &crate::ID);
     let (vault_x, x_bump) =
         Pubkey::find_program_address(&[mint_x.as_ref(), config.as_ref()], &crate::ID);
     let (vault_y, y_bump) =
@@ -743,6 +996,26 @@ fn swap() {
     )
     .unwrap();
 
+    let mut mint_lp_account = AccountSharedData::new(
+        mollusk
+            .sysvars
+            .rent
+            .minimum_balance(spl_token::state::Mint::LEN),
+        spl_token::state::Mint::LEN,
+        &token_program,
+    );
+    solana_program::program_pack::Pack::pack(
+        spl_token::state::Mint {
+            mint_authority: COption::Some(config),
+            supply: 1_000_000,
+            decimals: 6,
+            is_initialized: true,
+            freeze_authority: COption::None,
+        },
+        mint_lp_account.data_as_mut_slice(),
+    )
+    .unwrap();
+
     let mut vault_x_account = AccountSharedData::new(
         mollusk
             .sysvars
@@ -755,7 +1028,7 @@ fn swap() {
         spl_token::state::Account {
             mint: mint_x,
             owner: config,
-            amount: 20,
+            amount: 1_000_000,
             delegate: COption::None,
             state: spl_token::state::AccountState::Initialized,
             is_native: COption::None,
@@ -778,7 +1051,7 @@ fn swap() {
         spl_token::state::Account {
             mint: mint_y,
             owner: config,
-            amount: 30,
+            amount: 1_000_000,
             delegate: COption::None,
             state: spl_token::state::AccountState::Initialized,
             is_native: COption::None,
@@ -789,7 +1062,7 @@ fn swap() {
     )
     .unwrap();
 
-    let mut user_from_account = AccountSharedData::new(
+    let mut user_x_account = AccountSharedData::new(
         mollusk
             .sysvars
             .rent
@@ -801,18 +1074,18 @@ fn swap() {
         spl_token::state::Account {
             mint: mint_x,
             owner: user,
-            amount: 100_000_000,
+            amount: 0,
             delegate: COption::None,
             state: spl_token::state::AccountState::Initialized,
             is_native: COption::None,
             delegated_amount: 0,
             close_authority: COption::None,
         },
-        user_from_account.data_as_mut_slice(),
+        user_x_account.data_as_mut_slice(),
     )
     .unwrap();
 
-    let mut user_to_account = AccountSharedData::new(
+    let mut user_y_account = AccountSharedData::new(
         mollusk
             .sysvars
             .rent
@@ -824,14 +1097,37 @@ fn swap() {
         spl_token::state::Account {
             mint: mint_y,
             owner: user,
-            amount: 100_000_000,
+            amount: 0,
             delegate: COption::None,
             state: spl_token::state::AccountState::Initialized,
             is_native: COption::None,
             delegated_amount: 0,
             close_authority: COption::None,
         },
-        user_to_account.data_as_mut_slice(),
+        user_y_account.data_as_mut_slice(),
+    )
+    .unwrap();
+
+    let mut user_lp_account = AccountSharedData::new(
+        mollusk
+            .sysvars
+            .rent
+            .minimum_balance(spl_token::state::Account::LEN),
+        spl_token::state::Account::LEN,
+        &token_program,
+    );
+    solana_program::program_pack::Pack::pack(
+        spl_token::state::Account {
+            mint: mint_lp,
+            owner: user,
+            amount: 1_000_000,
+            delegate: COption::None,
+            state: spl_token::state::AccountState::Initialized,
+            is_native: COption::None,
+            delegated_amount: 0,
+            close_authority: COption::None,
+        },
+        user_lp_account.data_as_mut_slice(),
     )
     .unwrap();
 
@@ -844,26 +1140,52 @@ fn swap() {
         &crate::ID,
     );
     config_account.set_data_from_slice(bytes_of::<Config>(&Config {
+        discriminator: CONFIG_DISCRIMINATOR,
         seed,
         authority: crate::ID,
+        pending_authority: Pubkey::default(),
         mint_x,
         mint_y,
         fee: 100u16,
         locked: 0,
         config_bump,
-        lp_bump: 0,
+        lp_bump,
         x_bump,
         y_bump,
-        padding: [0],
+        curve_type: 0,
+        curve_params: [0; 32],
+        sqrt_price: 0.into(),
+        liquidity: 0.into(),
+        fee_growth_global_x: 0.into(),
+        fee_growth_global_y: 0.into(),
+        tick_current: 0,
+        tick_spacing: 0,
+        protocol_fee: 0,
+        treasury: crate::ID,
+        protocol_fees_x: 0,
+        protocol_fees_y: 0,
+        last_fee_update: 0,
+        fee_update_interval: 0,
+        price_x_cumulative: 0.into(),
+        price_y_cumulative: 0.into(),
+        liquidity_cumulative: 0.into(),
+        price_last_update: 0,
+        locked_liquidity: MINIMUM_LIQUIDITY,
+        fee_max_step: 0,
+        flash_fee: 0,
+        version: CONFIG_VERSION,
+        reentrancy_guard: 0,
+        padding: [0; 2],
     }));
 
     // Create our instruction
     let instruction = Instruction::new_with_bytes(
         crate::ID,
-        &AMMInstructions::Swap.serialize::<Swap>(
-            Swap {
-                amount: 5,
-                min: 0,
+        &AMMInstructions::Withdraw.serialize::<Withdraw>(
+            Withdraw {
+                amount: 1_000_000,
+                min_x: 999_000,
+                min_y: 999_000,
                 expiration: i64::MAX,
             }
         ),
@@ -871,11 +1193,15 @@ fn swap() {
             AccountMeta::new(user, true),
             AccountMeta::new_readonly(mint_x, false),
             AccountMeta::new_readonly(mint_y, false),
-            AccountMeta::new(user_from, false),
-            AccountMeta::new(user_to, false),
+            AccountMeta::new(mint_lp, false),
+            AccountMeta::new(user_x, false),
+            AccountMeta::new(user_y, false),
+            AccountMeta::new(user_lp, false),
             AccountMeta::new(vault_x, false),
             AccountMeta::new(vault_y, false),
-            AccountMeta::new_readonly(config, false),
+            AccountMeta::new(config, false),
+            AccountMeta::new_readonly(token_program, false),
+            AccountMeta::new_readonly(token_program, false),
             AccountMeta::new_readonly(token_program, false),
         ],
     );
@@ -889,8 +1215,10 @@ fn swap() {
             ),
             (mint_x, mint_x_account),
             (mint_y, mint_y_account),
-            (user_from, user_from_account),
-            (user_to, user_to_account),
+            (mint_lp, mint_lp_account),
+            (user_x, user_x_account),
+            (user_y, user_y_account),
+            (user_lp, user_lp_account),
             (vault_x, vault_x_account),
             (vault_y, vault_y_account),
             (config, config_account),
@@ -899,4 +1227,5022 @@ fn swap() {
         ],
     );
     assert!(matches!(result.program_result, ProgramResult::Success))
-}
\ No newline at end of file
+}
+#[test]
+fn withdraw_single() {
+    // Add our built program binary
+    let mut mollusk: Mollusk = Mollusk::new(&crate::ID, "target/deploy/native_amm_2024");
+
+    // Set our seed
+    let seed: u64 = 1337;
+
+    // Programs
+    mollusk.add_program(&spl_token::ID, "src/tests/spl_token");
+    let (token_program, token_program_account) = (spl_token::ID, program_account(&spl_token::ID));
+    let (system_program, system_program_account) = program::system_program();
+
+    // Accounts
+    let user = Pubkey::new_from_array([0x01; 32]);
+    let mint_x = Pubkey::new_from_array([0x02; 32]);
+    let mint_y = Pubkey::new_from_array([0x03; 32]);
+    let user_x = Pubkey::new_from_array([0x04; 32]);
+    let user_lp = Pubkey::new_from_array([0x06; 32]);
+    let (config, config_bump) =
+        Pubkey::find_program_address(&[b"config", &seed.to_le_bytes()], &crate::ID);
+    let (mint_lp, lp_bump) = Pubkey::find_program_address(&[config.as_ref()], &crate::ID);
+    let (vault_x, x_bump) =
+        Pubkey::find_program_address(&[mint_x.as_ref(), config.as_ref()], &crate::ID);
+    let (vault_y, y_bump) =
+        Pubkey::find_program_address(&[mint_y.as_ref(), config.as_ref()], &crate::ID);
+
+    // Fill out our account data
+    let mut mint_x_account = AccountSharedData::new(
+        mollusk
+            .sysvars
+            .rent
+            .minimum_balance(spl_token::state::Mint::LEN),
+        spl_token::state::Mint::LEN,
+        &token_program,
+    );
+    solana_program::program_pack::Pack::pack(
+        spl_token::state::Mint {
+            mint_authority: COption::None,
+            supply: 100_000_000_000,
+            decimals: 6,
+            is_initialized: true,
+            freeze_authority: COption::None,
+        },
+        mint_x_account.data_as_mut_slice(),
+    )
+    .unwrap();
+
+    let mut mint_lp_account = AccountSharedData::new(
+        mollusk
+            .sysvars
+            .rent
+            .minimum_balance(spl_token::state::Mint::LEN),
+        spl_token::state::Mint::LEN,
+        &token_program,
+    );
+    solana_program::program_pack::Pack::pack(
+        spl_token::state::Mint {
+            mint_authority: COption::Some(config),
+            supply: 1_000_000,
+            decimals: 6,
+            is_initialized: true,
+            freeze_authority: COption::None,
+        },
+        mint_lp_account.data_as_mut_slice(),
+    )
+    .unwrap();
+
+    let mut vault_x_account = AccountSharedData::new(
+        mollusk
+            .sysvars
+            .rent
+            .minimum_balance(spl_token::state::Account::LEN),
+        spl_token::state::Account::LEN,
+        &token_program,
+    );
+    solana_program::program_pack::Pack::pack(
+        spl_token::state::Account {
+            mint: mint_x,
+            owner: config,
+            amount: 1_000_000,
+            delegate: COption::None,
+            state: spl_token::state::AccountState::Initialized,
+            is_native: COption::None,
+            delegated_amount: 0,
+            close_authority: COption::None,
+        },
+        vault_x_account.data_as_mut_slice(),
+    )
+    .unwrap();
+
+    let mut vault_y_account = AccountSharedData::new(
+        mollusk
+            .sysvars
+            .rent
+            .minimum_balance(spl_token::state::Account::LEN),
+        spl_token::state::Account::LEN,
+        &token_program,
+    );
+    solana_program::program_pack::Pack::pack(
+        spl_token::state::Account {
+            mint: mint_y,
+            owner: config,
+            amount: 1_000_000,
+            delegate: COption::None,
+            state: spl_token::state::AccountState::Initialized,
+            is_native: COption::None,
+            delegated_amount: 0,
+            close_authority: COption::None,
+        },
+        vault_y_account.data_as_mut_slice(),
+    )
+    .unwrap();
+
+    let mut user_x_account = AccountSharedData::new(
+        mollusk
+            .sysvars
+            .rent
+            .minimum_balance(spl_token::state::Account::LEN),
+        spl_token::state::Account::LEN,
+        &token_program,
+    );
+    solana_program::program_pack::Pack::pack(
+        spl_token::state::Account {
+            mint: mint_x,
+            owner: user,
+            amount: 0,
+            delegate: COption::None,
+            state: spl_token::state::AccountState::Initialized,
+            is_native: COption::None,
+            delegated_amount: 0,
+            close_authority: COption::None,
+        },
+        user_x_account.data_as_mut_slice(),
+    )
+    .unwrap();
+
+    let mut user_lp_account = AccountSharedData::new(
+        mollusk
+            .sysvars
+            .rent
+            .minimum_balance(spl_token::state::Account::LEN),
+        spl_token::state::Account::LEN,
+        &token_program,
+    );
+    solana_program::program_pack::Pack::pack(
+        spl_token::state::Account {
+            mint: mint_lp,
+            owner: user,
+            amount: 1_000_000,
+            delegate: COption::None,
+            state: spl_token::state::AccountState::Initialized,
+            is_native: COption::None,
+            delegated_amount: 0,
+            close_authority: COption::None,
+        },
+        user_lp_account.data_as_mut_slice(),
+    )
+    .unwrap();
+
+    let mut config_account = AccountSharedData::new(
+        mollusk
+            .sysvars
+            .rent
+            .minimum_balance(mem::size_of::<Config>()),
+        mem::size_of::<Config>(),
+        &crate::ID,
+    );
+    config_account.set_data_from_slice(bytes_of::<Config>(&Config {
+        discriminator: CONFIG_DISCRIMINATOR,
+        seed,
+        authority: crate::ID,
+        pending_authority: Pubkey::default(),
+        mint_x,
+        mint_y,
+        fee: 100u16,
+        locked: 0,
+        config_bump,
+        lp_bump,
+        x_bump,
+        y_bump,
+        curve_type: 0,
+        curve_params: [0; 32],
+        sqrt_price: 0.into(),
+        liquidity: 0.into(),
+        fee_growth_global_x: 0.into(),
+        fee_growth_global_y: 0.into(),
+        tick_current: 0,
+        tick_spacing: 0,
+        protocol_fee: 0,
+        treasury: crate::ID,
+        protocol_fees_x: 0,
+        protocol_fees_y: 0,
+        last_fee_update: 0,
+        fee_update_interval: 0,
+        price_x_cumulative: 0.into(),
+        price_y_cumulative: 0.into(),
+        liquidity_cumulative: 0.into(),
+        price_last_update: 0,
+        locked_liquidity: MINIMUM_LIQUIDITY,
+        fee_max_step: 0,
+        flash_fee: 0,
+        version: CONFIG_VERSION,
+        reentrancy_guard: 0,
+        padding: [0; 2],
+    }));
+
+    // Create our instruction
+    let instruction = Instruction::new_with_bytes(
+        crate::ID,
+        &AMMInstructions::WithdrawSingle.serialize::<WithdrawSingle>(
+            WithdrawSingle {
+                amount: 100_000,
+                min_out: 180_000,
+                expiration: i64::MAX,
+            }
+        ),
+        vec![
+            AccountMeta::new(user, true),
+            AccountMeta::new_readonly(mint_x, false),
+            AccountMeta::new(mint_lp, false),
+            AccountMeta::new(user_x, false),
+            AccountMeta::new(user_lp, false),
+            AccountMeta::new(vault_x, false),
+            AccountMeta::new(vault_y, false),
+            AccountMeta::new(config, false),
+            AccountMeta::new_readonly(token_program, false),
+            AccountMeta::new_readonly(token_program, false),
+        ],
+    );
+
+    let result: mollusk_svm::result::InstructionResult = mollusk.process_instruction(
+        &instruction,
+        &vec![
+            (
+                user,
+                AccountSharedData::new(1_000_000_000, 0, &Pubkey::default()),
+            ),
+            (mint_x, mint_x_account),
+            (mint_lp, mint_lp_account),
+            (user_x, user_x_account),
+            (user_lp, user_lp_account),
+            (vault_x, vault_x_account),
+            (vault_y, vault_y_account),
+            (config, config_account),
+            (token_program, token_program_account),
+            (system_program, system_program_account),
+        ],
+    );
+    assert!(matches!(result.program_result, ProgramResult::Success))
+}
+
+#[test]
+fn swap() {
+    // Add our built program binary
+    let mut mollusk: Mollusk = Mollusk::new(&crate::ID, "target/deploy/native_amm_2024");
+
+    // Set our seed
+    let seed: u64 = 1337;
+
+    // Programs
+    mollusk.add_program(&spl_token::ID, "src/tests/spl_token");
+    let (token_program, token_program_account) = (spl_token::ID, program_account(&spl_token::ID));
+    let (system_program, system_program_account) = program::system_program();
+
+    // Accounts
+    let user = Pubkey::new_from_array([0x01; 32]);
+    let mint_x = Pubkey::new_from_array([0x02; 32]);
+    let mint_y = Pubkey::new_from_array([0x03; 32]);
+    let user_from = Pubkey::new_from_array([0x04; 32]);
+    let user_to = Pubkey::new_from_array([0x05; 32]);
+    let (config, config_bump) =
+        Pubkey::find_program_address(&[b"config", &seed.to_le_bytes()], &crate::ID);
+    let (vault_x, x_bump) =
+        Pubkey::find_program_address(&[mint_x.as_ref(), config.as_ref()], &crate::ID);
+    let (vault_y, y_bump) =
+        Pubkey::find_program_address(&[mint_y.as_ref(), config.as_ref()], &crate::ID);
+
+    // Fill out our account data
+    let mut mint_x_account = AccountSharedData::new(
+        mollusk
+            .sysvars
+            .rent
+            .minimum_balance(spl_token::state::Mint::LEN),
+        spl_token::state::Mint::LEN,
+        &token_program,
+    );
+    solana_program::program_pack::Pack::pack(
+        spl_token::state::Mint {
+            mint_authority: COption::None,
+            supply: 100_000_000_000,
+            decimals: 6,
+            is_initialized: true,
+            freeze_authority: COption::None,
+        },
+        mint_x_account.data_as_mut_slice(),
+    )
+    .unwrap();
+
+    let mut mint_y_account = AccountSharedData::new(
+        mollusk
+            .sysvars
+            .rent
+            .minimum_balance(spl_token::state::Mint::LEN),
+        spl_token::state::Mint::LEN,
+        &token_program,
+    );
+    solana_program::program_pack::Pack::pack(
+        spl_token::state::Mint {
+            mint_authority: COption::None,
+            supply: 100_000_000_000,
+            decimals: 6,
+            is_initialized: true,
+            freeze_authority: COption::None,
+        },
+        mint_y_account.data_as_mut_slice(),
+    )
+    .unwrap();
+
+    let mut vault_x_account = AccountSharedData::new(
+        mollusk
+            .sysvars
+            .rent
+            .minimum_balance(spl_token::state::Account::LEN),
+        spl_token::state::Account::LEN,
+        &token_program,
+    );
+    solana_program::program_pack::Pack::pack(
+        spl_token::state::Account {
+            mint: mint_x,
+            owner: config,
+            amount: 20,
+            delegate: COption::None,
+            state: spl_token::state::AccountState::Initialized,
+            is_native: COption::None,
+            delegated_amount: 0,
+            close_authority: COption::None,
+        },
+        vault_x_account.data_as_mut_slice(),
+    )
+    .unwrap();
+
+    let mut vault_y_account = AccountSharedData::new(
+        mollusk
+            .sysvars
+            .rent
+            .minimum_balance(spl_token::state::Account::LEN),
+        spl_token::state::Account::LEN,
+        &token_program,
+    );
+    solana_program::program_pack::Pack::pack(
+        spl_token::state::Account {
+            mint: mint_y,
+            owner: config,
+            amount: 30,
+            delegate: COption::None,
+            state: spl_token::state::AccountState::Initialized,
+            is_native: COption::None,
+            delegated_amount: 0,
+            close_authority: COption::None,
+        },
+        vault_y_account.data_as_mut_slice(),
+    )
+    .unwrap();
+
+    let mut user_from_account = AccountSharedData::new(
+        mollusk
+            .sysvars
+            .rent
+            .minimum_balance(spl_token::state::Account::LEN),
+        spl_token::state::Account::LEN,
+        &token_program,
+    );
+    solana_program::program_pack::Pack::pack(
+        spl_token::state::Account {
+            mint: mint_x,
+            owner: user,
+            amount: 100_000_000,
+            delegate: COption::None,
+            state: spl_token::state::AccountState::Initialized,
+            is_native: COption::None,
+            delegated_amount: 0,
+            close_authority: COption::None,
+        },
+        user_from_account.data_as_mut_slice(),
+    )
+    .unwrap();
+
+    let mut user_to_account = AccountSharedData::new(
+        mollusk
+            .sysvars
+            .rent
+            .minimum_balance(spl_token::state::Account::LEN),
+        spl_token::state::Account::LEN,
+        &token_program,
+    );
+    solana_program::program_pack::Pack::pack(
+        spl_token::state::Account {
+            mint: mint_y,
+            owner: user,
+            amount: 100_000_000,
+            delegate: COption::None,
+            state: spl_token::state::AccountState::Initialized,
+            is_native: COption::None,
+            delegated_amount: 0,
+            close_authority: COption::None,
+        },
+        user_to_account.data_as_mut_slice(),
+    )
+    .unwrap();
+
+    let mut config_account = AccountSharedData::new(
+        mollusk
+            .sysvars
+            .rent
+            .minimum_balance(mem::size_of::<Config>()),
+        mem::size_of::<Config>(),
+        &crate::ID,
+    );
+    config_account.set_data_from_slice(bytes_of::<Config>(&Config {
+        discriminator: CONFIG_DISCRIMINATOR,
+        seed,
+        authority: crate::ID,
+        pending_authority: Pubkey::default(),
+        mint_x,
+        mint_y,
+        fee: 100u16,
+        locked: 0,
+        config_bump,
+        lp_bump: 0,
+        x_bump,
+        y_bump,
+        curve_type: 0,
+        curve_params: [0; 32],
+        sqrt_price: 0.into(),
+        liquidity: 0.into(),
+        fee_growth_global_x: 0.into(),
+        fee_growth_global_y: 0.into(),
+        tick_current: 0,
+        tick_spacing: 0,
+        protocol_fee: 0,
+        treasury: crate::ID,
+        protocol_fees_x: 0,
+        protocol_fees_y: 0,
+        last_fee_update: 0,
+        fee_update_interval: 0,
+        price_x_cumulative: 0.into(),
+        price_y_cumulative: 0.into(),
+        liquidity_cumulative: 0.into(),
+        price_last_update: 0,
+        locked_liquidity: MINIMUM_LIQUIDITY,
+        fee_max_step: 0,
+        flash_fee: 0,
+        version: CONFIG_VERSION,
+        reentrancy_guard: 0,
+        padding: [0; 2],
+    }));
+
+    // Create our instruction
+    let instruction = Instruction::new_with_bytes(
+        crate::ID,
+        &AMMInstructions::Swap.serialize::<Swap>(
+            Swap {
+                amount: 5,
+                min: 0,
+                expiration: i64::MAX,
+            }
+        ),
+        vec![
+            AccountMeta::new(user, true),
+            AccountMeta::new_readonly(mint_x, false),
+            AccountMeta::new_readonly(mint_y, false),
+            AccountMeta::new(user_from, false),
+            AccountMeta::new(user_to, false),
+            AccountMeta::new(vault_x, false),
+            AccountMeta::new(vault_y, false),
+            AccountMeta::new(config, false),
+            AccountMeta::new_readonly(token_program, false),
+            AccountMeta::new_readonly(token_program, false),
+        ],
+    );
+
+    let result: mollusk_svm::result::InstructionResult = mollusk.process_instruction(
+        &instruction,
+        &vec![
+            (
+                user,
+                AccountSharedData::new(1_000_000_000, 0, &Pubkey::default()),
+            ),
+            (mint_x, mint_x_account),
+            (mint_y, mint_y_account),
+            (user_from, user_from_account),
+            (user_to, user_to_account),
+            (vault_x, vault_x_account),
+            (vault_y, vault_y_account),
+            (config, config_account),
+            (token_program, token_program_account),
+            (system_program, system_program_account),
+        ],
+    );
+    assert!(matches!(result.program_result, ProgramResult::Success))
+}
+
+#[test]
+fn swap_exact_out() {
+    // Add our built program binary
+    let mut mollusk: Mollusk = Mollusk::new(&crate::ID, "target/deploy/native_amm_2024");
+
+    // Set our seed
+    let seed: u64 = 1337;
+
+    // Programs
+    mollusk.add_program(&spl_token::ID, "src/tests/spl_token");
+    let (token_program, token_program_account) = (spl_token::ID, program_account(&spl_token::ID));
+    let (system_program, system_program_account) = program::system_program();
+
+    // Accounts
+    let user = Pubkey::new_from_array([0x01; 32]);
+    let mint_x = Pubkey::new_from_array([0x02; 32]);
+    let mint_y = Pubkey::new_from_array([0x03; 32]);
+    let user_from = Pubkey::new_from_array([0x04; 32]);
+    let user_to = Pubkey::new_from_array([0x05; 32]);
+    let (config, config_bump) =
+        Pubkey::find_program_address(&[b"config", &seed.to_le_bytes()], &crate::ID);
+    let (vault_x, x_bump) =
+        Pubkey::find_program_address(&[mint_x.as_ref(), config.as_ref()], &crate::ID);
+    let (vault_y, y_bump) =
+        Pubkey::find_program_address(&[mint_y.as_ref(), config.as_ref()], &crate::ID);
+
+    // Fill out our account data
+    let mut mint_x_account = AccountSharedData::new(
+        mollusk
+            .sysvars
+            .rent
+            .minimum_balance(spl_token::state::Mint::LEN),
+        spl_token::state::Mint::LEN,
+        &token_program,
+    );
+    solana_program::program_pack::Pack::pack(
+        spl_token::state::Mint {
+            mint_authority: COption::None,
+            supply: 100_000_000_000,
+            decimals: 6,
+            is_initialized: true,
+            freeze_authority: COption::None,
+        },
+        mint_x_account.data_as_mut_slice(),
+    )
+    .unwrap();
+
+    let mut mint_y_account = AccountSharedData::new(
+        mollusk
+            .sysvars
+            .rent
+            .minimum_balance(spl_token::state::Mint::LEN),
+        spl_token::state::Mint::LEN,
+        &token_program,
+    );
+    solana_program::program_pack::Pack::pack(
+        spl_token::state::Mint {
+            mint_authority: COption::None,
+            supply: 100_000_000_000,
+            decimals: 6,
+            is_initialized: true,
+            freeze_authority: COption::None,
+        },
+        mint_y_account.data_as_mut_slice(),
+    )
+    .unwrap();
+
+    let mut vault_x_account = AccountSharedData::new(
+        mollusk
+            .sysvars
+            .rent
+            .minimum_balance(spl_token::state::Account::LEN),
+        spl_token::state::Account::LEN,
+        &token_program,
+    );
+    solana_program::program_pack::Pack::pack(
+        spl_token::state::Account {
+            mint: mint_x,
+            owner: config,
+            amount: 20,
+            delegate: COption::None,
+            state: spl_token::state::AccountState::Initialized,
+            is_native: COption::None,
+            delegated_amount: 0,
+            close_authority: COption::None,
+        },
+        vault_x_account.data_as_mut_slice(),
+    )
+    .unwrap();
+
+    let mut vault_y_account = AccountSharedData::new(
+        mollusk
+            .sysvars
+            .rent
+            .minimum_balance(spl_token::state::Account::LEN),
+        spl_token::state::Account::LEN,
+        &token_program,
+    );
+    solana_program::program_pack::Pack::pack(
+        spl_token::state::Account {
+            mint: mint_y,
+            owner: config,
+            amount: 30,
+            delegate: COption::None,
+            state: spl_token::state::AccountState::Initialized,
+            is_native: COption::None,
+            delegated_amount: 0,
+            close_authority: COption::None,
+        },
+        vault_y_account.data_as_mut_slice(),
+    )
+    .unwrap();
+
+    let mut user_from_account = AccountSharedData::new(
+        mollusk
+            .sysvars
+            .rent
+            .minimum_balance(spl_token::state::Account::LEN),
+        spl_token::state::Account::LEN,
+        &token_program,
+    );
+    solana_program::program_pack::Pack::pack(
+        spl_token::state::Account {
+            mint: mint_x,
+            owner: user,
+            amount: 100_000_000,
+            delegate: COption::None,
+            state: spl_token::state::AccountState::Initialized,
+            is_native: COption::None,
+            delegated_amount: 0,
+            close_authority: COption::None,
+        },
+        user_from_account.data_as_mut_slice(),
+    )
+    .unwrap();
+
+    let mut user_to_account = AccountSharedData::new(
+        mollusk
+            .sysvars
+            .rent
+            .minimum_balance(spl_token::state::Account::LEN),
+        spl_token::state::Account::LEN,
+        &token_program,
+    );
+    solana_program::program_pack::Pack::pack(
+        spl_token::state::Account {
+            mint: mint_y,
+            owner: user,
+            amount: 100_000_000,
+            delegate: COption::None,
+            state: spl_token::state::AccountState::Initialized,
+            is_native: COption::None,
+            delegated_amount: 0,
+            close_authority: COption::None,
+        },
+        user_to_account.data_as_mut_slice(),
+    )
+    .unwrap();
+
+    let mut config_account = AccountSharedData::new(
+        mollusk
+            .sysvars
+            .rent
+            .minimum_balance(mem::size_of::<Config>()),
+        mem::size_of::<Config>(),
+        &crate::ID,
+    );
+    config_account.set_data_from_slice(bytes_of::<Config>(&Config {
+        discriminator: CONFIG_DISCRIMINATOR,
+        seed,
+        authority: crate::ID,
+        pending_authority: Pubkey::default(),
+        mint_x,
+        mint_y,
+        fee: 100u16,
+        locked: 0,
+        config_bump,
+        lp_bump: 0,
+        x_bump,
+        y_bump,
+        curve_type: 0,
+        curve_params: [0; 32],
+        sqrt_price: 0.into(),
+        liquidity: 0.into(),
+        fee_growth_global_x: 0.into(),
+        fee_growth_global_y: 0.into(),
+        tick_current: 0,
+        tick_spacing: 0,
+        protocol_fee: 0,
+        treasury: crate::ID,
+        protocol_fees_x: 0,
+        protocol_fees_y: 0,
+        last_fee_update: 0,
+        fee_update_interval: 0,
+        price_x_cumulative: 0.into(),
+        price_y_cumulative: 0.into(),
+        liquidity_cumulative: 0.into(),
+        price_last_update: 0,
+        locked_liquidity: MINIMUM_LIQUIDITY,
+        fee_max_step: 0,
+        flash_fee: 0,
+        version: CONFIG_VERSION,
+        reentrancy_guard: 0,
+        padding: [0; 2],
+    }));
+
+    // Create our instruction
+    let instruction = Instruction::new_with_bytes(
+        crate::ID,
+        &AMMInstructions::SwapExactOut.serialize::<SwapExactOut>(
+            SwapExactOut {
+                amount_out: 5,
+                max_in: 10,
+                expiration: i64::MAX,
+            }
+        ),
+        vec![
+            AccountMeta::new(user, true),
+            AccountMeta::new_readonly(mint_x, false),
+            AccountMeta::new_readonly(mint_y, false),
+            AccountMeta::new(user_from, false),
+            AccountMeta::new(user_to, false),
+            AccountMeta::new(vault_x, false),
+            AccountMeta::new(vault_y, false),
+            AccountMeta::new(config, false),
+            AccountMeta::new_readonly(token_program, false),
+            AccountMeta::new_readonly(token_program, false),
+        ],
+    );
+
+    let result: mollusk_svm::result::InstructionResult = mollusk.process_instruction(
+        &instruction,
+        &vec![
+            (
+                user,
+                AccountSharedData::new(1_000_000_000, 0, &Pubkey::default()),
+            ),
+            (mint_x, mint_x_account),
+            (mint_y, mint_y_account),
+            (user_from, user_from_account),
+            (user_to, user_to_account),
+            (vault_x, vault_x_account),
+            (vault_y, vault_y_account),
+            (config, config_account),
+            (token_program, token_program_account),
+            (system_program, system_program_account),
+        ],
+    );
+    assert!(matches!(result.program_result, ProgramResult::Success))
+}
+
+#[test]
+fn initialize_tick_array() {
+    // Add our built program binary
+    let mollusk: Mollusk = Mollusk::new(&crate::ID, "target/deploy/native_amm_2024");
+
+    // Set our seed
+    let seed: u64 = 1337;
+
+    // Programs
+    let (system_program, system_program_account) = program::system_program();
+
+    // Accounts
+    let payer = Pubkey::new_from_array([0x01; 32]);
+    let mint_x = Pubkey::new_from_array([0x02; 32]);
+    let mint_y = Pubkey::new_from_array([0x03; 32]);
+    let (config, config_bump) = Pubkey::find_program_address(&[b"config", &seed.to_le_bytes()], &crate::ID);
+    let start_tick_index: i32 = -640;
+    let tick_array = Pubkey::find_program_address(&[b"tick_array", config.as_ref(), &start_tick_index.to_le_bytes()], &crate::ID).0;
+
+    // Fill out our account data
+    let mut config_account = AccountSharedData::new(
+        mollusk
+            .sysvars
+            .rent
+            .minimum_balance(mem::size_of::<Config>()),
+        mem::size_of::<Config>(),
+        &crate::ID,
+    );
+    config_account.set_data_from_slice(bytes_of::<Config>(&Config {
+        discriminator: CONFIG_DISCRIMINATOR,
+        seed,
+        authority: crate::ID,
+        pending_authority: Pubkey::default(),
+        mint_x,
+        mint_y,
+        fee: 30u16,
+        locked: 0,
+        config_bump,
+        lp_bump: 0,
+        x_bump: 0,
+        y_bump: 0,
+        curve_type: 3,
+        curve_params: [0; 32],
+        sqrt_price: (1u128 << 64).into(),
+        liquidity: 0.into(),
+        fee_growth_global_x: 0.into(),
+        fee_growth_global_y: 0.into(),
+        tick_current: 0,
+        tick_spacing: 10,
+        protocol_fee: 0,
+        treasury: crate::ID,
+        protocol_fees_x: 0,
+        protocol_fees_y: 0,
+        last_fee_update: 0,
+        fee_update_interval: 0,
+        price_x_cumulative: 0.into(),
+        price_y_cumulative: 0.into(),
+        liquidity_cumulative: 0.into(),
+        price_last_update: 0,
+        locked_liquidity: MINIMUM_LIQUIDITY,
+        fee_max_step: 0,
+        flash_fee: 0,
+        version: CONFIG_VERSION,
+        reentrancy_guard: 0,
+        padding: [0; 2],
+    }));
+
+    // Create our instruction
+    let instruction = Instruction::new_with_bytes(
+        crate::ID,
+        &AMMInstructions::InitializeTickArray.serialize::<InitializeTickArray>(
+            InitializeTickArray {
+                start_tick_index,
+            }
+        ),
+        vec![
+            AccountMeta::new(payer, true),
+            AccountMeta::new_readonly(config, false),
+            AccountMeta::new(tick_array, false),
+            AccountMeta::new_readonly(system_program, false),
+        ],
+    );
+
+    let result: mollusk_svm::result::InstructionResult = mollusk.process_instruction(
+        &instruction,
+        &vec![
+            (
+                payer,
+                AccountSharedData::new(1_000_000_000, 0, &Pubkey::default()),
+            ),
+            (config, config_account),
+            (tick_array, AccountSharedData::new(0, 0, &Pubkey::default())),
+            (system_program, system_program_account),
+        ],
+    );
+    assert!(matches!(result.program_result, ProgramResult::Success))
+}
+
+#[test]
+fn initialize_tick_array_prefunded() {
+    // Add our built program binary
+    let mollusk: Mollusk = Mollusk::new(&crate::ID, "target/deploy/native_amm_2024");
+
+    // Set our seed
+    let seed: u64 = 1337;
+
+    // Programs
+    let (system_program, system_program_account) = program::system_program();
+
+    // Accounts
+    let payer = Pubkey::new_from_array([0x01; 32]);
+    let mint_x = Pubkey::new_from_array([0x02; 32]);
+    let mint_y = Pubkey::new_from_array([0x03; 32]);
+    let (config, config_bump) = Pubkey::find_program_address(&[b"config", &seed.to_le_bytes()], &crate::ID);
+    let start_tick_index: i32 = -640;
+    let tick_array = Pubkey::find_program_address(&[b"tick_array", config.as_ref(), &start_tick_index.to_le_bytes()], &crate::ID).0;
+
+    // Fill out our account data
+    let mut config_account = AccountSharedData::new(
+        mollusk
+            .sysvars
+            .rent
+            .minimum_balance(mem::size_of::<Config>()),
+        mem::size_of::<Config>(),
+        &crate::ID,
+    );
+    config_account.set_data_from_slice(bytes_of::<Config>(&Config {
+        discriminator: CONFIG_DISCRIMINATOR,
+        seed,
+        authority: crate::ID,
+        pending_authority: Pubkey::default(),
+        mint_x,
+        mint_y,
+        fee: 30u16,
+        locked: 0,
+        config_bump,
+        lp_bump: 0,
+        x_bump: 0,
+        y_bump: 0,
+        curve_type: 3,
+        curve_params: [0; 32],
+        sqrt_price: (1u128 << 64).into(),
+        liquidity: 0.into(),
+        fee_growth_global_x: 0.into(),
+        fee_growth_global_y: 0.into(),
+        tick_current: 0,
+        tick_spacing: 10,
+        protocol_fee: 0,
+        treasury: crate::ID,
+        protocol_fees_x: 0,
+        protocol_fees_y: 0,
+        last_fee_update: 0,
+        fee_update_interval: 0,
+        price_x_cumulative: 0.into(),
+        price_y_cumulative: 0.into(),
+        liquidity_cumulative: 0.into(),
+        price_last_update: 0,
+        locked_liquidity: MINIMUM_LIQUIDITY,
+        fee_max_step: 0,
+        flash_fee: 0,
+        version: CONFIG_VERSION,
+        reentrancy_guard: 0,
+        padding: [0; 2],
+    }));
+
+    // Someone has already sent a lamport to the tick array's address
+    let tick_array_account = AccountSharedData::new(1, 0, &Pubkey::default());
+
+    // Create our instruction
+    let instruction = Instruction::new_with_bytes(
+        crate::ID,
+        &AMMInstructions::InitializeTickArray.serialize::<InitializeTickArray>(
+            InitializeTickArray {
+                start_tick_index,
+            }
+        ),
+        vec![
+            AccountMeta::new(payer, true),
+            AccountMeta::new_readonly(config, false),
+            AccountMeta::new(tick_array, false),
+            AccountMeta::new_readonly(system_program, false),
+        ],
+    );
+
+    let result: mollusk_svm::result::InstructionResult = mollusk.process_instruction(
+        &instruction,
+        &vec![
+            (
+                payer,
+                AccountSharedData::new(1_000_000_000, 0, &Pubkey::default()),
+            ),
+            (config, config_account),
+            (tick_array, tick_array_account),
+            (system_program, system_program_account),
+        ],
+    );
+    assert!(matches!(result.program_result, ProgramResult::Success));
+
+    // The tick array is still created, topped up to rent exemption
+    let tick_array_account = result.get_account(&tick_array).unwrap();
+    assert!(tick_array_account.owner().eq(&crate::ID));
+    assert!(tick_array_account.lamports() == mollusk.sysvars.rent.minimum_balance(tick_array_account.data().len()));
+}
+
+#[test]
+fn open_position() {
+    // Add our built program binary
+    let mollusk: Mollusk = Mollusk::new(&crate::ID, "target/deploy/native_amm_2024");
+
+    // Set our seed
+    let seed: u64 = 1337;
+
+    // Programs
+    let (system_program, system_program_account) = program::system_program();
+
+    // Accounts
+    let owner = Pubkey::new_from_array([0x01; 32]);
+    let mint_x = Pubkey::new_from_array([0x02; 32]);
+    let mint_y = Pubkey::new_from_array([0x03; 32]);
+    let (config, config_bump) = Pubkey::find_program_address(&[b"config", &seed.to_le_bytes()], &crate::ID);
+    let (tick_lower, tick_upper): (i32, i32) = (-100, 100);
+    let position = Pubkey::find_program_address(
+        &[b"position", config.as_ref(), owner.as_ref(), &tick_lower.to_le_bytes(), &tick_upper.to_le_bytes()],
+        &crate::ID,
+    ).0;
+
+    // Fill out our account data
+    let mut config_account = AccountSharedData::new(
+        mollusk
+            .sysvars
+            .rent
+            .minimum_balance(mem::size_of::<Config>()),
+        mem::size_of::<Config>(),
+        &crate::ID,
+    );
+    config_account.set_data_from_slice(bytes_of::<Config>(&Config {
+        discriminator: CONFIG_DISCRIMINATOR,
+        seed,
+        authority: crate::ID,
+        pending_authority: Pubkey::default(),
+        mint_x,
+        mint_y,
+        fee: 30u16,
+        locked: 0,
+        config_bump,
+        lp_bump: 0,
+        x_bump: 0,
+        y_bump: 0,
+        curve_type: 3,
+        curve_params: [0; 32],
+        sqrt_price: (1u128 << 64).into(),
+        liquidity: 0.into(),
+        fee_growth_global_x: 0.into(),
+        fee_growth_global_y: 0.into(),
+        tick_current: 0,
+        tick_spacing: 10,
+        protocol_fee: 0,
+        treasury: crate::ID,
+        protocol_fees_x: 0,
+        protocol_fees_y: 0,
+        last_fee_update: 0,
+        fee_update_interval: 0,
+        price_x_cumulative: 0.into(),
+        price_y_cumulative: 0.into(),
+        liquidity_cumulative: 0.into(),
+        price_last_update: 0,
+        locked_liquidity: MINIMUM_LIQUIDITY,
+        fee_max_step: 0,
+        flash_fee: 0,
+        version: CONFIG_VERSION,
+        reentrancy_guard: 0,
+        padding: [0; 2],
+    }));
+
+    // Create our instruction
+    let instruction = Instruction::new_with_bytes(
+        crate::ID,
+        &AMMInstructions::OpenPosition.serialize::<OpenPosition>(
+            OpenPosition {
+                tick_lower,
+                tick_upper,
+            }
+        ),
+        vec![
+            AccountMeta::new(owner, true),
+            AccountMeta::new_readonly(config, false),
+            AccountMeta::new(position, false),
+            AccountMeta::new_readonly(system_program, false),
+        ],
+    );
+
+    let result: mollusk_svm::result::InstructionResult = mollusk.process_instruction(
+        &instruction,
+        &vec![
+            (
+                owner,
+                AccountSharedData::new(1_000_000_000, 0, &Pubkey::default()),
+            ),
+            (config, config_account),
+            (position, AccountSharedData::new(0, 0, &Pubkey::default())),
+            (system_program, system_program_account),
+        ],
+    );
+    assert!(matches!(result.program_result, ProgramResult::Success))
+}
+
+#[test]
+fn update_fee() {
+    // Add our built program binary
+    let mut mollusk: Mollusk = Mollusk::new(&crate::ID, "target/deploy/native_amm_2024");
+
+    // Set our seed
+    let seed: u64 = 1337;
+
+    // Accounts
+    let authority = Pubkey::new_from_array([0x01; 32]);
+    let mint_x = Pubkey::new_from_array([0x02; 32]);
+    let mint_y = Pubkey::new_from_array([0x03; 32]);
+    let (config, config_bump) = Pubkey::find_program_address(&[b"config", &seed.to_le_bytes()], &crate::ID);
+
+    // Fill out our account data
+    let mut config_account = AccountSharedData::new(
+        mollusk
+            .sysvars
+            .rent
+            .minimum_balance(mem::size_of::<Config>()),
+        mem::size_of::<Config>(),
+        &crate::ID,
+    );
+    config_account.set_data_from_slice(bytes_of::<Config>(&Config {
+        discriminator: CONFIG_DISCRIMINATOR,
+        seed,
+        authority,
+        pending_authority: Pubkey::default(),
+        mint_x,
+        mint_y,
+        fee: 30u16,
+        locked: 0,
+        config_bump,
+        lp_bump: 0,
+        x_bump: 0,
+        y_bump: 0,
+        curve_type: 0,
+        curve_params: [0; 32],
+        sqrt_price: 0.into(),
+        liquidity: 0.into(),
+        fee_growth_global_x: 0.into(),
+        fee_growth_global_y: 0.into(),
+        tick_current: 0,
+        tick_spacing: 0,
+        protocol_fee: 0,
+        treasury: crate::ID,
+        protocol_fees_x: 0,
+        protocol_fees_y: 0,
+        last_fee_update: 0,
+        fee_update_interval: 3600,
+        price_x_cumulative: 0.into(),
+        price_y_cumulative: 0.into(),
+        liquidity_cumulative: 0.into(),
+        price_last_update: 0,
+        locked_liquidity: MINIMUM_LIQUIDITY,
+        fee_max_step: 10,
+        flash_fee: 0,
+        version: CONFIG_VERSION,
+        reentrancy_guard: 0,
+        padding: [0; 2],
+    }));
+
+    // Create our instruction
+    let instruction = Instruction::new_with_bytes(
+        crate::ID,
+        &AMMInstructions::UpdateFee.serialize::<UpdateFee>(
+            UpdateFee {
+                fee: 25,
+            }
+        ),
+        vec![
+            AccountMeta::new(authority, true),
+            AccountMeta::new(config, false),
+        ],
+    );
+
+    // Wait out the update interval
+    mollusk.sysvars.clock.unix_timestamp = 3600;
+
+    let result: mollusk_svm::result::InstructionResult = mollusk.process_instruction(
+        &instruction,
+        &vec![
+            (
+                authority,
+                AccountSharedData::new(1_000_000_000, 0, &Pubkey::default()),
+            ),
+            (config, config_account),
+        ],
+    );
+    assert!(matches!(result.program_result, ProgramResult::Success))
+}
+
+#[test]
+fn accept_authority() {
+    // Add our built program binary
+    let mollusk: Mollusk = Mollusk::new(&crate::ID, "target/deploy/native_amm_2024");
+
+    // Set our seed
+    let seed: u64 = 1337;
+
+    // Accounts
+    let authority = Pubkey::new_from_array([0x01; 32]);
+    let new_authority = Pubkey::new_from_array([0x04; 32]);
+    let mint_x = Pubkey::new_from_array([0x02; 32]);
+    let mint_y = Pubkey::new_from_array([0x03; 32]);
+    let (config, config_bump) = Pubkey::find_program_address(&[b"config", &seed.to_le_bytes()], &crate::ID);
+
+    // Fill out our account data
+    let mut config_account = AccountSharedData::new(
+        mollusk
+            .sysvars
+            .rent
+            .minimum_balance(mem::size_of::<Config>()),
+        mem::size_of::<Config>(),
+        &crate::ID,
+    );
+    config_account.set_data_from_slice(bytes_of::<Config>(&Config {
+        discriminator: CONFIG_DISCRIMINATOR,
+        seed,
+        authority,
+        pending_authority: new_authority,
+        mint_x,
+        mint_y,
+        fee: 30u16,
+        locked: 0,
+        config_bump,
+        lp_bump: 0,
+        x_bump: 0,
+        y_bump: 0,
+        curve_type: 0,
+        curve_params: [0; 32],
+        sqrt_price: 0.into(),
+        liquidity: 0.into(),
+        fee_growth_global_x: 0.into(),
+        fee_growth_global_y: 0.into(),
+        tick_current: 0,
+        tick_spacing: 0,
+        protocol_fee: 0,
+        treasury: crate::ID,
+        protocol_fees_x: 0,
+        protocol_fees_y: 0,
+        last_fee_update: 0,
+        fee_update_interval: 0,
+        price_x_cumulative: 0.into(),
+        price_y_cumulative: 0.into(),
+        liquidity_cumulative: 0.into(),
+        price_last_update: 0,
+        locked_liquidity: MINIMUM_LIQUIDITY,
+        fee_max_step: 0,
+        flash_fee: 0,
+        version: CONFIG_VERSION,
+        reentrancy_guard: 0,
+        padding: [0; 2],
+    }));
+
+    // Create our instruction
+    let instruction = Instruction::new_with_bytes(
+        crate::ID,
+        &[AMMInstructions::AcceptAuthority as u8],
+        vec![
+            AccountMeta::new(new_authority, true),
+            AccountMeta::new(config, false),
+        ],
+    );
+
+    let result: mollusk_svm::result::InstructionResult = mollusk.process_instruction(
+        &instruction,
+        &vec![
+            (
+                new_authority,
+                AccountSharedData::new(1_000_000_000, 0, &Pubkey::default()),
+            ),
+            (config, config_account),
+        ],
+    );
+    assert!(matches!(result.program_result, ProgramResult::Success))
+}
+
+#[test]
+fn update_fee_too_soon() {
+    // Add our built program binary
+    let mollusk: Mollusk = Mollusk::new(&crate::ID, "target/deploy/native_amm_2024");
+
+    // Set our seed
+    let seed: u64 = 1337;
+
+    // Accounts
+    let authority = Pubkey::new_from_array([0x01; 32]);
+    let mint_x = Pubkey::new_from_array([0x02; 32]);
+    let mint_y = Pubkey::new_from_array([0x03; 32]);
+    let (config, config_bump) = Pubkey::find_program_address(&[b"config", &seed.to_le_bytes()], &crate::ID);
+
+    // Fill out our account data
+    let mut config_account = AccountSharedData::new(
+        mollusk
+            .sysvars
+            .rent
+            .minimum_balance(mem::size_of::<Config>()),
+        mem::size_of::<Config>(),
+        &crate::ID,
+    );
+    config_account.set_data_from_slice(bytes_of::<Config>(&Config {
+        discriminator: CONFIG_DISCRIMINATOR,
+        seed,
+        authority,
+        pending_authority: Pubkey::default(),
+        mint_x,
+        mint_y,
+        fee: 30u16,
+        locked: 0,
+        config_bump,
+        lp_bump: 0,
+        x_bump: 0,
+        y_bump: 0,
+        curve_type: 0,
+        curve_params: [0; 32],
+        sqrt_price: 0.into(),
+        liquidity: 0.into(),
+        fee_growth_global_x: 0.into(),
+        fee_growth_global_y: 0.into(),
+        tick_current: 0,
+        tick_spacing: 0,
+        protocol_fee: 0,
+        treasury: crate::ID,
+        protocol_fees_x: 0,
+        protocol_fees_y: 0,
+        last_fee_update: 0,
+        fee_update_interval: 3600,
+        price_x_cumulative: 0.into(),
+        price_y_cumulative: 0.into(),
+        liquidity_cumulative: 0.into(),
+        price_last_update: 0,
+        locked_liquidity: MINIMUM_LIQUIDITY,
+        fee_max_step: 10,
+        flash_fee: 0,
+        version: CONFIG_VERSION,
+        reentrancy_guard: 0,
+        padding: [0; 2],
+    }));
+
+    // Create our instruction
+    let instruction = Instruction::new_with_bytes(
+        crate::ID,
+        &AMMInstructions::UpdateFee.serialize::<UpdateFee>(
+            UpdateFee {
+                fee: 25,
+            }
+        ),
+        vec![
+            AccountMeta::new(authority, true),
+            AccountMeta::new(config, false),
+        ],
+    );
+
+    let result: mollusk_svm::result::InstructionResult = mollusk.process_instruction(
+        &instruction,
+        &vec![
+            (
+                authority,
+                AccountSharedData::new(1_000_000_000, 0, &Pubkey::default()),
+            ),
+            (config, config_account),
+        ],
+    );
+    assert_eq!(
+        result.program_result,
+        ProgramResult::Failure(AmmError::FeeUpdateTooSoon.into())
+    )
+}
+
+#[test]
+fn lock() {
+    // Add our built program binary
+    let mollusk: Mollusk = Mollusk::new(&crate::ID, "target/deploy/native_amm_2024");
+
+    // Set our seed
+    let seed: u64 = 1337;
+
+    // Accounts
+    let authority = Pubkey::new_from_array([0x01; 32]);
+    let mint_x = Pubkey::new_from_array([0x02; 32]);
+    let mint_y = Pubkey::new_from_array([0x03; 32]);
+    let (config, config_bump) = Pubkey::find_program_address(&[b"config", &seed.to_le_bytes()], &crate::ID);
+
+    // Fill out our account data
+    let mut config_account = AccountSharedData::new(
+        mollusk
+            .sysvars
+            .rent
+            .minimum_balance(mem::size_of::<Config>()),
+        mem::size_of::<Config>(),
+        &crate::ID,
+    );
+    config_account.set_data_from_slice(bytes_of::<Config>(&Config {
+        discriminator: CONFIG_DISCRIMINATOR,
+        seed,
+        authority,
+        pending_authority: Pubkey::default(),
+        mint_x,
+        mint_y,
+        fee: 30u16,
+        locked: 0,
+        config_bump,
+        lp_bump: 0,
+        x_bump: 0,
+        y_bump: 0,
+        curve_type: 0,
+        curve_params: [0; 32],
+        sqrt_price: 0.into(),
+        liquidity: 0.into(),
+        fee_growth_global_x: 0.into(),
+        fee_growth_global_y: 0.into(),
+        tick_current: 0,
+        tick_spacing: 0,
+        protocol_fee: 0,
+        treasury: crate::ID,
+        protocol_fees_x: 0,
+        protocol_fees_y: 0,
+        last_fee_update: 0,
+        fee_update_interval: 0,
+        price_x_cumulative: 0.into(),
+        price_y_cumulative: 0.into(),
+        liquidity_cumulative: 0.into(),
+        price_last_update: 0,
+        locked_liquidity: MINIMUM_LIQUIDITY,
+        fee_max_step: 0,
+        flash_fee: 0,
+        version: CONFIG_VERSION,
+        reentrancy_guard: 0,
+        padding: [0; 2],
+    }));
+
+    // Create our instruction
+    let instruction = Instruction::new_with_bytes(
+        crate::ID,
+        &[AMMInstructions::Lock as u8, 1],
+        vec![
+            AccountMeta::new(authority, true),
+            AccountMeta::new(config, false),
+        ],
+    );
+
+    let result: mollusk_svm::result::InstructionResult = mollusk.process_instruction(
+        &instruction,
+        &vec![
+            (
+                authority,
+                AccountSharedData::new(1_000_000_000, 0, &Pubkey::default()),
+            ),
+            (config, config_account),
+        ],
+    );
+    assert!(matches!(result.program_result, ProgramResult::Success));
+
+    // The lock state is written back to the config account
+    let config_account = result.get_account(&config).unwrap();
+    assert_eq!(bytemuck::from_bytes::<Config>(config_account.data()).locked, 1)
+}
+
+#[test]
+fn migrate_config() {
+    // Add our built program binary
+    let mollusk: Mollusk = Mollusk::new(&crate::ID, "target/deploy/native_amm_2024");
+
+    // Set our seed
+    let seed: u64 = 1337;
+
+    // Programs
+    let (system_program, system_program_account) = program::system_program();
+
+    // Accounts
+    let payer = Pubkey::new_from_array([0x01; 32]);
+    let mint_x = Pubkey::new_from_array([0x02; 32]);
+    let mint_y = Pubkey::new_from_array([0x03; 32]);
+    let (config, config_bump) = Pubkey::find_program_address(&[b"config", &seed.to_le_bytes()], &crate::ID);
+
+    // Fill out our account data with the legacy layout
+    let mut config_account = AccountSharedData::new(
+        mollusk
+            .sysvars
+            .rent
+            .minimum_balance(mem::size_of::<ConfigV0>()),
+        mem::size_of::<ConfigV0>(),
+        &crate::ID,
+    );
+    config_account.set_data_from_slice(bytes_of::<ConfigV0>(&ConfigV0 {
+        seed,
+        authority: payer,
+        mint_x,
+        mint_y,
+        fee: 30u16,
+        locked: 0,
+        config_bump,
+        lp_bump: 0,
+        x_bump: 0,
+        y_bump: 0,
+        padding: [0; 1],
+    }));
+
+    // Create our instruction
+    let instruction = Instruction::new_with_bytes(
+        crate::ID,
+        &[AMMInstructions::MigrateConfig as u8],
+        vec![
+            AccountMeta::new(payer, true),
+            AccountMeta::new(config, false),
+            AccountMeta::new_readonly(system_program, false),
+        ],
+    );
+
+    let result: mollusk_svm::result::InstructionResult = mollusk.process_instruction(
+        &instruction,
+        &vec![
+            (
+                payer,
+                AccountSharedData::new(1_000_000_000, 0, &Pubkey::default()),
+            ),
+            (config, config_account),
+            (system_program, system_program_account),
+        ],
+    );
+    assert!(matches!(result.program_result, ProgramResult::Success));
+
+    // The config now holds the current layout
+    let config_account = result.get_account(&config).unwrap();
+    let migrated = bytemuck::from_bytes::<Config>(config_account.data());
+    assert_eq!(migrated.discriminator, CONFIG_DISCRIMINATOR);
+    assert_eq!(migrated.version, CONFIG_VERSION);
+    assert_eq!(migrated.fee, 30)
+}
+
+#[test]
+fn observe() {
+    // Add our built program binary
+    let mut mollusk: Mollusk = Mollusk::new(&crate::ID, "target/deploy/native_amm_2024");
+
+    // Set our seed
+    let seed: u64 = 1337;
+
+    // Programs
+    let token_program = spl_token::ID;
+
+    // Accounts
+    let authority = Pubkey::new_from_array([0x01; 32]);
+    let mint_x = Pubkey::new_from_array([0x02; 32]);
+    let mint_y = Pubkey::new_from_array([0x03; 32]);
+    let (config, config_bump) =
+        Pubkey::find_program_address(&[b"config", &seed.to_le_bytes()], &crate::ID);
+    let (vault_x, x_bump) =
+        Pubkey::find_program_address(&[mint_x.as_ref(), config.as_ref()], &crate::ID);
+    let (vault_y, y_bump) =
+        Pubkey::find_program_address(&[mint_y.as_ref(), config.as_ref()], &crate::ID);
+
+    // Fill out our account data
+    let mut vault_x_account = AccountSharedData::new(
+        mollusk
+            .sysvars
+            .rent
+            .minimum_balance(spl_token::state::Account::LEN),
+        spl_token::state::Account::LEN,
+        &token_program,
+    );
+    solana_program::program_pack::Pack::pack(
+        spl_token::state::Account {
+            mint: mint_x,
+            owner: config,
+            amount: 20,
+            delegate: COption::None,
+            state: spl_token::state::AccountState::Initialized,
+            is_native: COption::None,
+            delegated_amount: 0,
+            close_authority: COption::None,
+        },
+        vault_x_account.data_as_mut_slice(),
+    )
+    .unwrap();
+
+    let mut vault_y_account = AccountSharedData::new(
+        mollusk
+            .sysvars
+            .rent
+            .minimum_balance(spl_token::state::Account::LEN),
+        spl_token::state::Account::LEN,
+        &token_program,
+    );
+    solana_program::program_pack::Pack::pack(
+        spl_token::state::Account {
+            mint: mint_y,
+            owner: config,
+            amount: 30,
+            delegate: COption::None,
+            state: spl_token::state::AccountState::Initialized,
+            is_native: COption::None,
+            delegated_amount: 0,
+            close_authority: COption::None,
+        },
+        vault_y_account.data_as_mut_slice(),
+    )
+    .unwrap();
+
+    let mut config_account = AccountSharedData::new(
+        mollusk
+            .sysvars
+            .rent
+            .minimum_balance(mem::size_of::<Config>()),
+        mem::size_of::<Config>(),
+        &crate::ID,
+    );
+    config_account.set_data_from_slice(bytes_of::<Config>(&Config {
+        discriminator: CONFIG_DISCRIMINATOR,
+        seed,
+        authority,
+        pending_authority: Pubkey::default(),
+        mint_x,
+        mint_y,
+        fee: 30u16,
+        locked: 0,
+        config_bump,
+        lp_bump: 0,
+        x_bump,
+        y_bump,
+        curve_type: 0,
+        curve_params: [0; 32],
+        sqrt_price: 0.into(),
+        liquidity: 0.into(),
+        fee_growth_global_x: 0.into(),
+        fee_growth_global_y: 0.into(),
+        tick_current: 0,
+        tick_spacing: 0,
+        protocol_fee: 0,
+        treasury: crate::ID,
+        protocol_fees_x: 0,
+        protocol_fees_y: 0,
+        last_fee_update: 0,
+        fee_update_interval: 0,
+        price_x_cumulative: 0.into(),
+        price_y_cumulative: 0.into(),
+        liquidity_cumulative: 0.into(),
+        price_last_update: 0,
+        locked_liquidity: MINIMUM_LIQUIDITY,
+        fee_max_step: 0,
+        flash_fee: 0,
+        version: CONFIG_VERSION,
+        reentrancy_guard: 0,
+        padding: [0; 2],
+    }));
+
+    // Create our instruction
+    let instruction = Instruction::new_with_bytes(
+        crate::ID,
+        &[AMMInstructions::Observe as u8],
+        vec![
+            AccountMeta::new_readonly(config, false),
+            AccountMeta::new_readonly(vault_x, false),
+            AccountMeta::new_readonly(vault_y, false),
+        ],
+    );
+
+    // Let some time pass since the last update
+    mollusk.sysvars.clock.unix_timestamp = 100;
+
+    let result: mollusk_svm::result::InstructionResult = mollusk.process_instruction(
+        &instruction,
+        &vec![
+            (config, config_account),
+            (vault_x, vault_x_account),
+            (vault_y, vault_y_account),
+        ],
+    );
+    assert!(matches!(result.program_result, ProgramResult::Success))
+}
+
+#[test]
+fn quote() {
+    // Add our built program binary
+    let mollusk: Mollusk = Mollusk::new(&crate::ID, "target/deploy/native_amm_2024");
+
+    // Set our seed
+    let seed: u64 = 1337;
+
+    // Programs
+    let token_program = spl_token::ID;
+
+    // Accounts
+    let authority = Pubkey::new_from_array([0x01; 32]);
+    let mint_x = Pubkey::new_from_array([0x02; 32]);
+    let mint_y = Pubkey::new_from_array([0x03; 32]);
+    let (config, config_bump) =
+        Pubkey::find_program_address(&[b"config", &seed.to_le_bytes()], &crate::ID);
+    let (mint_lp, lp_bump) = Pubkey::find_program_address(&[config.as_ref()], &crate::ID);
+    let (vault_x, x_bump) =
+        Pubkey::find_program_address(&[mint_x.as_ref(), config.as_ref()], &crate::ID);
+    let (vault_y, y_bump) =
+        Pubkey::find_program_address(&[mint_y.as_ref(), config.as_ref()], &crate::ID);
+
+    // Fill out our account data
+    let mut mint_x_account = AccountSharedData::new(
+        mollusk
+            .sysvars
+            .rent
+            .minimum_balance(spl_token::state::Mint::LEN),
+        spl_token::state::Mint::LEN,
+        &token_program,
+    );
+    solana_program::program_pack::Pack::pack(
+        spl_token::state::Mint {
+            mint_authority: COption::None,
+            supply: 100_000_000_000,
+            decimals: 6,
+            is_initialized: true,
+            freeze_authority: COption::None,
+        },
+        mint_x_account.data_as_mut_slice(),
+    )
+    .unwrap();
+
+    let mut mint_y_account = AccountSharedData::new(
+        mollusk
+            .sysvars
+            .rent
+            .minimum_balance(spl_token::state::Mint::LEN),
+        spl_token::state::Mint::LEN,
+        &token_program,
+    );
+    solana_program::program_pack::Pack::pack(
+        spl_token::state::Mint {
+            mint_authority: COption::None,
+            supply: 100_000_000_000,
+            decimals: 6,
+            is_initialized: true,
+            freeze_authority: COption::None,
+        },
+        mint_y_account.data_as_mut_slice(),
+    )
+    .unwrap();
+
+    let mut mint_lp_account = AccountSharedData::new(
+        mollusk
+            .sysvars
+            .rent
+            .minimum_balance(spl_token::state::Mint::LEN),
+        spl_token::state::Mint::LEN,
+        &token_program,
+    );
+    solana_program::program_pack::Pack::pack(
+        spl_token::state::Mint {
+            mint_authority: COption::Some(config),
+            supply: 19_000,
+            decimals: 6,
+            is_initialized: true,
+            freeze_authority: COption::None,
+        },
+        mint_lp_account.data_as_mut_slice(),
+    )
+    .unwrap();
+
+    let mut vault_x_account = AccountSharedData::new(
+        mollusk
+            .sysvars
+            .rent
+            .minimum_balance(spl_token::state::Account::LEN),
+        spl_token::state::Account::LEN,
+        &token_program,
+    );
+    solana_program::program_pack::Pack::pack(
+        spl_token::state::Account {
+            mint: mint_x,
+            owner: config,
+            amount: 20_000,
+            delegate: COption::None,
+            state: spl_token::state::AccountState::Initialized,
+            is_native: COption::None,
+            delegated_amount: 0,
+            close_authority: COption::None,
+        },
+        vault_x_account.data_as_mut_slice(),
+    )
+    .unwrap();
+
+    let mut vault_y_account = AccountSharedData::new(
+        mollusk
+            .sysvars
+            .rent
+            .minimum_balance(spl_token::state::Account::LEN),
+        spl_token::state::Account::LEN,
+        &token_program,
+    );
+    solana_program::program_pack::Pack::pack(
+        spl_token::state::Account {
+            mint: mint_y,
+            owner: config,
+            amount: 20_000,
+            delegate: COption::None,
+            state: spl_token::state::AccountState::Initialized,
+            is_native: COption::None,
+            delegated_amount: 0,
+            close_authority: COption::None,
+        },
+        vault_y_account.data_as_mut_slice(),
+    )
+    .unwrap();
+
+    let mut config_account = AccountSharedData::new(
+        mollusk
+            .sysvars
+            .rent
+            .minimum_balance(mem::size_of::<Config>()),
+        mem::size_of::<Config>(),
+        &crate::ID,
+    );
+    config_account.set_data_from_slice(bytes_of::<Config>(&Config {
+        discriminator: CONFIG_DISCRIMINATOR,
+        seed,
+        authority,
+        pending_authority: Pubkey::default(),
+        mint_x,
+        mint_y,
+        fee: 30u16,
+        locked: 0,
+        config_bump,
+        lp_bump,
+        x_bump,
+        y_bump,
+        curve_type: 0,
+        curve_params: [0; 32],
+        sqrt_price: 0.into(),
+        liquidity: 0.into(),
+        fee_growth_global_x: 0.into(),
+        fee_growth_global_y: 0.into(),
+        tick_current: 0,
+        tick_spacing: 0,
+        protocol_fee: 0,
+        treasury: crate::ID,
+        protocol_fees_x: 0,
+        protocol_fees_y: 0,
+        last_fee_update: 0,
+        fee_update_interval: 0,
+        price_x_cumulative: 0.into(),
+        price_y_cumulative: 0.into(),
+        liquidity_cumulative: 0.into(),
+        price_last_update: 0,
+        locked_liquidity: MINIMUM_LIQUIDITY,
+        fee_max_step: 0,
+        flash_fee: 0,
+        version: CONFIG_VERSION,
+        reentrancy_guard: 0,
+        padding: [0; 2],
+    }));
+
+    // Create our instruction
+    let instruction = Instruction::new_with_bytes(
+        crate::ID,
+        AMMInstructions::Quote
+            .serialize(Quote {
+                amount: 1_000,
+                max_x: 0,
+                max_y: 0,
+                kind: QuoteKind::Swap as u8,
+                is_x: 1,
+                padding: [0; 6],
+            })
+            .as_ref(),
+        vec![
+            AccountMeta::new_readonly(mint_x, false),
+            AccountMeta::new_readonly(mint_y, false),
+            AccountMeta::new_readonly(mint_lp, false),
+            AccountMeta::new_readonly(vault_x, false),
+            AccountMeta::new_readonly(vault_y, false),
+            AccountMeta::new_readonly(config, false),
+        ],
+    );
+
+    let result: mollusk_svm::result::InstructionResult = mollusk.process_instruction(
+        &instruction,
+        &vec![
+            (mint_x, mint_x_account),
+            (mint_y, mint_y_account),
+            (mint_lp, mint_lp_account),
+            (vault_x, vault_x_account),
+            (vault_y, vault_y_account),
+            (config, config_account),
+        ],
+    );
+    assert!(matches!(result.program_result, ProgramResult::Success))
+}
+
+#[test]
+fn increase_observation_cardinality() {
+    // Add our built program binary
+    let mollusk: Mollusk = Mollusk::new(&crate::ID, "target/deploy/native_amm_2024");
+
+    // Set our seed
+    let seed: u64 = 1337;
+
+    // Programs
+    let (system_program, system_program_account) = program::system_program();
+
+    // Accounts
+    let authority = Pubkey::new_from_array([0x01; 32]);
+    let mint_x = Pubkey::new_from_array([0x02; 32]);
+    let mint_y = Pubkey::new_from_array([0x03; 32]);
+    let (config, config_bump) = Pubkey::find_program_address(&[b"config", &seed.to_le_bytes()], &crate::ID);
+    let observations = Pubkey::find_program_address(&[b"observations", config.as_ref()], &crate::ID).0;
+
+    // Fill out our account data
+    let mut config_account = AccountSharedData::new(
+        mollusk
+            .sysvars
+            .rent
+            .minimum_balance(mem::size_of::<Config>()),
+        mem::size_of::<Config>(),
+        &crate::ID,
+    );
+    config_account.set_data_from_slice(bytes_of::<Config>(&Config {
+        discriminator: CONFIG_DISCRIMINATOR,
+        seed,
+        authority,
+        pending_authority: Pubkey::default(),
+        mint_x,
+        mint_y,
+        fee: 30u16,
+        locked: 0,
+        config_bump,
+        lp_bump: 0,
+        x_bump: 0,
+        y_bump: 0,
+        curve_type: 0,
+        curve_params: [0; 32],
+        sqrt_price: 0.into(),
+        liquidity: 0.into(),
+        fee_growth_global_x: 0.into(),
+        fee_growth_global_y: 0.into(),
+        tick_current: 0,
+        tick_spacing: 0,
+        protocol_fee: 0,
+        treasury: crate::ID,
+        protocol_fees_x: 0,
+        protocol_fees_y: 0,
+        last_fee_update: 0,
+        fee_update_interval: 0,
+        price_x_cumulative: 0.into(),
+        price_y_cumulative: 0.into(),
+        liquidity_cumulative: 0.into(),
+        price_last_update: 0,
+        locked_liquidity: MINIMUM_LIQUIDITY,
+        fee_max_step: 0,
+        flash_fee: 0,
+        version: CONFIG_VERSION,
+        reentrancy_guard: 0,
+        padding: [0; 2],
+    }));
+
+    // Create our instruction
+    let instruction = Instruction::new_with_bytes(
+        crate::ID,
+        &AMMInstructions::IncreaseObservationCardinality.serialize::<IncreaseObservationCardinality>(
+            IncreaseObservationCardinality {
+                cardinality_next: 16,
+            }
+        ),
+        vec![
+            AccountMeta::new(authority, true),
+            AccountMeta::new_readonly(config, false),
+            AccountMeta::new(observations, false),
+            AccountMeta::new_readonly(system_program, false),
+        ],
+    );
+
+    let result: mollusk_svm::result::InstructionResult = mollusk.process_instruction(
+        &instruction,
+        &vec![
+            (
+                authority,
+                AccountSharedData::new(1_000_000_000, 0, &Pubkey::default()),
+            ),
+            (config, config_account),
+            (observations, AccountSharedData::new(0, 0, &Pubkey::default())),
+            (system_program, system_program_account),
+        ],
+    );
+    assert!(matches!(result.program_result, ProgramResult::Success));
+
+    // The ring holds a single observation with room to grow
+    let observations_account = result.get_account(&observations).unwrap();
+    assert_eq!(observations_account.data().len(), Observations::space(16));
+    let header = bytemuck::from_bytes::<Observations>(&observations_account.data()[..mem::size_of::<Observations>()]);
+    assert_eq!((header.cardinality, header.cardinality_next), (1, 16))
+}
+
+#[test]
+fn decode_events() {
+    let event = AmmEvent::Swap(SwapEvent {
+        pool: Pubkey::new_from_array([0x01; 32]),
+        user: Pubkey::new_from_array([0x02; 32]),
+        amount_in: 1_000,
+        amount_out: 996,
+        fee: 3,
+        reserve_x: 11_000,
+        reserve_y: 9_004,
+        is_x: 1,
+        padding: [0; 7],
+    });
+
+    // Events decode from the bytes they are logged as
+    let data = event.serialize();
+    assert!(AmmEvent::try_from(data.as_ref()) == Ok(event));
+
+    // Unknown discriminators and truncated events are rejected
+    assert!(AmmEvent::try_from([5u8].as_ref()).is_err());
+    assert!(AmmEvent::try_from(&data[..data.len() - 1]).is_err());
+}
+
+#[test]
+fn flash_loan() {
+    // Add our built program binary
+    let mut mollusk: Mollusk = Mollusk::new(&crate::ID, "target/deploy/native_amm_2024");
+
+    // Set our seed
+    let seed: u64 = 1337;
+
+    // Programs
+    mollusk.add_program(&spl_token::ID, "src/tests/spl_token");
+    let (token_program, token_program_account) = (spl_token::ID, program_account(&spl_token::ID));
+    let (system_program, system_program_account) = program::system_program();
+
+    // Accounts
+    let borrower = Pubkey::new_from_array([0x01; 32]);
+    let mint_x = Pubkey::new_from_array([0x02; 32]);
+    let mint_y = Pubkey::new_from_array([0x03; 32]);
+    let borrower_x = Pubkey::new_from_array([0x04; 32]);
+    let (config, config_bump) =
+        Pubkey::find_program_address(&[b"config", &seed.to_le_bytes()], &crate::ID);
+    let (vault_x, x_bump) =
+        Pubkey::find_program_address(&[mint_x.as_ref(), config.as_ref()], &crate::ID);
+    let (vault_y, y_bump) =
+        Pubkey::find_program_address(&[mint_y.as_ref(), config.as_ref()], &crate::ID);
+
+    // Fill out our account data
+    let mut mint_x_account = AccountSharedData::new(
+        mollusk
+            .sysvars
+            .rent
+            .minimum_balance(spl_token::state::Mint::LEN),
+        spl_token::state::Mint::LEN,
+        &token_program,
+    );
+    solana_program::program_pack::Pack::pack(
+        spl_token::state::Mint {
+            mint_authority: COption::None,
+            supply: 100_000_000_000,
+            decimals: 6,
+            is_initialized: true,
+            freeze_authority: COption::None,
+        },
+        mint_x_account.data_as_mut_slice(),
+    )
+    .unwrap();
+
+    let mut vault_x_account = AccountSharedData::new(
+        mollusk
+            .sysvars
+            .rent
+            .minimum_balance(spl_token::state::Account::LEN),
+        spl_token::state::Account::LEN,
+        &token_program,
+    );
+    solana_program::program_pack::Pack::pack(
+        spl_token::state::Account {
+            mint: mint_x,
+            owner: config,
+            amount: 20,
+            delegate: COption::None,
+            state: spl_token::state::AccountState::Initialized,
+            is_native: COption::None,
+            delegated_amount: 0,
+            close_authority: COption::None,
+        },
+        vault_x_account.data_as_mut_slice(),
+    )
+    .unwrap();
+
+    let mut vault_y_account = AccountSharedData::new(
+        mollusk
+            .sysvars
+            .rent
+            .minimum_balance(spl_token::state::Account::LEN),
+        spl_token::state::Account::LEN,
+        &token_program,
+    );
+    solana_program::program_pack::Pack::pack(
+        spl_token::state::Account {
+            mint: mint_y,
+            owner: config,
+            amount: 30,
+            delegate: COption::None,
+            state: spl_token::state::AccountState::Initialized,
+            is_native: COption::None,
+            delegated_amount: 0,
+            close_authority: COption::None,
+        },
+        vault_y_account.data_as_mut_slice(),
+    )
+    .unwrap();
+
+    let mut borrower_x_account = AccountSharedData::new(
+        mollusk
+            .sysvars
+            .rent
+            .minimum_balance(spl_token::state::Account::LEN),
+        spl_token::state::Account::LEN,
+        &token_program,
+    );
+    solana_program::program_pack::Pack::pack(
+        spl_token::state::Account {
+            mint: mint_x,
+            owner: borrower,
+            amount: 100_000_000,
+            delegate: COption::None,
+            state: spl_token::state::AccountState::Initialized,
+            is_native: COption::None,
+            delegated_amount: 0,
+            close_authority: COption::None,
+        },
+        borrower_x_account.data_as_mut_slice(),
+    )
+    .unwrap();
+
+    let mut config_account = AccountSharedData::new(
+        mollusk
+            .sysvars
+            .rent
+            .minimum_balance(mem::size_of::<Config>()),
+        mem::size_of::<Config>(),
+        &crate::ID,
+    );
+    config_account.set_data_from_slice(bytes_of::<Config>(&Config {
+        discriminator: CONFIG_DISCRIMINATOR,
+        seed,
+        authority: crate::ID,
+        pending_authority: Pubkey::default(),
+        mint_x,
+        mint_y,
+        fee: 100u16,
+        locked: 0,
+        config_bump,
+        lp_bump: 0,
+        x_bump,
+        y_bump,
+        curve_type: 0,
+        curve_params: [0; 32],
+        sqrt_price: 0.into(),
+        liquidity: 0.into(),
+        fee_growth_global_x: 0.into(),
+        fee_growth_global_y: 0.into(),
+        tick_current: 0,
+        tick_spacing: 0,
+        protocol_fee: 0,
+        treasury: crate::ID,
+        protocol_fees_x: 0,
+        protocol_fees_y: 0,
+        last_fee_update: 0,
+        fee_update_interval: 0,
+        price_x_cumulative: 0.into(),
+        price_y_cumulative: 0.into(),
+        liquidity_cumulative: 0.into(),
+        price_last_update: 0,
+        locked_liquidity: MINIMUM_LIQUIDITY,
+        fee_max_step: 0,
+        flash_fee: 30,
+        version: CONFIG_VERSION,
+        reentrancy_guard: 0,
+        padding: [0; 2],
+    }));
+
+    // Repay the loan and its fee from the borrower's own tokens in the callback
+    let callback = spl_token::instruction::transfer_checked(
+        &token_program,
+        &borrower_x,
+        &mint_x,
+        &vault_x,
+        &borrower,
+        &[],
+        1_003,
+        6,
+    )
+    .unwrap();
+
+    // Create our instruction
+    let instruction = Instruction::new_with_bytes(
+        crate::ID,
+        &[
+            AMMInstructions::FlashLoan.serialize::<FlashLoan>(
+                FlashLoan {
+                    amount: 1_000,
+                    expiration: i64::MAX,
+                    is_x: 1,
+                    padding: [0; 7],
+                }
+            ),
+            callback.data,
+        ]
+        .concat(),
+        vec![
+            AccountMeta::new(borrower, true),
+            AccountMeta::new_readonly(mint_x, false),
+            AccountMeta::new(borrower_x, false),
+            AccountMeta::new(vault_x, false),
+            AccountMeta::new_readonly(vault_y, false),
+            AccountMeta::new_readonly(config, false),
+            AccountMeta::new_readonly(token_program, false),
+            AccountMeta::new_readonly(token_program, false),
+            AccountMeta::new(borrower_x, false),
+            AccountMeta::new_readonly(mint_x, false),
+            AccountMeta::new(vault_x, false),
+            AccountMeta::new_readonly(borrower, true),
+        ],
+    );
+
+    let result: mollusk_svm::result::InstructionResult = mollusk.process_instruction(
+        &instruction,
+        &vec![
+            (
+                borrower,
+                AccountSharedData::new(1_000_000_000, 0, &Pubkey::default()),
+            ),
+            (mint_x, mint_x_account),
+            (borrower_x, borrower_x_account),
+            (vault_x, vault_x_account),
+            (vault_y, vault_y_account),
+            (config, config_account),
+            (token_program, token_program_account),
+            (system_program, system_program_account),
+        ],
+    );
+    assert!(matches!(result.program_result, ProgramResult::Success))
+}
+
+#[test]
+fn flash_swap() {
+    // Add our built program binary
+    let mut mollusk: Mollusk = Mollusk::new(&crate::ID, "target/deploy/native_amm_2024");
+
+    // Set our seed
+    let seed: u64 = 1337;
+
+    // Programs
+    mollusk.add_program(&spl_token::ID, "src/tests/spl_token");
+    let (token_program, token_program_account) = (spl_token::ID, program_account(&spl_token::ID));
+    let (system_program, system_program_account) = program::system_program();
+
+    // Accounts
+    let user = Pubkey::new_from_array([0x01; 32]);
+    let mint_x = Pubkey::new_from_array([0x02; 32]);
+    let mint_y = Pubkey::new_from_array([0x03; 32]);
+    let user_from = Pubkey::new_from_array([0x04; 32]);
+    let user_to = Pubkey::new_from_array([0x05; 32]);
+    let (config, config_bump) =
+        Pubkey::find_program_address(&[b"config", &seed.to_le_bytes()], &crate::ID);
+    let (vault_x, x_bump) =
+        Pubkey::find_program_address(&[mint_x.as_ref(), config.as_ref()], &crate::ID);
+    let (vault_y, y_bump) =
+        Pubkey::find_program_address(&[mint_y.as_ref(), config.as_ref()], &crate::ID);
+
+    // Fill out our account data
+    let mut mint_x_account = AccountSharedData::new(
+        mollusk
+            .sysvars
+            .rent
+            .minimum_balance(spl_token::state::Mint::LEN),
+        spl_token::state::Mint::LEN,
+        &token_program,
+    );
+    solana_program::program_pack::Pack::pack(
+        spl_token::state::Mint {
+            mint_authority: COption::None,
+            supply: 100_000_000_000,
+            decimals: 6,
+            is_initialized: true,
+            freeze_authority: COption::None,
+        },
+        mint_x_account.data_as_mut_slice(),
+    )
+    .unwrap();
+
+    let mut mint_y_account = AccountSharedData::new(
+        mollusk
+            .sysvars
+            .rent
+            .minimum_balance(spl_token::state::Mint::LEN),
+        spl_token::state::Mint::LEN,
+        &token_program,
+    );
+    solana_program::program_pack::Pack::pack(
+        spl_token::state::Mint {
+            mint_authority: COption::None,
+            supply: 100_000_000_000,
+            decimals: 6,
+            is_initialized: true,
+            freeze_authority: COption::None,
+        },
+        mint_y_account.data_as_mut_slice(),
+    )
+    .unwrap();
+
+    let mut vault_x_account = AccountSharedData::new(
+        mollusk
+            .sysvars
+            .rent
+            .minimum_balance(spl_token::state::Account::LEN),
+        spl_token::state::Account::LEN,
+        &token_program,
+    );
+    solana_program::program_pack::Pack::pack(
+        spl_token::state::Account {
+            mint: mint_x,
+            owner: config,
+            amount: 20,
+            delegate: COption::None,
+            state: spl_token::state::AccountState::Initialized,
+            is_native: COption::None,
+            delegated_amount: 0,
+            close_authority: COption::None,
+        },
+        vault_x_account.data_as_mut_slice(),
+    )
+    .unwrap();
+
+    let mut vault_y_account = AccountSharedData::new(
+        mollusk
+            .sysvars
+            .rent
+            .minimum_balance(spl_token::state::Account::LEN),
+        spl_token::state::Account::LEN,
+        &token_program,
+    );
+    solana_program::program_pack::Pack::pack(
+        spl_token::state::Account {
+            mint: mint_y,
+            owner: config,
+            amount: 30,
+            delegate: COption::None,
+            state: spl_token::state::AccountState::Initialized,
+            is_native: COption::None,
+            delegated_amount: 0,
+            close_authority: COption::None,
+        },
+        vault_y_account.data_as_mut_slice(),
+    )
+    .unwrap();
+
+    let mut user_from_account = AccountSharedData::new(
+        mollusk
+            .sysvars
+            .rent
+            .minimum_balance(spl_token::state::Account::LEN),
+        spl_token::state::Account::LEN,
+        &token_program,
+    );
+    solana_program::program_pack::Pack::pack(
+        spl_token::state::Account {
+            mint: mint_x,
+            owner: user,
+            amount: 100_000_000,
+            delegate: COption::None,
+            state: spl_token::state::AccountState::Initialized,
+            is_native: COption::None,
+            delegated_amount: 0,
+            close_authority: COption::None,
+        },
+        user_from_account.data_as_mut_slice(),
+    )
+    .unwrap();
+
+    let mut user_to_account = AccountSharedData::new(
+        mollusk
+            .sysvars
+            .rent
+            .minimum_balance(spl_token::state::Account::LEN),
+        spl_token::state::Account::LEN,
+        &token_program,
+    );
+    solana_program::program_pack::Pack::pack(
+        spl_token::state::Account {
+            mint: mint_y,
+            owner: user,
+            amount: 100_000_000,
+            delegate: COption::None,
+            state: spl_token::state::AccountState::Initialized,
+            is_native: COption::None,
+            delegated_amount: 0,
+            close_authority: COption::None,
+        },
+        user_to_account.data_as_mut_slice(),
+    )
+    .unwrap();
+
+    let mut config_account = AccountSharedData::new(
+        mollusk
+            .sysvars
+            .rent
+            .minimum_balance(mem::size_of::<Config>()),
+        mem::size_of::<Config>(),
+        &crate::ID,
+    );
+    config_account.set_data_from_slice(bytes_of::<Config>(&Config {
+        discriminator: CONFIG_DISCRIMINATOR,
+        seed,
+        authority: crate::ID,
+        pending_authority: Pubkey::default(),
+        mint_x,
+        mint_y,
+        fee: 100u16,
+        locked: 0,
+        config_bump,
+        lp_bump: 0,
+        x_bump,
+        y_bump,
+        curve_type: 0,
+        curve_params: [0; 32],
+        sqrt_price: 0.into(),
+        liquidity: 0.into(),
+        fee_growth_global_x: 0.into(),
+        fee_growth_global_y: 0.into(),
+        tick_current: 0,
+        tick_spacing: 0,
+        protocol_fee: 0,
+        treasury: crate::ID,
+        protocol_fees_x: 0,
+        protocol_fees_y: 0,
+        last_fee_update: 0,
+        fee_update_interval: 0,
+        price_x_cumulative: 0.into(),
+        price_y_cumulative: 0.into(),
+        liquidity_cumulative: 0.into(),
+        price_last_update: 0,
+        locked_liquidity: MINIMUM_LIQUIDITY,
+        fee_max_step: 0,
+        flash_fee: 0,
+        version: CONFIG_VERSION,
+        reentrancy_guard: 0,
+        padding: [0; 2],
+    }));
+
+    // Pay for the swap from the user's own tokens in the callback
+    let callback = spl_token::instruction::transfer_checked(
+        &token_program,
+        &user_from,
+        &mint_x,
+        &vault_x,
+        &user,
+        &[],
+        5,
+        6,
+    )
+    .unwrap();
+
+    // Create our instruction
+    let instruction = Instruction::new_with_bytes(
+        crate::ID,
+        &[
+            AMMInstructions::FlashSwap.serialize::<Swap>(
+                Swap {
+                    amount: 5,
+                    min: 0,
+                    expiration: i64::MAX,
+                }
+            ),
+            callback.data,
+        ]
+        .concat(),
+        vec![
+            AccountMeta::new(user, true),
+            AccountMeta::new_readonly(mint_x, false),
+            AccountMeta::new_readonly(mint_y, false),
+            AccountMeta::new(user_from, false),
+            AccountMeta::new(user_to, false),
+            AccountMeta::new(vault_x, false),
+            AccountMeta::new(vault_y, false),
+            AccountMeta::new(config, false),
+            AccountMeta::new_readonly(token_program, false),
+            AccountMeta::new_readonly(token_program, false),
+            AccountMeta::new_readonly(token_program, false),
+            AccountMeta::new(user_from, false),
+            AccountMeta::new_readonly(mint_x, false),
+            AccountMeta::new(vault_x, false),
+            AccountMeta::new_readonly(user, true),
+        ],
+    );
+
+    let result: mollusk_svm::result::InstructionResult = mollusk.process_instruction(
+        &instruction,
+        &vec![
+            (
+                user,
+                AccountSharedData::new(1_000_000_000, 0, &Pubkey::default()),
+            ),
+            (mint_x, mint_x_account),
+            (mint_y, mint_y_account),
+            (user_from, user_from_account),
+            (user_to, user_to_account),
+            (vault_x, vault_x_account),
+            (vault_y, vault_y_account),
+            (config, config_account),
+            (token_program, token_program_account),
+            (system_program, system_program_account),
+        ],
+    );
+    assert!(matches!(result.program_result, ProgramResult::Success))
+}
+
+#[test]
+fn route_swap() {
+    // Add our built program binary
+    let mut mollusk: Mollusk = Mollusk::new(&crate::ID, "target/deploy/native_amm_2024");
+
+    // Set our seeds, one pool of X and Y and one of Y and Z
+    let seed_xy: u64 = 1337;
+    let seed_yz: u64 = 1338;
+
+    // Programs
+    mollusk.add_program(&spl_token::ID, "src/tests/spl_token");
+    let (token_program, token_program_account) = (spl_token::ID, program_account(&spl_token::ID));
+
+    // Accounts
+    let user = Pubkey::new_from_array([0x01; 32]);
+    let mint_x = Pubkey::new_from_array([0x02; 32]);
+    let mint_y = Pubkey::new_from_array([0x03; 32]);
+    let mint_z = Pubkey::new_from_array([0x04; 32]);
+    let user_x = Pubkey::new_from_array([0x05; 32]);
+    let user_y = Pubkey::new_from_array([0x06; 32]);
+    let user_z = Pubkey::new_from_array([0x07; 32]);
+    let (config_xy, config_xy_bump) =
+        Pubkey::find_program_address(&[b"config", &seed_xy.to_le_bytes()], &crate::ID);
+    let (config_yz, config_yz_bump) =
+        Pubkey::find_program_address(&[b"config", &seed_yz.to_le_bytes()], &crate::ID);
+    let (vault_xy_x, vault_xy_x_bump) =
+        Pubkey::find_program_address(&[mint_x.as_ref(), config_xy.as_ref()], &crate::ID);
+    let (vault_xy_y, vault_xy_y_bump) =
+        Pubkey::find_program_address(&[mint_y.as_ref(), config_xy.as_ref()], &crate::ID);
+    let (vault_yz_y, vault_yz_y_bump) =
+        Pubkey::find_program_address(&[mint_y.as_ref(), config_yz.as_ref()], &crate::ID);
+    let (vault_yz_z, vault_yz_z_bump) =
+        Pubkey::find_program_address(&[mint_z.as_ref(), config_yz.as_ref()], &crate::ID);
+
+    // Fill out our account data
+    let mut mint_x_account = AccountSharedData::new(
+        mollusk
+            .sysvars
+            .rent
+            .minimum_balance(spl_token::state::Mint::LEN),
+        spl_token::state::Mint::LEN,
+        &token_program,
+    );
+    solana_program::program_pack::Pack::pack(
+        spl_token::state::Mint {
+            mint_authority: COption::None,
+            supply: 100_000_000_000,
+            decimals: 6,
+            is_initialized: true,
+            freeze_authority: COption::None,
+        },
+        mint_x_account.data_as_mut_slice(),
+    )
+    .unwrap();
+
+    let mut mint_y_account = AccountSharedData::new(
+        mollusk
+            .sysvars
+            .rent
+            .minimum_balance(spl_token::state::Mint::LEN),
+        spl_token::state::Mint::LEN,
+        &token_program,
+    );
+    solana_program::program_pack::Pack::pack(
+        spl_token::state::Mint {
+            mint_authority: COption::None,
+            supply: 100_000_000_000,
+            decimals: 6,
+            is_initialized: true,
+            freeze_authority: COption::None,
+        },
+        mint_y_account.data_as_mut_slice(),
+    )
+    .unwrap();
+
+    let mut mint_z_account = AccountSharedData::new(
+        mollusk
+            .sysvars
+            .rent
+            .minimum_balance(spl_token::state::Mint::LEN),
+        spl_token::state::Mint::LEN,
+        &token_program,
+    );
+    solana_program::program_pack::Pack::pack(
+        spl_token::state::Mint {
+            mint_authority: COption::None,
+            supply: 100_000_000_000,
+            decimals: 6,
+            is_initialized: true,
+            freeze_authority: COption::None,
+        },
+        mint_z_account.data_as_mut_slice(),
+    )
+    .unwrap();
+
+    let mut vault_xy_x_account = AccountSharedData::new(
+        mollusk
+            .sysvars
+            .rent
+            .minimum_balance(spl_token::state::Account::LEN),
+        spl_token::state::Account::LEN,
+        &token_program,
+    );
+    solana_program::program_pack::Pack::pack(
+        spl_token::state::Account {
+            mint: mint_x,
+            owner: config_xy,
+            amount: 1_000_000,
+            delegate: COption::None,
+            state: spl_token::state::AccountState::Initialized,
+            is_native: COption::None,
+            delegated_amount: 0,
+            close_authority: COption::None,
+        },
+        vault_xy_x_account.data_as_mut_slice(),
+    )
+    .unwrap();
+
+    let mut vault_xy_y_account = AccountSharedData::new(
+        mollusk
+            .sysvars
+            .rent
+            .minimum_balance(spl_token::state::Account::LEN),
+        spl_token::state::Account::LEN,
+        &token_program,
+    );
+    solana_program::program_pack::Pack::pack(
+        spl_token::state::Account {
+            mint: mint_y,
+            owner: config_xy,
+            amount: 1_000_000,
+            delegate: COption::None,
+            state: spl_token::state::AccountState::Initialized,
+            is_native: COption::None,
+            delegated_amount: 0,
+            close_authority: COption::None,
+        },
+        vault_xy_y_account.data_as_mut_slice(),
+    )
+    .unwrap();
+
+    let mut vault_yz_y_account = AccountSharedData::new(
+        mollusk
+            .sysvars
+            .rent
+            .minimum_balance(spl_token::state::Account::LEN),
+        spl_token::state::Account::LEN,
+        &token_program,
+    );
+    solana_program::program_pack::Pack::pack(
+        spl_token::state::Account {
+            mint: mint_y,
+            owner: config_yz,
+            amount: 1_000_000,
+            delegate: COption::None,
+            state: spl_token::state::AccountState::Initialized,
+            is_native: COption::None,
+            delegated_amount: 0,
+            close_authority: COption::None,
+        },
+        vault_yz_y_account.data_as_mut_slice(),
+    )
+    .unwrap();
+
+    let mut vault_yz_z_account = AccountSharedData::new(
+        mollusk
+            .sysvars
+            .rent
+            .minimum_balance(spl_token::state::Account::LEN),
+        spl_token::state::Account::LEN,
+        &token_program,
+    );
+    solana_program::program_pack::Pack::pack(
+        spl_token::state::Account {
+            mint: mint_z,
+            owner: config_yz,
+            amount: 1_000_000,
+            delegate: COption::None,
+            state: spl_token::state::AccountState::Initialized,
+            is_native: COption::None,
+            delegated_amount: 0,
+            close_authority: COption::None,
+        },
+        vault_yz_z_account.data_as_mut_slice(),
+    )
+    .unwrap();
+
+    let mut user_x_account = AccountSharedData::new(
+        mollusk
+            .sysvars
+            .rent
+            .minimum_balance(spl_token::state::Account::LEN),
+        spl_token::state::Account::LEN,
+        &token_program,
+    );
+    solana_program::program_pack::Pack::pack(
+        spl_token::state::Account {
+            mint: mint_x,
+            owner: user,
+            amount: 100_000,
+            delegate: COption::None,
+            state: spl_token::state::AccountState::Initialized,
+            is_native: COption::None,
+            delegated_amount: 0,
+            close_authority: COption::None,
+        },
+        user_x_account.data_as_mut_slice(),
+    )
+    .unwrap();
+
+    let mut user_y_account = AccountSharedData::new(
+        mollusk
+            .sysvars
+            .rent
+            .minimum_balance(spl_token::state::Account::LEN),
+        spl_token::state::Account::LEN,
+        &token_program,
+    );
+    solana_program::program_pack::Pack::pack(
+        spl_token::state::Account {
+            mint: mint_y,
+            owner: user,
+            amount: 0,
+            delegate: COption::None,
+            state: spl_token::state::AccountState::Initialized,
+            is_native: COption::None,
+            delegated_amount: 0,
+            close_authority: COption::None,
+        },
+        user_y_account.data_as_mut_slice(),
+    )
+    .unwrap();
+
+    let mut user_z_account = AccountSharedData::new(
+        mollusk
+            .sysvars
+            .rent
+            .minimum_balance(spl_token::state::Account::LEN),
+        spl_token::state::Account::LEN,
+        &token_program,
+    );
+    solana_program::program_pack::Pack::pack(
+        spl_token::state::Account {
+            mint: mint_z,
+            owner: user,
+            amount: 0,
+            delegate: COption::None,
+            state: spl_token::state::AccountState::Initialized,
+            is_native: COption::None,
+            delegated_amount: 0,
+            close_authority: COption::None,
+        },
+        user_z_account.data_as_mut_slice(),
+    )
+    .unwrap();
+
+    let mut config_xy_account = AccountSharedData::new(
+        mollusk
+            .sysvars
+            .rent
+            .minimum_balance(mem::size_of::<Config>()),
+        mem::size_of::<Config>(),
+        &crate::ID,
+    );
+    config_xy_account.set_data_from_slice(bytes_of::<Config>(&Config {
+        discriminator: CONFIG_DISCRIMINATOR,
+        seed: seed_xy,
+        authority: crate::ID,
+        pending_authority: Pubkey::default(),
+        mint_x,
+        mint_y,
+        fee: 30u16,
+        locked: 0,
+        config_bump: config_xy_bump,
+        lp_bump: 0,
+        x_bump: vault_xy_x_bump,
+        y_bump: vault_xy_y_bump,
+        curve_type: 0,
+        curve_params: [0; 32],
+        sqrt_price: 0.into(),
+        liquidity: 0.into(),
+        fee_growth_global_x: 0.into(),
+        fee_growth_global_y: 0.into(),
+        tick_current: 0,
+        tick_spacing: 0,
+        protocol_fee: 0,
+        treasury: crate::ID,
+        protocol_fees_x: 0,
+        protocol_fees_y: 0,
+        last_fee_update: 0,
+        fee_update_interval: 0,
+        price_x_cumulative: 0.into(),
+        price_y_cumulative: 0.into(),
+        liquidity_cumulative: 0.into(),
+        price_last_update: 0,
+        locked_liquidity: MINIMUM_LIQUIDITY,
+        fee_max_step: 0,
+        flash_fee: 0,
+        version: CONFIG_VERSION,
+        reentrancy_guard: 0,
+        padding: [0; 2],
+    }));
+
+    let mut config_yz_account = AccountSharedData::new(
+        mollusk
+            .sysvars
+            .rent
+            .minimum_balance(mem::size_of::<Config>()),
+        mem::size_of::<Config>(),
+        &crate::ID,
+    );
+    config_yz_account.set_data_from_slice(bytes_of::<Config>(&Config {
+        discriminator: CONFIG_DISCRIMINATOR,
+        seed: seed_yz,
+        authority: crate::ID,
+        pending_authority: Pubkey::default(),
+        mint_x: mint_y,
+        mint_y: mint_z,
+        fee: 30u16,
+        locked: 0,
+        config_bump: config_yz_bump,
+        lp_bump: 0,
+        x_bump: vault_yz_y_bump,
+        y_bump: vault_yz_z_bump,
+        curve_type: 0,
+        curve_params: [0; 32],
+        sqrt_price: 0.into(),
+        liquidity: 0.into(),
+        fee_growth_global_x: 0.into(),
+        fee_growth_global_y: 0.into(),
+        tick_current: 0,
+        tick_spacing: 0,
+        protocol_fee: 0,
+        treasury: crate::ID,
+        protocol_fees_x: 0,
+        protocol_fees_y: 0,
+        last_fee_update: 0,
+        fee_update_interval: 0,
+        price_x_cumulative: 0.into(),
+        price_y_cumulative: 0.into(),
+        liquidity_cumulative: 0.into(),
+        price_last_update: 0,
+        locked_liquidity: MINIMUM_LIQUIDITY,
+        fee_max_step: 0,
+        flash_fee: 0,
+        version: CONFIG_VERSION,
+        reentrancy_guard: 0,
+        padding: [0; 2],
+    }));
+
+    // Create our instruction, swapping X for Y in the first pool and that Y for Z in the second
+    let instruction = Instruction::new_with_bytes(
+        crate::ID,
+        &AMMInstructions::RouteSwap.serialize::<RouteSwap>(
+            RouteSwap {
+                amount: 10_000,
+                min_out: 9_000,
+                expiration: i64::MAX,
+                hops: 2,
+                padding: [0; 7],
+            }
+        ),
+        vec![
+            AccountMeta::new(user, true),
+            AccountMeta::new(user_x, false),
+            AccountMeta::new(config_xy, false),
+            AccountMeta::new_readonly(mint_x, false),
+            AccountMeta::new_readonly(mint_y, false),
+            AccountMeta::new(vault_xy_x, false),
+            AccountMeta::new(vault_xy_y, false),
+            AccountMeta::new_readonly(token_program, false),
+            AccountMeta::new_readonly(token_program, false),
+            AccountMeta::new(user_y, false),
+            AccountMeta::new(config_yz, false),
+            AccountMeta::new_readonly(mint_y, false),
+            AccountMeta::new_readonly(mint_z, false),
+            AccountMeta::new(vault_yz_y, false),
+            AccountMeta::new(vault_yz_z, false),
+            AccountMeta::new_readonly(token_program, false),
+            AccountMeta::new_readonly(token_program, false),
+            AccountMeta::new(user_z, false),
+        ],
+    );
+
+    let result: mollusk_svm::result::InstructionResult = mollusk.process_instruction(
+        &instruction,
+        &vec![
+            (
+                user,
+                AccountSharedData::new(1_000_000_000, 0, &Pubkey::default()),
+            ),
+            (user_x, user_x_account),
+            (config_xy, config_xy_account),
+            (mint_x, mint_x_account),
+            (mint_y, mint_y_account),
+            (vault_xy_x, vault_xy_x_account),
+            (vault_xy_y, vault_xy_y_account),
+            (token_program, token_program_account),
+            (user_y, user_y_account),
+            (config_yz, config_yz_account),
+            (mint_z, mint_z_account),
+            (vault_yz_y, vault_yz_y_account),
+            (vault_yz_z, vault_yz_z_account),
+            (user_z, user_z_account),
+        ],
+    );
+    assert!(matches!(result.program_result, ProgramResult::Success))
+}
+
+#[test]
+fn token_2022_transfer_fees() {
+    // Set our seed
+    let seed: u64 = 1337;
+
+    // Accounts
+    let user = Pubkey::new_from_array([0x01; 32]);
+    let mint_x = Pubkey::new_from_array([0x02; 32]);
+    let mint_y = Pubkey::new_from_array([0x03; 32]);
+    let user_x = Pubkey::new_from_array([0x04; 32]);
+    let user_y = Pubkey::new_from_array([0x05; 32]);
+    let user_lp = Pubkey::new_from_array([0x06; 32]);
+    let (config, config_bump) =
+        Pubkey::find_program_address(&[b"config", &seed.to_le_bytes()], &crate::ID);
+    let (mint_lp, lp_bump) = Pubkey::find_program_address(&[config.as_ref()], &crate::ID);
+    let (vault_x, x_bump) =
+        Pubkey::find_program_address(&[mint_x.as_ref(), config.as_ref()], &crate::ID);
+    let (vault_y, y_bump) =
+        Pubkey::find_program_address(&[mint_y.as_ref(), config.as_ref()], &crate::ID);
+
+    // A fresh pool of 1,000,000 X and Y, both withholding 1% of every transfer, for each call
+    let pool = || {
+        let mut mint_lp_data = vec![0; spl_token::state::Mint::LEN];
+        solana_program::program_pack::Pack::pack(
+            spl_token::state::Mint {
+                mint_authority: COption::Some(config),
+                supply: 999_000,
+                decimals: 6,
+                is_initialized: true,
+                freeze_authority: COption::None,
+            },
+            &mut mint_lp_data,
+        )
+        .unwrap();
+
+        let mut user_lp_data = vec![0; spl_token::state::Account::LEN];
+        solana_program::program_pack::Pack::pack(
+            spl_token::state::Account {
+                mint: mint_lp,
+                owner: user,
+                amount: 100_000,
+                delegate: COption::None,
+                state: spl_token::state::AccountState::Initialized,
+                is_native: COption::None,
+                delegated_amount: 0,
+                close_authority: COption::None,
+            },
+            &mut user_lp_data,
+        )
+        .unwrap();
+
+        let config_data = bytes_of::<Config>(&Config {
+            discriminator: CONFIG_DISCRIMINATOR,
+            seed,
+            authority: crate::ID,
+            pending_authority: Pubkey::default(),
+            mint_x,
+            mint_y,
+            fee: 30u16,
+            locked: 0,
+            config_bump,
+            lp_bump,
+            x_bump,
+            y_bump,
+            curve_type: 0,
+            curve_params: [0; 32],
+            sqrt_price: 0.into(),
+            liquidity: 0.into(),
+            fee_growth_global_x: 0.into(),
+            fee_growth_global_y: 0.into(),
+            tick_current: 0,
+            tick_spacing: 0,
+            protocol_fee: 0,
+            treasury: crate::ID,
+            protocol_fees_x: 0,
+            protocol_fees_y: 0,
+            last_fee_update: 0,
+            fee_update_interval: 0,
+            price_x_cumulative: 0.into(),
+            price_y_cumulative: 0.into(),
+            liquidity_cumulative: 0.into(),
+            price_last_update: 0,
+            locked_liquidity: MINIMUM_LIQUIDITY,
+            fee_max_step: 0,
+            flash_fee: 0,
+            version: CONFIG_VERSION,
+            reentrancy_guard: 0,
+            padding: [0; 2],
+        })
+        .to_vec();
+
+        [
+            native_account(user, Pubkey::default(), vec![], true, true),
+            native_account(mint_x, spl_token_2022::ID, token_2022_mint(100_000_000_000, 100), false, false),
+            native_account(mint_y, spl_token_2022::ID, token_2022_mint(100_000_000_000, 100), false, false),
+            native_account(mint_lp, spl_token::ID, mint_lp_data, false, true),
+            native_account(user_x, spl_token_2022::ID, token_2022_account(mint_x, user, 1_000_000), false, true),
+            native_account(user_y, spl_token_2022::ID, token_2022_account(mint_y, user, 1_000_000), false, true),
+            native_account(user_lp, spl_token::ID, user_lp_data, false, true),
+            native_account(vault_x, spl_token_2022::ID, token_2022_account(mint_x, config, 1_000_000), false, true),
+            native_account(vault_y, spl_token_2022::ID, token_2022_account(mint_y, config, 1_000_000), false, true),
+            native_account(config, crate::ID, config_data, false, true),
+            native_account(spl_token_2022::ID, Pubkey::default(), vec![], false, false),
+            native_account(spl_token::ID, Pubkey::default(), vec![], false, false),
+        ]
+    };
+
+    // Swap 10,000 X. The vault receives 9,900, and min applies to the Y the user receives after the fee
+    let swap = |min: u64| {
+        let [user, mint_x, mint_y, _, user_x, user_y, _, vault_x, vault_y, config, token_program_2022, _] = pool();
+        let accounts = [user, mint_x, mint_y, user_x, user_y.clone(), vault_x.clone(), vault_y, config, token_program_2022.clone(), token_program_2022];
+        let (result, _) = process_native(
+            &AMMInstructions::Swap.serialize::<Swap>(Swap { amount: 10_000, min, expiration: i64::MAX }),
+            &accounts,
+        );
+        (result, native_amount(&user_y), native_amount(&vault_x))
+    };
+    assert!(swap(9_676).0 == Err(AmmError::SlippageExceeded.into()));
+    assert!(swap(9_675) == (Ok(()), 1_009_675, 1_009_900));
+
+    // Swap for 5,000 Y received. max_in applies to the X the user sends, grossed up so the vault gets what it priced
+    let swap_exact_out = |max_in: u64| {
+        let [user, mint_x, mint_y, _, user_x, user_y, _, vault_x, vault_y, config, token_program_2022, _] = pool();
+        let accounts = [user, mint_x, mint_y, user_x.clone(), user_y.clone(), vault_x, vault_y, config, token_program_2022.clone(), token_program_2022];
+        let (result, _) = process_native(
+            &AMMInstructions::SwapExactOut.serialize::<SwapExactOut>(SwapExactOut { amount_out: 5_000, max_in, expiration: i64::MAX }),
+            &accounts,
+        );
+        (result, native_amount(&user_x), native_amount(&user_y))
+    };
+    assert!(swap_exact_out(5_144).0 == Err(AmmError::SlippageExceeded.into()));
+    assert!(swap_exact_out(5_145) == (Ok(()), 994_855, 1_005_000));
+
+    // Deposit for 10,000 LP, 1% of the pool. max_x applies to the X sent so the vault receives 10,000 after the fee
+    let deposit = |max_x: u64| {
+        let [user, mint_x, mint_y, mint_lp, user_x, user_y, user_lp, vault_x, vault_y, config, token_program_2022, token_program] = pool();
+        let accounts = [user.clone(), mint_x, mint_y, mint_lp, user_x.clone(), user_y, user_lp, vault_x.clone(), vault_y, config, token_program_2022.clone(), token_program_2022, token_program, user];
+        let (result, _) = process_native(
+            &AMMInstructions::Deposit.serialize::<Deposit>(Deposit { amount: 10_000, max_x, max_y: u64::MAX, expiration: i64::MAX }),
+            &accounts,
+        );
+        (result, native_amount(&user_x), native_amount(&vault_x))
+    };
+    assert!(deposit(10_101).0 == Err(AmmError::SlippageExceeded.into()));
+    assert!(deposit(10_102) == (Ok(()), 989_898, 1_010_000));
+
+    // Withdraw 10,000 LP. The vault sends 10,000 X, and min_x applies to the 9,900 the user receives
+    let withdraw = |min_x: u64| {
+        let [user, mint_x, mint_y, mint_lp, user_x, user_y, user_lp, vault_x, vault_y, config, token_program_2022, token_program] = pool();
+        let accounts = [user, mint_x, mint_y, mint_lp, user_x.clone(), user_y, user_lp, vault_x.clone(), vault_y, config, token_program_2022.clone(), token_program_2022, token_program];
+        let (result, _) = process_native(
+            &AMMInstructions::Withdraw.serialize::<Withdraw>(Withdraw { amount: 10_000, min_x, min_y: 0, expiration: i64::MAX }),
+            &accounts,
+        );
+        (result, native_amount(&user_x), native_amount(&vault_x))
+    };
+    assert!(withdraw(9_901).0 == Err(AmmError::SlippageExceeded.into()));
+    assert!(withdraw(9_900) == (Ok(()), 1_009_900, 990_000));
+}
+
+#[test]
+fn deposit_after_donation() {
+    // Set our seed
+    let seed: u64 = 1337;
+
+    // Accounts
+    let user = Pubkey::new_from_array([0x01; 32]);
+    let mint_x = Pubkey::new_from_array([0x02; 32]);
+    let mint_y = Pubkey::new_from_array([0x03; 32]);
+    let user_x = Pubkey::new_from_array([0x04; 32]);
+    let user_y = Pubkey::new_from_array([0x05; 32]);
+    let user_lp = Pubkey::new_from_array([0x06; 32]);
+    let (config, config_bump) =
+        Pubkey::find_program_address(&[b"config", &seed.to_le_bytes()], &crate::ID);
+    let (mint_lp, lp_bump) = Pubkey::find_program_address(&[config.as_ref()], &crate::ID);
+    let (vault_x, x_bump) =
+        Pubkey::find_program_address(&[mint_x.as_ref(), config.as_ref()], &crate::ID);
+    let (vault_y, y_bump) =
+        Pubkey::find_program_address(&[mint_y.as_ref(), config.as_ref()], &crate::ID);
+
+    // A new pool where someone has sent 1 X to the vault before the first deposit
+    let config_data = bytes_of::<Config>(&Config {
+        discriminator: CONFIG_DISCRIMINATOR,
+        seed,
+        authority: crate::ID,
+        pending_authority: Pubkey::default(),
+        mint_x,
+        mint_y,
+        fee: 30u16,
+        locked: 0,
+        config_bump,
+        lp_bump,
+        x_bump,
+        y_bump,
+        curve_type: 0,
+        curve_params: [0; 32],
+        sqrt_price: 0.into(),
+        liquidity: 0.into(),
+        fee_growth_global_x: 0.into(),
+        fee_growth_global_y: 0.into(),
+        tick_current: 0,
+        tick_spacing: 0,
+        protocol_fee: 0,
+        treasury: crate::ID,
+        protocol_fees_x: 0,
+        protocol_fees_y: 0,
+        last_fee_update: 0,
+        fee_update_interval: 0,
+        price_x_cumulative: 0.into(),
+        price_y_cumulative: 0.into(),
+        liquidity_cumulative: 0.into(),
+        price_last_update: 0,
+        locked_liquidity: MINIMUM_LIQUIDITY,
+        fee_max_step: 0,
+        flash_fee: 0,
+        version: CONFIG_VERSION,
+        reentrancy_guard: 0,
+        padding: [0; 2],
+    })
+    .to_vec();
+
+    let user = native_account(user, Pubkey::default(), vec![], true, true);
+    let mint_x = native_account(mint_x, spl_token::ID, token_mint(100_000_000_000, COption::None), false, false);
+    let mint_y = native_account(mint_y, spl_token::ID, token_mint(100_000_000_000, COption::None), false, false);
+    let mint_lp = native_account(mint_lp, spl_token::ID, token_mint(0, COption::Some(config)), false, true);
+    let user_x = native_account(user_x, spl_token::ID, token_account(*mint_x.key, *user.key, 2_000_000), false, true);
+    let user_y = native_account(user_y, spl_token::ID, token_account(*mint_y.key, *user.key, 2_000_000), false, true);
+    let user_lp = native_account(user_lp, spl_token::ID, token_account(*mint_lp.key, *user.key, 0), false, true);
+    let vault_x = native_account(vault_x, spl_token::ID, token_account(*mint_x.key, config, 1), false, true);
+    let vault_y = native_account(vault_y, spl_token::ID, token_account(*mint_y.key, config, 0), false, true);
+    let config = native_account(config, crate::ID, config_data, false, true);
+    let token_program = native_account(spl_token::ID, Pubkey::default(), vec![], false, false);
+
+    // The first deposit still takes both sides, and mints √(1,000,001 · 1,000,000) less the locked minimum liquidity
+    let (result, _) = process_native(
+        &AMMInstructions::Deposit.serialize::<Deposit>(Deposit {
+            amount: 999_000,
+            max_x: 1_000_000,
+            max_y: 1_000_000,
+            expiration: i64::MAX,
+        }),
+        &[
+            user.clone(),
+            mint_x.clone(),
+            mint_y.clone(),
+            mint_lp,
+            user_x.clone(),
+            user_y.clone(),
+            user_lp.clone(),
+            vault_x.clone(),
+            vault_y.clone(),
+            config.clone(),
+            token_program.clone(),
+            token_program.clone(),
+            token_program.clone(),
+            user.clone(),
+        ],
+    );
+    assert!(result == Ok(()));
+    assert!(native_amount(&user_lp) == 999_000);
+    assert!(native_amount(&vault_x) == 1_000_001);
+    assert!(native_amount(&vault_y) == 1_000_000);
+
+    // And the pool swaps against both reserves afterwards
+    let (result, _) = process_native(
+        &AMMInstructions::Swap.serialize::<Swap>(Swap {
+            amount: 10_000,
+            min: 1,
+            expiration: i64::MAX,
+        }),
+        &[
+            user,
+            mint_x,
+            mint_y,
+            user_x,
+            user_y,
+            vault_x,
+            vault_y,
+            config,
+            token_program.clone(),
+            token_program,
+        ],
+    );
+    assert!(result == Ok(()));
+}
+
+#[test]
+fn deposit_after_drain() {
+    // Set our seed
+    let seed: u64 = 1337;
+
+    // Accounts
+    let user = Pubkey::new_from_array([0x01; 32]);
+    let mint_x = Pubkey::new_from_array([0x02; 32]);
+    let mint_y = Pubkey::new_from_array([0x03; 32]);
+    let user_x = Pubkey::new_from_array([0x04; 32]);
+    let user_y = Pubkey::new_from_array([0x05; 32]);
+    let user_lp = Pubkey::new_from_array([0x06; 32]);
+    let (config, config_bump) =
+        Pubkey::find_program_address(&[b"config", &seed.to_le_bytes()], &crate::ID);
+    let (mint_lp, lp_bump) = Pubkey::find_program_address(&[config.as_ref()], &crate::ID);
+    let (vault_x, x_bump) =
+        Pubkey::find_program_address(&[mint_x.as_ref(), config.as_ref()], &crate::ID);
+    let (vault_y, y_bump) =
+        Pubkey::find_program_address(&[mint_y.as_ref(), config.as_ref()], &crate::ID);
+
+    // A pool of 1,000,000 X and Y whose whole LP supply is held by the user, on top of the locked minimum liquidity
+    let config_data = bytes_of::<Config>(&Config {
+        discriminator: CONFIG_DISCRIMINATOR,
+        seed,
+        authority: crate::ID,
+        pending_authority: Pubkey::default(),
+        mint_x,
+        mint_y,
+        fee: 30u16,
+        locked: 0,
+        config_bump,
+        lp_bump,
+        x_bump,
+        y_bump,
+        curve_type: 0,
+        curve_params: [0; 32],
+        sqrt_price: 0.into(),
+        liquidity: 0.into(),
+        fee_growth_global_x: 0.into(),
+        fee_growth_global_y: 0.into(),
+        tick_current: 0,
+        tick_spacing: 0,
+        protocol_fee: 0,
+        treasury: crate::ID,
+        protocol_fees_x: 0,
+        protocol_fees_y: 0,
+        last_fee_update: 0,
+        fee_update_interval: 0,
+        price_x_cumulative: 0.into(),
+        price_y_cumulative: 0.into(),
+        liquidity_cumulative: 0.into(),
+        price_last_update: 0,
+        locked_liquidity: MINIMUM_LIQUIDITY,
+        fee_max_step: 0,
+        flash_fee: 0,
+        version: CONFIG_VERSION,
+        reentrancy_guard: 0,
+        padding: [0; 2],
+    })
+    .to_vec();
+
+    let user = native_account(user, Pubkey::default(), vec![], true, true);
+    let mint_x = native_account(mint_x, spl_token::ID, token_mint(100_000_000_000, COption::None), false, false);
+    let mint_y = native_account(mint_y, spl_token::ID, token_mint(100_000_000_000, COption::None), false, false);
+    let mint_lp = native_account(mint_lp, spl_token::ID, token_mint(999_000, COption::Some(config)), false, true);
+    let user_x = native_account(user_x, spl_token::ID, token_account(*mint_x.key, *user.key, 0), false, true);
+    let user_y = native_account(user_y, spl_token::ID, token_account(*mint_y.key, *user.key, 0), false, true);
+    let user_lp = native_account(user_lp, spl_token::ID, token_account(*mint_lp.key, *user.key, 999_000), false, true);
+    let vault_x = native_account(vault_x, spl_token::ID, token_account(*mint_x.key, config, 1_000_000), false, true);
+    let vault_y = native_account(vault_y, spl_token::ID, token_account(*mint_y.key, config, 1_000_000), false, true);
+    let config = native_account(config, crate::ID, config_data, false, true);
+    let token_program = native_account(spl_token::ID, Pubkey::default(), vec![], false, false);
+
+    // Every LP withdraws, leaving the reserves behind the locked liquidity in the vaults
+    let (result, _) = process_native(
+        &AMMInstructions::Withdraw.serialize::<Withdraw>(Withdraw {
+            amount: 999_000,
+            min_x: 0,
+            min_y: 0,
+            expiration: i64::MAX,
+        }),
+        &[
+            user.clone(),
+            mint_x.clone(),
+            mint_y.clone(),
+            mint_lp.clone(),
+            user_x.clone(),
+            user_y.clone(),
+            user_lp.clone(),
+            vault_x.clone(),
+            vault_y.clone(),
+            config.clone(),
+            token_program.clone(),
+            token_program.clone(),
+            token_program.clone(),
+        ],
+    );
+    assert!(result == Ok(()));
+    assert_eq!(native_amount(&user_lp), 0);
+    assert_eq!(native_amount(&vault_x), 1_000);
+    assert_eq!(native_amount(&vault_y), 1_000);
+
+    let deposit = |amount: u64, max_x: u64, max_y: u64| {
+        process_native(
+            &AMMInstructions::Deposit.serialize::<Deposit>(Deposit {
+                amount,
+                max_x,
+                max_y,
+                expiration: i64::MAX,
+            }),
+            &[
+                user.clone(),
+                mint_x.clone(),
+                mint_y.clone(),
+                mint_lp.clone(),
+                user_x.clone(),
+                user_y.clone(),
+                user_lp.clone(),
+                vault_x.clone(),
+                vault_y.clone(),
+                config.clone(),
+                token_program.clone(),
+                token_program.clone(),
+                token_program.clone(),
+                user.clone(),
+            ],
+        )
+        .0
+    };
+
+    // The next deposit is priced on the reserves left behind rather than seeding the pool afresh at whatever ratio it
+    // offers, so it only takes what its LP is worth and the locked reserves stay in the vaults
+    assert!(deposit(999_000, 2_000_000, 1_500_000) == Ok(()));
+    assert_eq!(native_amount(&user_lp), 999_000);
+    assert_eq!(native_amount(&user_x), 0);
+    assert_eq!(native_amount(&user_y), 0);
+    assert_eq!(native_amount(&vault_x), 1_000_000);
+    assert_eq!(native_amount(&vault_y), 1_000_000);
+}
+
+#[test]
+fn single_sided_liquidity() {
+    // Set our seed
+    let seed: u64 = 1337;
+
+    // Accounts
+    let user = Pubkey::new_from_array([0x01; 32]);
+    let mint_x = Pubkey::new_from_array([0x02; 32]);
+    let mint_y = Pubkey::new_from_array([0x03; 32]);
+    let user_x = Pubkey::new_from_array([0x04; 32]);
+    let user_lp = Pubkey::new_from_array([0x06; 32]);
+    let (config, config_bump) =
+        Pubkey::find_program_address(&[b"config", &seed.to_le_bytes()], &crate::ID);
+    let (mint_lp, lp_bump) = Pubkey::find_program_address(&[config.as_ref()], &crate::ID);
+    let (vault_x, x_bump) =
+        Pubkey::find_program_address(&[mint_x.as_ref(), config.as_ref()], &crate::ID);
+    let (vault_y, y_bump) =
+        Pubkey::find_program_address(&[mint_y.as_ref(), config.as_ref()], &crate::ID);
+
+    // A pool of 1,000,000 X and Y with a 0.3% fee, half of which goes to the protocol
+    let config_data = bytes_of::<Config>(&Config {
+        discriminator: CONFIG_DISCRIMINATOR,
+        seed,
+        authority: crate::ID,
+        pending_authority: Pubkey::default(),
+        mint_x,
+        mint_y,
+        fee: 30u16,
+        locked: 0,
+        config_bump,
+        lp_bump,
+        x_bump,
+        y_bump,
+        curve_type: 0,
+        curve_params: [0; 32],
+        sqrt_price: 0.into(),
+        liquidity: 0.into(),
+        fee_growth_global_x: 0.into(),
+        fee_growth_global_y: 0.into(),
+        tick_current: 0,
+        tick_spacing: 0,
+        protocol_fee: 5_000,
+        treasury: crate::ID,
+        protocol_fees_x: 0,
+        protocol_fees_y: 0,
+        last_fee_update: 0,
+        fee_update_interval: 0,
+        price_x_cumulative: 0.into(),
+        price_y_cumulative: 0.into(),
+        liquidity_cumulative: 0.into(),
+        price_last_update: 0,
+        locked_liquidity: MINIMUM_LIQUIDITY,
+        fee_max_step: 0,
+        flash_fee: 0,
+        version: CONFIG_VERSION,
+        reentrancy_guard: 0,
+        padding: [0; 2],
+    })
+    .to_vec();
+
+    let user = native_account(user, Pubkey::default(), vec![], true, true);
+    let mint_x = native_account(mint_x, spl_token::ID, token_mint(100_000_000_000, COption::None), false, false);
+    let mint_lp = native_account(mint_lp, spl_token::ID, token_mint(999_000, COption::Some(config)), false, true);
+    let user_x = native_account(user_x, spl_token::ID, token_account(*mint_x.key, *user.key, 200_000), false, true);
+    let user_lp = native_account(user_lp, spl_token::ID, token_account(*mint_lp.key, *user.key, 0), false, true);
+    let vault_x = native_account(vault_x, spl_token::ID, token_account(*mint_x.key, config, 1_000_000), false, true);
+    let vault_y = native_account(vault_y, spl_token::ID, token_account(mint_y, config, 1_000_000), false, true);
+    let config = native_account(config, crate::ID, config_data, false, true);
+    let token_program = native_account(spl_token::ID, Pubkey::default(), vec![], false, false);
+
+    let accounts = [
+        user.clone(),
+        mint_x.clone(),
+        mint_lp.clone(),
+        user_x.clone(),
+        user_lp.clone(),
+        vault_x.clone(),
+        vault_y.clone(),
+        config.clone(),
+        token_program.clone(),
+        token_program.clone(),
+    ];
+    let deposit_single = |amount: u64, min_lp: u64| {
+        process_native(
+            &AMMInstructions::DepositSingle.serialize::<DepositSingle>(DepositSingle {
+                amount,
+                min_lp,
+                expiration: i64::MAX,
+            }),
+            &accounts,
+        )
+        .0
+    };
+    let withdraw_single = |amount: u64, min_out: u64| {
+        process_native(
+            &AMMInstructions::WithdrawSingle.serialize::<WithdrawSingle>(WithdrawSingle {
+                amount,
+                min_out,
+                expiration: i64::MAX,
+            }),
+            &accounts,
+        )
+        .0
+    };
+    // Reserves owned by LPs, per LP token, as the square of the pool's invariant over its supply
+    let k_per_lp = || {
+        let pool = Config::load(&config).unwrap();
+        let reserve_x = (native_amount(&vault_x) - pool.protocol_fees_x) as u128;
+        let reserve_y = (native_amount(&vault_y) - pool.protocol_fees_y) as u128;
+        let l = (spl_token::state::Mint::unpack(&mint_lp.data.borrow()).unwrap().supply + MINIMUM_LIQUIDITY) as u128;
+        reserve_x * reserve_y * 1_000_000 / (l * l)
+    };
+    let before = k_per_lp();
+
+    // Deposit 200,000 X, swapping part of it for Y. The LP is priced on the reserves without the protocol's half of the
+    // swap's fee, and whatever the smaller share leaves unminted stays with the existing LPs
+    assert!(deposit_single(200_000, 95_294) == Ok(()));
+    assert_eq!(native_amount(&user_x), 0);
+    assert_eq!(native_amount(&user_lp), 95_294);
+    assert_eq!(native_amount(&vault_x), 1_200_000);
+    assert_eq!(native_amount(&vault_y), 1_000_000);
+    assert_eq!(Config::load(&config).unwrap().protocol_fees_y, 131);
+    let after_deposit = k_per_lp();
+    assert!(after_deposit >= before);
+
+    // Withdraw it all back as X, swapping the Y side of the withdrawal back into the pool, for less than was put in
+    assert!(withdraw_single(95_294, 199_439) == Ok(()));
+    assert_eq!(native_amount(&user_x), 199_439);
+    assert_eq!(native_amount(&user_lp), 0);
+    assert_eq!(native_amount(&vault_x), 1_000_561);
+    assert_eq!(native_amount(&vault_y), 1_000_000);
+    assert_eq!(Config::load(&config).unwrap().protocol_fees_x, 143);
+    assert!(k_per_lp() >= after_deposit);
+}
+
+#[test]
+fn empty_pool() {
+    // Set our seed
+    let seed: u64 = 1337;
+
+    // Accounts
+    let user = Pubkey::new_from_array([0x01; 32]);
+    let mint_x = Pubkey::new_from_array([0x02; 32]);
+    let mint_y = Pubkey::new_from_array([0x03; 32]);
+    let user_x = Pubkey::new_from_array([0x04; 32]);
+    let user_y = Pubkey::new_from_array([0x05; 32]);
+    let user_lp = Pubkey::new_from_array([0x06; 32]);
+    let (config, config_bump) =
+        Pubkey::find_program_address(&[b"config", &seed.to_le_bytes()], &crate::ID);
+    let (mint_lp, lp_bump) = Pubkey::find_program_address(&[config.as_ref()], &crate::ID);
+    let (vault_x, x_bump) =
+        Pubkey::find_program_address(&[mint_x.as_ref(), config.as_ref()], &crate::ID);
+    let (vault_y, y_bump) =
+        Pubkey::find_program_address(&[mint_y.as_ref(), config.as_ref()], &crate::ID);
+
+    // A new pool with nothing in its vaults
+    let config_data = bytes_of::<Config>(&Config {
+        discriminator: CONFIG_DISCRIMINATOR,
+        seed,
+        authority: crate::ID,
+        pending_authority: Pubkey::default(),
+        mint_x,
+        mint_y,
+        fee: 30u16,
+        locked: 0,
+        config_bump,
+        lp_bump,
+        x_bump,
+        y_bump,
+        curve_type: 0,
+        curve_params: [0; 32],
+        sqrt_price: 0.into(),
+        liquidity: 0.into(),
+        fee_growth_global_x: 0.into(),
+        fee_growth_global_y: 0.into(),
+        tick_current: 0,
+        tick_spacing: 0,
+        protocol_fee: 0,
+        treasury: crate::ID,
+        protocol_fees_x: 0,
+        protocol_fees_y: 0,
+        last_fee_update: 0,
+        fee_update_interval: 0,
+        price_x_cumulative: 0.into(),
+        price_y_cumulative: 0.into(),
+        liquidity_cumulative: 0.into(),
+        price_last_update: 0,
+        locked_liquidity: MINIMUM_LIQUIDITY,
+        fee_max_step: 0,
+        flash_fee: 0,
+        version: CONFIG_VERSION,
+        reentrancy_guard: 0,
+        padding: [0; 2],
+    })
+    .to_vec();
+
+    let user = native_account(user, Pubkey::default(), vec![], true, true);
+    let mint_x = native_account(mint_x, spl_token::ID, token_mint(100_000_000_000, COption::None), false, false);
+    let mint_y = native_account(mint_y, spl_token::ID, token_mint(100_000_000_000, COption::None), false, false);
+    let mint_lp = native_account(mint_lp, spl_token::ID, token_mint(0, COption::Some(config)), false, true);
+    let user_x = native_account(user_x, spl_token::ID, token_account(*mint_x.key, *user.key, 2_000_000), false, true);
+    let user_y = native_account(user_y, spl_token::ID, token_account(*mint_y.key, *user.key, 2_000_000), false, true);
+    let user_lp = native_account(user_lp, spl_token::ID, token_account(*mint_lp.key, *user.key, 0), false, true);
+    let vault_x = native_account(vault_x, spl_token::ID, token_account(*mint_x.key, config, 0), false, true);
+    let vault_y = native_account(vault_y, spl_token::ID, token_account(*mint_y.key, config, 0), false, true);
+    let config = native_account(config, crate::ID, config_data, false, true);
+    let token_program = native_account(spl_token::ID, Pubkey::default(), vec![], false, false);
+
+    // Swapping against the empty pool fails cleanly
+    let (result, _) = process_native(
+        &AMMInstructions::Swap.serialize::<Swap>(Swap {
+            amount: 10_000,
+            min: 0,
+            expiration: i64::MAX,
+        }),
+        &[
+            user.clone(),
+            mint_x.clone(),
+            mint_y.clone(),
+            user_x.clone(),
+            user_y.clone(),
+            vault_x.clone(),
+            vault_y.clone(),
+            config.clone(),
+            token_program.clone(),
+            token_program.clone(),
+        ],
+    );
+    assert!(result == Err(AmmError::EmptyPool.into()));
+
+    // As does a first deposit with no X
+    let (result, _) = process_native(
+        &AMMInstructions::Deposit.serialize::<Deposit>(Deposit {
+            amount: 999_000,
+            max_x: 0,
+            max_y: 1_000_000,
+            expiration: i64::MAX,
+        }),
+        &[
+            user.clone(),
+            mint_x,
+            mint_y,
+            mint_lp,
+            user_x,
+            user_y,
+            user_lp.clone(),
+            vault_x.clone(),
+            vault_y.clone(),
+            config,
+            token_program.clone(),
+            token_program.clone(),
+            token_program,
+            user,
+        ],
+    );
+    assert!(result == Err(AmmError::EmptyPool.into()));
+    assert!(native_amount(&user_lp) == 0);
+    assert!(native_amount(&vault_y) == 0);
+}
+
+#[test]
+fn concentrated_liquidity() {
+    // Set our seed
+    let seed: u64 = 1337;
+
+    // Accounts
+    let owner = Pubkey::new_from_array([0x01; 32]);
+    let mint_x = Pubkey::new_from_array([0x02; 32]);
+    let mint_y = Pubkey::new_from_array([0x03; 32]);
+    let owner_x = Pubkey::new_from_array([0x04; 32]);
+    let owner_y = Pubkey::new_from_array([0x05; 32]);
+    let (config, config_bump) =
+        Pubkey::find_program_address(&[b"config", &seed.to_le_bytes()], &crate::ID);
+    let (vault_x, x_bump) =
+        Pubkey::find_program_address(&[mint_x.as_ref(), config.as_ref()], &crate::ID);
+    let (vault_y, y_bump) =
+        Pubkey::find_program_address(&[mint_y.as_ref(), config.as_ref()], &crate::ID);
+    let tick_array_address = |start_tick_index: i32| {
+        Pubkey::find_program_address(&[b"tick_array", config.as_ref(), &start_tick_index.to_le_bytes()], &crate::ID).0
+    };
+    let position_address = |tick_lower: i32, tick_upper: i32| {
+        Pubkey::find_program_address(
+            &[b"position", config.as_ref(), owner.as_ref(), &tick_lower.to_le_bytes(), &tick_upper.to_le_bytes()],
+            &crate::ID,
+        )
+        .0
+    };
+
+    // An empty pool at a price of 1 with a tick spacing of 10 and a 0.3% fee
+    let config_data = bytes_of::<Config>(&Config {
+        discriminator: CONFIG_DISCRIMINATOR,
+        seed,
+        authority: crate::ID,
+        pending_authority: Pubkey::default(),
+        mint_x,
+        mint_y,
+        fee: 30u16,
+        locked: 0,
+        config_bump,
+        lp_bump: 0,
+        x_bump,
+        y_bump,
+        curve_type: 3,
+        curve_params: [0; 32],
+        sqrt_price: (1u128 << 64).into(),
+        liquidity: 0.into(),
+        fee_growth_global_x: 0.into(),
+        fee_growth_global_y: 0.into(),
+        tick_current: 0,
+        tick_spacing: 10,
+        protocol_fee: 0,
+        treasury: crate::ID,
+        protocol_fees_x: 0,
+        protocol_fees_y: 0,
+        last_fee_update: 0,
+        fee_update_interval: 0,
+        price_x_cumulative: 0.into(),
+        price_y_cumulative: 0.into(),
+        liquidity_cumulative: 0.into(),
+        price_last_update: 0,
+        locked_liquidity: MINIMUM_LIQUIDITY,
+        fee_max_step: 0,
+        flash_fee: 0,
+        version: CONFIG_VERSION,
+        reentrancy_guard: 0,
+        padding: [0; 2],
+    })
+    .to_vec();
+
+    let owner = native_account(owner, Pubkey::default(), vec![], true, true);
+    let mint_x = native_account(mint_x, spl_token::ID, token_mint(100_000_000_000, COption::None), false, false);
+    let mint_y = native_account(mint_y, spl_token::ID, token_mint(100_000_000_000, COption::None), false, false);
+    let owner_x = native_account(owner_x, spl_token::ID, token_account(*mint_x.key, *owner.key, 100_000_000), false, true);
+    let owner_y = native_account(owner_y, spl_token::ID, token_account(*mint_y.key, *owner.key, 100_000_000), false, true);
+    let vault_x = native_account(vault_x, spl_token::ID, token_account(*mint_x.key, config, 0), false, true);
+    let vault_y = native_account(vault_y, spl_token::ID, token_account(*mint_y.key, config, 0), false, true);
+    let config = native_account(config, crate::ID, config_data, false, true);
+    let token_program = native_account(spl_token::ID, Pubkey::default(), vec![], false, false);
+    let system_program = native_account(system_program::ID, Pubkey::default(), vec![], false, false);
+    let tick_array_lower = native_uninitialized_account(tick_array_address(-640), mem::size_of::<TickArray>());
+    let tick_array_upper = native_uninitialized_account(tick_array_address(0), mem::size_of::<TickArray>());
+    let wide = native_uninitialized_account(position_address(-200, 200), mem::size_of::<Position>());
+    let narrow = native_uninitialized_account(position_address(-50, 50), mem::size_of::<Position>());
+
+    // Someone has already sent a lamport to the narrow position's address
+    **narrow.try_borrow_mut_lamports().unwrap() = 1;
+
+    // Create the tick arrays either side of the current price
+    for (tick_array, start_tick_index) in [(&tick_array_lower, -640), (&tick_array_upper, 0)] {
+        let (result, _) = process_native(
+            &AMMInstructions::InitializeTickArray.serialize::<InitializeTickArray>(InitializeTickArray { start_tick_index }),
+            &[owner.clone(), config.clone(), tick_array.clone(), system_program.clone()],
+        );
+        assert!(result == Ok(()));
+    }
+
+    // Open a wide and a narrow position, each with 1,000,000,000 liquidity. Both are in range, so take X and Y evenly
+    for (position, tick_lower, tick_upper, deposited) in [(&wide, -200, 200, 9_949_672), (&narrow, -50, 50, 2_496_753)] {
+        let (x, y) = (native_amount(&owner_x), native_amount(&owner_y));
+        let (result, _) = process_native(
+            &AMMInstructions::OpenPosition.serialize::<OpenPosition>(OpenPosition { tick_lower, tick_upper }),
+            &[owner.clone(), config.clone(), position.clone(), system_program.clone()],
+        );
+        assert!(result == Ok(()));
+
+        let (result, _) = process_native(
+            &AMMInstructions::IncreaseLiquidity.serialize::<IncreaseLiquidity>(IncreaseLiquidity {
+                liquidity: 1_000_000_000u128.into(),
+                max_x: u64::MAX,
+                max_y: u64::MAX,
+                expiration: i64::MAX,
+            }),
+            &[
+                owner.clone(),
+                mint_x.clone(),
+                mint_y.clone(),
+                owner_x.clone(),
+                owner_y.clone(),
+                vault_x.clone(),
+                vault_y.clone(),
+                config.clone(),
+                position.clone(),
+                tick_array_lower.clone(),
+                tick_array_upper.clone(),
+                token_program.clone(),
+                token_program.clone(),
+            ],
+        );
+        assert!(result == Ok(()));
+        assert!(x - native_amount(&owner_x) == deposited);
+        assert!(y - native_amount(&owner_y) == deposited);
+    }
+    assert!(u128::from(Config::load(&config).unwrap().liquidity) == 2_000_000_000);
+
+    // Swap X for Y far enough to push the price out of the narrow position's range
+    let y = native_amount(&owner_y);
+    let (result, _) = process_native(
+        &AMMInstructions::Swap.serialize::<Swap>(Swap {
+            amount: 6_000_000,
+            min: 0,
+            expiration: i64::MAX,
+        }),
+        &[
+            owner.clone(),
+            mint_x.clone(),
+            mint_y.clone(),
+            owner_x.clone(),
+            owner_y.clone(),
+            vault_x.clone(),
+            vault_y.clone(),
+            config.clone(),
+            token_program.clone(),
+            token_program.clone(),
+            tick_array_lower.clone(),
+            tick_array_upper.clone(),
+        ],
+    );
+    assert!(result == Ok(()));
+    assert!(native_amount(&owner_y) - y == 5_963_686);
+
+    // Crossing tick -50 took the narrow position's liquidity out of the pool
+    let pool = Config::load(&config).unwrap();
+    assert!(pool.tick_current == -70);
+    assert!(u128::from(pool.liquidity) == 1_000_000_000);
+
+    // Collect each position's fees by removing no liquidity. The 18,000 X fee was shared evenly until the crossing,
+    // and went to the wide position alone after it
+    for (position, fees) in [(&wide, 10_468), (&narrow, 7_531)] {
+        let (x, y) = (native_amount(&owner_x), native_amount(&owner_y));
+        let (result, _) = process_native(
+            &AMMInstructions::DecreaseLiquidity.serialize::<DecreaseLiquidity>(DecreaseLiquidity {
+                liquidity: 0u128.into(),
+                min_x: 0,
+                min_y: 0,
+                expiration: i64::MAX,
+            }),
+            &[
+                owner.clone(),
+                mint_x.clone(),
+                mint_y.clone(),
+                owner_x.clone(),
+                owner_y.clone(),
+                vault_x.clone(),
+                vault_y.clone(),
+                config.clone(),
+                position.clone(),
+                tick_array_lower.clone(),
+                tick_array_upper.clone(),
+                token_program.clone(),
+                token_program.clone(),
+            ],
+        );
+        assert!(result == Ok(()));
+        assert!(native_amount(&owner_x) - x == fees);
+        assert!(native_amount(&owner_y) == y);
+    }
+
+    // Remove the narrow position's liquidity, now all in X below its range
+    let (x, y) = (native_amount(&owner_x), native_amount(&owner_y));
+    let (result, _) = process_native(
+        &AMMInstructions::DecreaseLiquidity.serialize::<DecreaseLiquidity>(DecreaseLiquidity {
+            liquidity: 1_000_000_000u128.into(),
+            min_x: 0,
+            min_y: 0,
+            expiration: i64::MAX,
+        }),
+        &[
+            owner.clone(),
+            mint_x.clone(),
+            mint_y.clone(),
+            owner_x.clone(),
+            owner_y.clone(),
+            vault_x.clone(),
+            vault_y.clone(),
+            config.clone(),
+            narrow.clone(),
+            tick_array_lower.clone(),
+            tick_array_upper.clone(),
+            token_program.clone(),
+            token_program.clone(),
+        ],
+    );
+    assert!(result == Ok(()));
+    assert!(native_amount(&owner_x) - x == 4_999_755);
+    assert!(native_amount(&owner_y) == y);
+
+    // Its fees were already collected, and no other position references its ticks, so they are cleared
+    assert!(load_account_mut::<Position>(&narrow).unwrap().tokens_owed_x == 0);
+    assert!(u128::from(load_account_mut::<TickArray>(&tick_array_lower).unwrap().ticks[59].liquidity_gross) == 0);
+    assert!(u128::from(load_account_mut::<TickArray>(&tick_array_upper).unwrap().ticks[5].liquidity_gross) == 0);
+}
+
+#[test]
+fn protocol_fees() {
+    // Set our seed
+    let seed: u64 = 1337;
+
+    // Accounts
+    let user = Pubkey::new_from_array([0x01; 32]);
+    let mint_x = Pubkey::new_from_array([0x02; 32]);
+    let mint_y = Pubkey::new_from_array([0x03; 32]);
+    let user_x = Pubkey::new_from_array([0x04; 32]);
+    let user_y = Pubkey::new_from_array([0x05; 32]);
+    let user_lp = Pubkey::new_from_array([0x06; 32]);
+    let treasury = Pubkey::new_from_array([0x07; 32]);
+    let treasury_x = Pubkey::new_from_array([0x08; 32]);
+    let treasury_y = Pubkey::new_from_array([0x09; 32]);
+    let (config, config_bump) =
+        Pubkey::find_program_address(&[b"config", &seed.to_le_bytes()], &crate::ID);
+    let (mint_lp, lp_bump) = Pubkey::find_program_address(&[config.as_ref()], &crate::ID);
+    let (vault_x, x_bump) =
+        Pubkey::find_program_address(&[mint_x.as_ref(), config.as_ref()], &crate::ID);
+    let (vault_y, y_bump) =
+        Pubkey::find_program_address(&[mint_y.as_ref(), config.as_ref()], &crate::ID);
+
+    // A pool of 1,000,000 X and Y with a 1% fee, half of which goes to the treasury
+    let config_data = bytes_of::<Config>(&Config {
+        discriminator: CONFIG_DISCRIMINATOR,
+        seed,
+        authority: crate::ID,
+        pending_authority: Pubkey::default(),
+        mint_x,
+        mint_y,
+        fee: 100u16,
+        locked: 0,
+        config_bump,
+        lp_bump,
+        x_bump,
+        y_bump,
+        curve_type: 0,
+        curve_params: [0; 32],
+        sqrt_price: 0.into(),
+        liquidity: 0.into(),
+        fee_growth_global_x: 0.into(),
+        fee_growth_global_y: 0.into(),
+        tick_current: 0,
+        tick_spacing: 0,
+        protocol_fee: 5_000,
+        treasury,
+        protocol_fees_x: 0,
+        protocol_fees_y: 0,
+        last_fee_update: 0,
+        fee_update_interval: 0,
+        price_x_cumulative: 0.into(),
+        price_y_cumulative: 0.into(),
+        liquidity_cumulative: 0.into(),
+        price_last_update: 0,
+        locked_liquidity: MINIMUM_LIQUIDITY,
+        fee_max_step: 0,
+        flash_fee: 0,
+        version: CONFIG_VERSION,
+        reentrancy_guard: 0,
+        padding: [0; 2],
+    })
+    .to_vec();
+
+    let user = native_account(user, Pubkey::default(), vec![], true, true);
+    let treasury = native_account(treasury, Pubkey::default(), vec![], true, true);
+    let mint_x = native_account(mint_x, spl_token::ID, token_mint(100_000_000_000, COption::None), false, false);
+    let mint_y = native_account(mint_y, spl_token::ID, token_mint(100_000_000_000, COption::None), false, false);
+    let mint_lp = native_account(mint_lp, spl_token::ID, token_mint(999_000, COption::Some(config)), false, true);
+    let user_x = native_account(user_x, spl_token::ID, token_account(*mint_x.key, *user.key, 1_000_000), false, true);
+    let user_y = native_account(user_y, spl_token::ID, token_account(*mint_y.key, *user.key, 0), false, true);
+    let user_lp = native_account(user_lp, spl_token::ID, token_account(*mint_lp.key, *user.key, 99_900), false, true);
+    let treasury_x = native_account(treasury_x, spl_token::ID, token_account(*mint_x.key, *treasury.key, 0), false, true);
+    let treasury_y = native_account(treasury_y, spl_token::ID, token_account(*mint_y.key, *treasury.key, 0), false, true);
+    let vault_x = native_account(vault_x, spl_token::ID, token_account(*mint_x.key, config, 1_000_000), false, true);
+    let vault_y = native_account(vault_y, spl_token::ID, token_account(*mint_y.key, config, 1_000_000), false, true);
+    let config = native_account(config, crate::ID, config_data, false, true);
+    let token_program = native_account(spl_token::ID, Pubkey::default(), vec![], false, false);
+
+    // Swap 100,000 X for Y, with the fee kept from the Y sent out
+    let (result, _) = process_native(
+        &AMMInstructions::Swap.serialize::<Swap>(Swap {
+            amount: 100_000,
+            min: 0,
+            expiration: i64::MAX,
+        }),
+        &[
+            user.clone(),
+            mint_x.clone(),
+            mint_y.clone(),
+            user_x.clone(),
+            user_y.clone(),
+            vault_x.clone(),
+            vault_y.clone(),
+            config.clone(),
+            token_program.clone(),
+            token_program.clone(),
+        ],
+    );
+    assert!(result == Ok(()));
+    assert_eq!(native_amount(&user_y), 90_000);
+    assert_eq!(native_amount(&vault_y), 910_000);
+    {
+        let pool = Config::load(&config).unwrap();
+        assert_eq!(pool.protocol_fees_x, 0);
+        assert_eq!(pool.protocol_fees_y, 455);
+    }
+
+    // Withdraw 9.99% of the LP, priced on the vaults less the 455 Y owed to the treasury
+    let (result, _) = process_native(
+        &AMMInstructions::Withdraw.serialize::<Withdraw>(Withdraw {
+            amount: 99_900,
+            min_x: 0,
+            min_y: 0,
+            expiration: i64::MAX,
+        }),
+        &[
+            user.clone(),
+            mint_x.clone(),
+            mint_y.clone(),
+            mint_lp.clone(),
+            user_x.clone(),
+            user_y.clone(),
+            user_lp.clone(),
+            vault_x.clone(),
+            vault_y.clone(),
+            config.clone(),
+            token_program.clone(),
+            token_program.clone(),
+            token_program.clone(),
+        ],
+    );
+    assert!(result == Ok(()));
+    assert_eq!(native_amount(&user_x), 1_009_890);
+    assert_eq!(native_amount(&user_y), 180_864);
+    assert_eq!(native_amount(&vault_y), 819_136);
+
+    // Only the treasury can collect
+    let (result, _) = process_native(
+        &[AMMInstructions::CollectProtocolFees as u8],
+        &[
+            user.clone(),
+            mint_x.clone(),
+            mint_y.clone(),
+            treasury_x.clone(),
+            treasury_y.clone(),
+            vault_x.clone(),
+            vault_y.clone(),
+            config.clone(),
+            token_program.clone(),
+            token_program.clone(),
+        ],
+    );
+    assert!(result.is_err());
+
+    // Collect the fees owed to the treasury
+    let (result, _) = process_native(
+        &[AMMInstructions::CollectProtocolFees as u8],
+        &[
+            treasury.clone(),
+            mint_x.clone(),
+            mint_y.clone(),
+            treasury_x.clone(),
+            treasury_y.clone(),
+            vault_x.clone(),
+            vault_y.clone(),
+            config.clone(),
+            token_program.clone(),
+            token_program.clone(),
+        ],
+    );
+    assert!(result == Ok(()));
+    assert_eq!(native_amount(&treasury_x), 0);
+    assert_eq!(native_amount(&treasury_y), 455);
+    assert_eq!(native_amount(&vault_y), 818_681);
+    let pool = Config::load(&config).unwrap();
+    assert_eq!(pool.protocol_fees_x, 0);
+    assert_eq!(pool.protocol_fees_y, 0);
+}
+
+#[test]
+fn cancel_and_renounce_authority() {
+    // Set our seed
+    let seed: u64 = 1337;
+
+    // Accounts
+    let authority = Pubkey::new_from_array([0x01; 32]);
+    let new_authority = Pubkey::new_from_array([0x04; 32]);
+    let (config, config_bump) =
+        Pubkey::find_program_address(&[b"config", &seed.to_le_bytes()], &crate::ID);
+
+    let config_data = bytes_of::<Config>(&Config {
+        discriminator: CONFIG_DISCRIMINATOR,
+        seed,
+        authority,
+        pending_authority: Pubkey::default(),
+        mint_x: Pubkey::new_from_array([0x02; 32]),
+        mint_y: Pubkey::new_from_array([0x03; 32]),
+        fee: 30u16,
+        locked: 0,
+        config_bump,
+        lp_bump: 0,
+        x_bump: 0,
+        y_bump: 0,
+        curve_type: 0,
+        curve_params: [0; 32],
+        sqrt_price: 0.into(),
+        liquidity: 0.into(),
+        fee_growth_global_x: 0.into(),
+        fee_growth_global_y: 0.into(),
+        tick_current: 0,
+        tick_spacing: 0,
+        protocol_fee: 0,
+        treasury: crate::ID,
+        protocol_fees_x: 0,
+        protocol_fees_y: 0,
+        last_fee_update: 0,
+        fee_update_interval: 0,
+        price_x_cumulative: 0.into(),
+        price_y_cumulative: 0.into(),
+        liquidity_cumulative: 0.into(),
+        price_last_update: 0,
+        locked_liquidity: MINIMUM_LIQUIDITY,
+        fee_max_step: 100,
+        flash_fee: 0,
+        version: CONFIG_VERSION,
+        reentrancy_guard: 0,
+        padding: [0; 2],
+    })
+    .to_vec();
+
+    let authority = native_account(authority, Pubkey::default(), vec![], true, true);
+    let new_authority = native_account(new_authority, Pubkey::default(), vec![], true, true);
+    let revoked = native_account(Pubkey::default(), Pubkey::default(), vec![], true, true);
+    let config = native_account(config, crate::ID, config_data, false, true);
+
+    let propose = |new_authority: Pubkey| {
+        process_native(
+            &AMMInstructions::ProposeAuthority.serialize::<ProposeAuthority>(ProposeAuthority { new_authority }),
+            &[authority.clone(), config.clone()],
+        )
+        .0
+    };
+
+    // Propose a new authority, then cancel the proposal by proposing the default pubkey
+    assert!(propose(*new_authority.key) == Ok(()));
+    assert_eq!(Config::load(&config).unwrap().pending_authority, *new_authority.key);
+    assert!(propose(Pubkey::default()) == Ok(()));
+    assert_eq!(Config::load(&config).unwrap().pending_authority, Pubkey::default());
+
+    // The cancelled proposal can no longer be accepted
+    let (result, _) = process_native(
+        &[AMMInstructions::AcceptAuthority as u8],
+        &[new_authority.clone(), config.clone()],
+    );
+    assert!(result == Err(AmmError::WrongAuthority.into()));
+    assert_eq!(Config::load(&config).unwrap().authority, *authority.key);
+
+    // Renounce the authority
+    let (result, _) = process_native(
+        &[AMMInstructions::RenounceAuthority as u8],
+        &[authority.clone(), config.clone()],
+    );
+    assert!(result == Ok(()));
+    {
+        let pool = Config::load(&config).unwrap();
+        assert_eq!(pool.locked, 2);
+        assert_eq!(pool.authority, Pubkey::default());
+    }
+
+    // Neither the old authority nor the default pubkey can lock the pool or update its fee
+    for signer in [&authority, &revoked] {
+        let expected: Result<(), ProgramError> = if signer.key == authority.key {
+            Err(AmmError::WrongAuthority.into())
+        } else {
+            Err(AmmError::AuthorityRevoked.into())
+        };
+        let (result, _) = process_native(
+            &[AMMInstructions::Lock as u8, 1],
+            &[signer.clone(), config.clone()],
+        );
+        assert!(result == expected);
+        let (result, _) = process_native(
+            &AMMInstructions::UpdateFee.serialize::<UpdateFee>(UpdateFee { fee: 40 }),
+            &[signer.clone(), config.clone()],
+        );
+        assert!(result == expected);
+    }
+
+    let pool = Config::load(&config).unwrap();
+    assert_eq!(pool.locked, 2);
+    assert_eq!(pool.fee, 30);
+}
+
+#[test]
+fn migrate_then_withdraw() {
+    // Set our seed
+    let seed: u64 = 1337;
+
+    // Accounts
+    let user = Pubkey::new_from_array([0x01; 32]);
+    let mint_x = Pubkey::new_from_array([0x02; 32]);
+    let mint_y = Pubkey::new_from_array([0x03; 32]);
+    let user_x = Pubkey::new_from_array([0x04; 32]);
+    let user_y = Pubkey::new_from_array([0x05; 32]);
+    let user_lp = Pubkey::new_from_array([0x06; 32]);
+    let (config, config_bump) =
+        Pubkey::find_program_address(&[b"config", &seed.to_le_bytes()], &crate::ID);
+    let (mint_lp, lp_bump) = Pubkey::find_program_address(&[config.as_ref()], &crate::ID);
+    let (vault_x, x_bump) =
+        Pubkey::find_program_address(&[mint_x.as_ref(), config.as_ref()], &crate::ID);
+    let (vault_y, y_bump) =
+        Pubkey::find_program_address(&[mint_y.as_ref(), config.as_ref()], &crate::ID);
+
+    // A legacy pool of 1,000,000 X and Y, whose whole LP supply is held by the user
+    let config_data = bytes_of::<ConfigV0>(&ConfigV0 {
+        seed,
+        authority: user,
+        mint_x,
+        mint_y,
+        fee: 30u16,
+        locked: 0,
+        config_bump,
+        lp_bump,
+        x_bump,
+        y_bump,
+        padding: [0; 1],
+    })
+    .to_vec();
+
+    let user = native_account(user, Pubkey::default(), vec![], true, true);
+    let mint_x = native_account(mint_x, spl_token::ID, token_mint(100_000_000_000, COption::None), false, false);
+    let mint_y = native_account(mint_y, spl_token::ID, token_mint(100_000_000_000, COption::None), false, false);
+    let mint_lp = native_account(mint_lp, spl_token::ID, token_mint(1_000_000, COption::Some(config)), false, true);
+    let user_x = native_account(user_x, spl_token::ID, token_account(*mint_x.key, *user.key, 0), false, true);
+    let user_y = native_account(user_y, spl_token::ID, token_account(*mint_y.key, *user.key, 0), false, true);
+    let user_lp = native_account(user_lp, spl_token::ID, token_account(*mint_lp.key, *user.key, 1_000_000), false, true);
+    let vault_x = native_account(vault_x, spl_token::ID, token_account(*mint_x.key, config, 1_000_000), false, true);
+    let vault_y = native_account(vault_y, spl_token::ID, token_account(*mint_y.key, config, 1_000_000), false, true);
+    let config = native_resizable_account(config, crate::ID, config_data);
+    let token_program = native_account(spl_token::ID, Pubkey::default(), vec![], false, false);
+    let system_program = native_account(system_program::ID, Pubkey::default(), vec![], false, false);
+
+    // Migrate the config onto the current layout
+    let (result, _) = process_native(
+        &[AMMInstructions::MigrateConfig as u8],
+        &[user.clone(), config.clone(), system_program],
+    );
+    assert!(result == Ok(()));
+    assert_eq!(config.data_len(), mem::size_of::<Config>());
+    assert_eq!(Config::load(&config).unwrap().locked_liquidity, 0);
+
+    // The legacy pool never locked any liquidity, so burning the whole supply withdraws the whole pool
+    let (result, _) = process_native(
+        &AMMInstructions::Withdraw.serialize::<Withdraw>(Withdraw {
+            amount: 1_000_000,
+            min_x: 1_000_000,
+            min_y: 1_000_000,
+            expiration: i64::MAX,
+        }),
+        &[
+            user,
+            mint_x,
+            mint_y,
+            mint_lp,
+            user_x.clone(),
+            user_y.clone(),
+            user_lp.clone(),
+            vault_x.clone(),
+            vault_y.clone(),
+            config,
+            token_program.clone(),
+            token_program.clone(),
+            token_program,
+        ],
+    );
+    assert!(result == Ok(()));
+    assert_eq!(native_amount(&user_x), 1_000_000);
+    assert_eq!(native_amount(&user_y), 1_000_000);
+    assert_eq!(native_amount(&user_lp), 0);
+    assert_eq!(native_amount(&vault_x), 0);
+    assert_eq!(native_amount(&vault_y), 0);
+}
+
+#[test]
+fn observe_cumulative_prices() {
+    // Set our seed
+    let seed: u64 = 1337;
+
+    // Accounts
+    let user = Pubkey::new_from_array([0x01; 32]);
+    let mint_x = Pubkey::new_from_array([0x02; 32]);
+    let mint_y = Pubkey::new_from_array([0x03; 32]);
+    let user_x = Pubkey::new_from_array([0x04; 32]);
+    let user_y = Pubkey::new_from_array([0x05; 32]);
+    let (config, config_bump) =
+        Pubkey::find_program_address(&[b"config", &seed.to_le_bytes()], &crate::ID);
+    let (vault_x, x_bump) =
+        Pubkey::find_program_address(&[mint_x.as_ref(), config.as_ref()], &crate::ID);
+    let (vault_y, y_bump) =
+        Pubkey::find_program_address(&[mint_y.as_ref(), config.as_ref()], &crate::ID);
+
+    // A pool of 1,000,000 X and 4,000,000 Y whose accumulators last moved at t = 100
+    let config_data = bytes_of::<Config>(&Config {
+        discriminator: CONFIG_DISCRIMINATOR,
+        seed,
+        authority: crate::ID,
+        pending_authority: Pubkey::default(),
+        mint_x,
+        mint_y,
+        fee: 30u16,
+        locked: 0,
+        config_bump,
+        lp_bump: 0,
+        x_bump,
+        y_bump,
+        curve_type: 0,
+        curve_params: [0; 32],
+        sqrt_price: 0.into(),
+        liquidity: 0.into(),
+        fee_growth_global_x: 0.into(),
+        fee_growth_global_y: 0.into(),
+        tick_current: 0,
+        tick_spacing: 0,
+        protocol_fee: 0,
+        treasury: crate::ID,
+        protocol_fees_x: 0,
+        protocol_fees_y: 0,
+        last_fee_update: 0,
+        fee_update_interval: 0,
+        price_x_cumulative: 7.into(),
+        price_y_cumulative: 9.into(),
+        liquidity_cumulative: 0.into(),
+        price_last_update: 100,
+        locked_liquidity: MINIMUM_LIQUIDITY,
+        fee_max_step: 0,
+        flash_fee: 0,
+        version: CONFIG_VERSION,
+        reentrancy_guard: 0,
+        padding: [0; 2],
+    })
+    .to_vec();
+
+    let user = native_account(user, Pubkey::default(), vec![], true, true);
+    let mint_x = native_account(mint_x, spl_token::ID, token_mint(100_000_000_000, COption::None), false, false);
+    let mint_y = native_account(mint_y, spl_token::ID, token_mint(100_000_000_000, COption::None), false, false);
+    let user_x = native_account(user_x, spl_token::ID, token_account(*mint_x.key, *user.key, 1_000_000), false, true);
+    let user_y = native_account(user_y, spl_token::ID, token_account(*mint_y.key, *user.key, 0), false, true);
+    let vault_x = native_account(vault_x, spl_token::ID, token_account(*mint_x.key, config, 1_000_000), false, true);
+    let vault_y = native_account(vault_y, spl_token::ID, token_account(*mint_y.key, config, 4_000_000), false, true);
+    let config = native_account(config, crate::ID, config_data, false, true);
+    let token_program = native_account(spl_token::ID, Pubkey::default(), vec![], false, false);
+
+    let observe = |now: i64| {
+        NATIVE_CLOCK.with(|clock| clock.borrow_mut().unix_timestamp = now);
+        let (result, return_data) = process_native(
+            &[AMMInstructions::Observe as u8],
+            &[config.clone(), vault_x.clone(), vault_y.clone()],
+        );
+        assert!(result == Ok(()));
+        let prices = *bytemuck::from_bytes::<CumulativePrices>(&return_data.unwrap());
+        (prices.timestamp, u128::from(prices.price_x_cumulative), u128::from(prices.price_y_cumulative))
+    };
+
+    // Nothing has accumulated yet at the time of the last update
+    assert_eq!(observe(100), (100, 7, 9));
+
+    // Ten seconds later, X has been worth 4 Y and Y a quarter of an X throughout. Observing doesn't write to the pool
+    assert_eq!(observe(110), (110, 7 + (4 << 64) * 10, 9 + (1 << 62) * 10));
+    assert_eq!(Config::load(&config).unwrap().price_last_update, 100);
+
+    // A swap at t = 110 carries the accumulators forward on the prices before it
+    NATIVE_CLOCK.with(|clock| clock.borrow_mut().unix_timestamp = 110);
+    let (result, _) = process_native(
+        &AMMInstructions::Swap.serialize::<Swap>(Swap {
+            amount: 1_000_000,
+            min: 0,
+            expiration: i64::MAX,
+        }),
+        &[
+            user,
+            mint_x,
+            mint_y,
+            user_x,
+            user_y,
+            vault_x.clone(),
+            vault_y.clone(),
+            config.clone(),
+            token_program.clone(),
+            token_program,
+        ],
+    );
+    assert!(result == Ok(()));
+    {
+        let pool = Config::load(&config).unwrap();
+        assert_eq!(u128::from(pool.price_x_cumulative), 7 + (4 << 64) * 10);
+        assert_eq!(u128::from(pool.price_y_cumulative), 9 + (1 << 62) * 10);
+        assert_eq!(pool.price_last_update, 110);
+    }
+
+    // And the next observation accrues the prices after it
+    let (x, y) = (native_amount(&vault_x) as u128, native_amount(&vault_y) as u128);
+    assert_eq!(
+        observe(120),
+        (120, 7 + (4 << 64) * 10 + (y << 64) / x * 10, 9 + (1 << 62) * 10 + (x << 64) / y * 10)
+    );
+}
+
+#[test]
+fn accumulate_liquidity() {
+    // Set our seed
+    let seed: u64 = 1337;
+
+    // Accounts
+    let user = Pubkey::new_from_array([0x01; 32]);
+    let mint_x = Pubkey::new_from_array([0x02; 32]);
+    let mint_y = Pubkey::new_from_array([0x03; 32]);
+    let user_x = Pubkey::new_from_array([0x04; 32]);
+    let user_y = Pubkey::new_from_array([0x05; 32]);
+    let (config, config_bump) =
+        Pubkey::find_program_address(&[b"config", &seed.to_le_bytes()], &crate::ID);
+    let (vault_x, x_bump) =
+        Pubkey::find_program_address(&[mint_x.as_ref(), config.as_ref()], &crate::ID);
+    let (vault_y, y_bump) =
+        Pubkey::find_program_address(&[mint_y.as_ref(), config.as_ref()], &crate::ID);
+    let (observations, observations_bump) =
+        Pubkey::find_program_address(&[b"observations", config.as_ref()], &crate::ID);
+
+    // A pool of 1,000,000 X and Y whose accumulators last moved at t = 100
+    let config_data = bytes_of::<Config>(&Config {
+        discriminator: CONFIG_DISCRIMINATOR,
+        seed,
+        authority: crate::ID,
+        pending_authority: Pubkey::default(),
+        mint_x,
+        mint_y,
+        fee: 30u16,
+        locked: 0,
+        config_bump,
+        lp_bump: 0,
+        x_bump,
+        y_bump,
+        curve_type: 0,
+        curve_params: [0; 32],
+        sqrt_price: 0.into(),
+        liquidity: 0.into(),
+        fee_growth_global_x: 0.into(),
+        fee_growth_global_y: 0.into(),
+        tick_current: 0,
+        tick_spacing: 0,
+        protocol_fee: 0,
+        treasury: crate::ID,
+        protocol_fees_x: 0,
+        protocol_fees_y: 0,
+        last_fee_update: 0,
+        fee_update_interval: 0,
+        price_x_cumulative: 0.into(),
+        price_y_cumulative: 0.into(),
+        liquidity_cumulative: 0.into(),
+        price_last_update: 100,
+        locked_liquidity: MINIMUM_LIQUIDITY,
+        fee_max_step: 0,
+        flash_fee: 0,
+        version: CONFIG_VERSION,
+        reentrancy_guard: 0,
+        padding: [0; 2],
+    })
+    .to_vec();
+
+    // Observations with room for two entries, the first taken at t = 100
+    let observations_data = [
+        bytes_of::<Observations>(&Observations {
+            discriminator: OBSERVATIONS_DISCRIMINATOR,
+            config,
+            index: 0,
+            cardinality: 1,
+            cardinality_next: 2,
+            bump: observations_bump,
+            padding: [0; 1],
+        }),
+        bytes_of::<[Observation; 2]>(&[
+            Observation {
+                timestamp: 100,
+                price_x_cumulative: 0.into(),
+                price_y_cumulative: 0.into(),
+                liquidity_cumulative: 0.into(),
+            },
+            Observation {
+                timestamp: 0,
+                price_x_cumulative: 0.into(),
+                price_y_cumulative: 0.into(),
+                liquidity_cumulative: 0.into(),
+            },
+        ]),
+    ]
+    .concat();
+
+    let user = native_account(user, Pubkey::default(), vec![], true, true);
+    let mint_x = native_account(mint_x, spl_token::ID, token_mint(100_000_000_000, COption::None), false, false);
+    let mint_y = native_account(mint_y, spl_token::ID, token_mint(100_000_000_000, COption::None), false, false);
+    let user_x = native_account(user_x, spl_token::ID, token_account(*mint_x.key, *user.key, 1_000_000), false, true);
+    let user_y = native_account(user_y, spl_token::ID, token_account(*mint_y.key, *user.key, 0), false, true);
+    let vault_x = native_account(vault_x, spl_token::ID, token_account(*mint_x.key, config, 1_000_000), false, true);
+    let vault_y = native_account(vault_y, spl_token::ID, token_account(*mint_y.key, config, 1_000_000), false, true);
+    let config = native_account(config, crate::ID, config_data, false, true);
+    let observations = native_account(observations, crate::ID, observations_data, false, true);
+    let token_program = native_account(spl_token::ID, Pubkey::default(), vec![], false, false);
+
+    let swap = |now: i64, remaining_accounts: &[AccountInfo<'static>]| {
+        NATIVE_CLOCK.with(|clock| clock.borrow_mut().unix_timestamp = now);
+        let accounts = [
+            &[
+                user.clone(),
+                mint_x.clone(),
+                mint_y.clone(),
+                user_x.clone(),
+                user_y.clone(),
+                vault_x.clone(),
+                vault_y.clone(),
+                config.clone(),
+                token_program.clone(),
+                token_program.clone(),
+            ],
+            remaining_accounts,
+        ]
+        .concat();
+        process_native(
+            &AMMInstructions::Swap.serialize::<Swap>(Swap {
+                amount: 100_000,
+                min: 0,
+                expiration: i64::MAX,
+            }),
+            &accounts,
+        )
+        .0
+    };
+
+    // A swap without the observations still accumulates liquidity in the config
+    assert!(swap(110, &[]) == Ok(()));
+    assert_eq!(u128::from(Config::load(&config).unwrap().liquidity_cumulative), 1_000_000 * 10);
+
+    // So an observation written later covers every second since the last one at the liquidity that held over it
+    let liquidity = sqrt_u128(native_amount(&vault_x) as u128 * native_amount(&vault_y) as u128);
+    assert!(swap(120, &[observations.clone()]) == Ok(()));
+    let liquidity_cumulative = u128::from(Config::load(&config).unwrap().liquidity_cumulative);
+    assert_eq!(liquidity_cumulative, 1_000_000 * 10 + liquidity * 10);
+
+    let data = observations.data.borrow();
+    let (_, entries) = data.split_at(mem::size_of::<Observations>());
+    let entry = bytemuck::cast_slice::<u8, Observation>(entries)[1];
+    assert_eq!(entry.timestamp, 120);
+    assert_eq!(u128::from(entry.liquidity_cumulative), liquidity_cumulative);
+}
+
+#[test]
+fn quote_matches_execution() {
+    // Set our seed
+    let seed: u64 = 1337;
+
+    // Accounts
+    let user = Pubkey::new_from_array([0x01; 32]);
+    let mint_x = Pubkey::new_from_array([0x02; 32]);
+    let mint_y = Pubkey::new_from_array([0x03; 32]);
+    let user_x = Pubkey::new_from_array([0x04; 32]);
+    let user_y = Pubkey::new_from_array([0x05; 32]);
+    let user_lp = Pubkey::new_from_array([0x06; 32]);
+    let (config, config_bump) =
+        Pubkey::find_program_address(&[b"config", &seed.to_le_bytes()], &crate::ID);
+    let (mint_lp, lp_bump) = Pubkey::find_program_address(&[config.as_ref()], &crate::ID);
+    let (vault_x, x_bump) =
+        Pubkey::find_program_address(&[mint_x.as_ref(), config.as_ref()], &crate::ID);
+    let (vault_y, y_bump) =
+        Pubkey::find_program_address(&[mint_y.as_ref(), config.as_ref()], &crate::ID);
+
+    // A pool of 1,000,000 X and 2,000,000 Y with a 0.3% fee
+    let config_data = bytes_of::<Config>(&Config {
+        discriminator: CONFIG_DISCRIMINATOR,
+        seed,
+        authority: crate::ID,
+        pending_authority: Pubkey::default(),
+        mint_x,
+        mint_y,
+        fee: 30u16,
+        locked: 0,
+        config_bump,
+        lp_bump,
+        x_bump,
+        y_bump,
+        curve_type: 0,
+        curve_params: [0; 32],
+        sqrt_price: 0.into(),
+        liquidity: 0.into(),
+        fee_growth_global_x: 0.into(),
+        fee_growth_global_y: 0.into(),
+        tick_current: 0,
+        tick_spacing: 0,
+        protocol_fee: 0,
+        treasury: crate::ID,
+        protocol_fees_x: 0,
+        protocol_fees_y: 0,
+        last_fee_update: 0,
+        fee_update_interval: 0,
+        price_x_cumulative: 0.into(),
+        price_y_cumulative: 0.into(),
+        liquidity_cumulative: 0.into(),
+        price_last_update: 0,
+        locked_liquidity: MINIMUM_LIQUIDITY,
+        fee_max_step: 0,
+        flash_fee: 0,
+        version: CONFIG_VERSION,
+        reentrancy_guard: 0,
+        padding: [0; 2],
+    })
+    .to_vec();
+
+    let user = native_account(user, Pubkey::default(), vec![], true, true);
+    let mint_x = native_account(mint_x, spl_token::ID, token_mint(100_000_000_000, COption::None), false, false);
+    let mint_y = native_account(mint_y, spl_token::ID, token_mint(100_000_000_000, COption::None), false, false);
+    let mint_lp = native_account(mint_lp, spl_token::ID, token_mint(1_414_213, COption::Some(config)), false, true);
+    let user_x = native_account(user_x, spl_token::ID, token_account(*mint_x.key, *user.key, 1_000_000), false, true);
+    let user_y = native_account(user_y, spl_token::ID, token_account(*mint_y.key, *user.key, 1_000_000), false, true);
+    let user_lp = native_account(user_lp, spl_token::ID, token_account(*mint_lp.key, *user.key, 100_000), false, true);
+    let vault_x = native_account(vault_x, spl_token::ID, token_account(*mint_x.key, config, 1_000_000), false, true);
+    let vault_y = native_account(vault_y, spl_token::ID, token_account(*mint_y.key, config, 2_000_000), false, true);
+    let config = native_account(config, crate::ID, config_data, false, true);
+    let token_program = native_account(spl_token::ID, Pubkey::default(), vec![], false, false);
+
+    let quote = |kind: QuoteKind, amount: u64, is_x: bool| {
+        let (result, return_data) = process_native(
+            &AMMInstructions::Quote.serialize::<Quote>(Quote {
+                amount,
+                max_x: 0,
+                max_y: 0,
+                kind: kind as u8,
+                is_x: is_x as u8,
+                padding: [0; 6],
+            }),
+            &[mint_x.clone(), mint_y.clone(), mint_lp.clone(), vault_x.clone(), vault_y.clone(), config.clone()],
+        );
+        assert!(result == Ok(()));
+        *bytemuck::from_bytes::<QuoteAmounts>(&return_data.unwrap())
+    };
+    let balances = || (native_amount(&user_x), native_amount(&user_y), native_amount(&user_lp));
+
+    // Swap 10,000 X for Y
+    let swap = quote(QuoteKind::Swap, 10_000, true);
+    assert_eq!((swap.amount_in, swap.amount_out, swap.fee), (10_000, 19_742, 60));
+    let (x, y, _) = balances();
+    let (result, _) = process_native(
+        &AMMInstructions::Swap.serialize::<Swap>(Swap {
+            amount: 10_000,
+            min: swap.amount_out,
+            expiration: i64::MAX,
+        }),
+        &[
+            user.clone(),
+            mint_x.clone(),
+            mint_y.clone(),
+            user_x.clone(),
+            user_y.clone(),
+            vault_x.clone(),
+            vault_y.clone(),
+            config.clone(),
+            token_program.clone(),
+            token_program.clone(),
+        ],
+    );
+    assert!(result == Ok(()));
+    assert_eq!(balances().0, x - swap.amount_in);
+    assert_eq!(balances().1, y + swap.amount_out);
+
+    // Deposit for 10,000 LP
+    let deposit = quote(QuoteKind::Deposit, 10_000, false);
+    assert_eq!((deposit.amount_x, deposit.amount_y, deposit.lp), (7_136, 13_992, 10_000));
+    let (x, y, lp) = balances();
+    let (result, _) = process_native(
+        &AMMInstructions::Deposit.serialize::<Deposit>(Deposit {
+            amount: 10_000,
+            max_x: deposit.amount_x,
+            max_y: deposit.amount_y,
+            expiration: i64::MAX,
+        }),
+        &[
+            user.clone(),
+            mint_x.clone(),
+            mint_y.clone(),
+            mint_lp.clone(),
+            user_x.clone(),
+            user_y.clone(),
+            user_lp.clone(),
+            vault_x.clone(),
+            vault_y.clone(),
+            config.clone(),
+            token_program.clone(),
+            token_program.clone(),
+            token_program.clone(),
+            user.clone(),
+        ],
+    );
+    assert!(result == Ok(()));
+    assert_eq!(balances(), (x - deposit.amount_x, y - deposit.amount_y, lp + deposit.lp));
+
+    // Withdraw 10,000 LP
+    let withdraw = quote(QuoteKind::Withdraw, 10_000, false);
+    assert_eq!((withdraw.amount_x, withdraw.amount_y, withdraw.lp), (7_137, 13_993, 10_000));
+    let (x, y, lp) = balances();
+    let (result, _) = process_native(
+        &AMMInstructions::Withdraw.serialize::<Withdraw>(Withdraw {
+            amount: 10_000,
+            min_x: withdraw.amount_x,
+            min_y: withdraw.amount_y,
+            expiration: i64::MAX,
+        }),
+        &[
+            user,
+            mint_x,
+            mint_y,
+            mint_lp,
+            user_x.clone(),
+            user_y.clone(),
+            user_lp.clone(),
+            vault_x,
+            vault_y,
+            config,
+            token_program.clone(),
+            token_program.clone(),
+            token_program,
+        ],
+    );
+    assert!(result == Ok(()));
+    assert_eq!(balances(), (x + withdraw.amount_x, y + withdraw.amount_y, lp - withdraw.lp));
+}
+
+#[test]
+fn flash_loan_reentrancy() {
+    // Set our seed
+    let seed: u64 = 1337;
+
+    // Accounts
+    let user = Pubkey::new_from_array([0x01; 32]);
+    let mint_x = Pubkey::new_from_array([0x02; 32]);
+    let mint_y = Pubkey::new_from_array([0x03; 32]);
+    let user_x = Pubkey::new_from_array([0x04; 32]);
+    let user_y = Pubkey::new_from_array([0x05; 32]);
+    let (config, config_bump) =
+        Pubkey::find_program_address(&[b"config", &seed.to_le_bytes()], &crate::ID);
+    let (vault_x, x_bump) =
+        Pubkey::find_program_address(&[mint_x.as_ref(), config.as_ref()], &crate::ID);
+    let (vault_y, y_bump) =
+        Pubkey::find_program_address(&[mint_y.as_ref(), config.as_ref()], &crate::ID);
+
+    // A pool of 1,000,000 X and Y with a 0.3% flash fee
+    let config_data = bytes_of::<Config>(&Config {
+        discriminator: CONFIG_DISCRIMINATOR,
+        seed,
+        authority: crate::ID,
+        pending_authority: Pubkey::default(),
+        mint_x,
+        mint_y,
+        fee: 30u16,
+        locked: 0,
+        config_bump,
+        lp_bump: 0,
+        x_bump,
+        y_bump,
+        curve_type: 0,
+        curve_params: [0; 32],
+        sqrt_price: 0.into(),
+        liquidity: 0.into(),
+        fee_growth_global_x: 0.into(),
+        fee_growth_global_y: 0.into(),
+        tick_current: 0,
+        tick_spacing: 0,
+        protocol_fee: 0,
+        treasury: crate::ID,
+        protocol_fees_x: 0,
+        protocol_fees_y: 0,
+        last_fee_update: 0,
+        fee_update_interval: 0,
+        price_x_cumulative: 0.into(),
+        price_y_cumulative: 0.into(),
+        liquidity_cumulative: 0.into(),
+        price_last_update: 0,
+        locked_liquidity: MINIMUM_LIQUIDITY,
+        fee_max_step: 0,
+        flash_fee: 30,
+        version: CONFIG_VERSION,
+        reentrancy_guard: 0,
+        padding: [0; 2],
+    })
+    .to_vec();
+
+    let user = native_account(user, Pubkey::default(), vec![], true, true);
+    let mint_x = native_account(mint_x, spl_token::ID, token_mint(100_000_000_000, COption::None), false, false);
+    let mint_y = native_account(mint_y, spl_token::ID, token_mint(100_000_000_000, COption::None), false, false);
+    let user_x = native_account(user_x, spl_token::ID, token_account(*mint_x.key, *user.key, 1_000), false, true);
+    let user_y = native_account(user_y, spl_token::ID, token_account(*mint_y.key, *user.key, 0), false, true);
+    let vault_x = native_account(vault_x, spl_token::ID, token_account(*mint_x.key, config, 1_000_000), false, true);
+    let vault_y = native_account(vault_y, spl_token::ID, token_account(*mint_y.key, config, 1_000_000), false, true);
+    let config = native_account(config, crate::ID, config_data, false, true);
+    let token_program = native_account(spl_token::ID, Pubkey::default(), vec![], false, false);
+    let amm_program = native_account(crate::ID, Pubkey::default(), vec![], false, false);
+
+    let swap_accounts = [
+        user.clone(),
+        mint_x.clone(),
+        mint_y.clone(),
+        user_x.clone(),
+        user_y.clone(),
+        vault_x.clone(),
+        vault_y.clone(),
+        config.clone(),
+        token_program.clone(),
+        token_program.clone(),
+    ];
+    let swap_data = AMMInstructions::Swap.serialize::<Swap>(Swap {
+        amount: 500,
+        min: 0,
+        expiration: i64::MAX,
+    });
+    let flash_loan = |callback_program: &AccountInfo<'static>, callback_data: &[u8], remaining_accounts: &[AccountInfo<'static>]| {
+        let accounts = [
+            &[
+                user.clone(),
+                mint_x.clone(),
+                user_x.clone(),
+                vault_x.clone(),
+                vault_y.clone(),
+                config.clone(),
+                token_program.clone(),
+                callback_program.clone(),
+            ],
+            remaining_accounts,
+        ]
+        .concat();
+        process_native(
+            &[
+                AMMInstructions::FlashLoan.serialize::<FlashLoan>(FlashLoan {
+                    amount: 100_000,
+                    expiration: i64::MAX,
+                    is_x: 1,
+                    padding: [0; 7],
+                }),
+                callback_data.to_vec(),
+            ]
+            .concat(),
+            &accounts,
+        )
+        .0
+    };
+
+    // A callback can't call straight back into the pool, here to swap some of the borrowed X
+    assert!(flash_loan(&amm_program, &swap_data, &swap_accounts) == Err(AmmError::Reentrancy.into()));
+    assert_eq!(native_amount(&vault_x), 1_000_000);
+
+    // Nor can anything enter the pool while the guard is held
+    load_account_mut::<Config>(&config).unwrap().reentrancy_guard = 1;
+    assert!(process_native(&swap_data, &swap_accounts).0 == Err(AmmError::Reentrancy.into()));
+    load_account_mut::<Config>(&config).unwrap().reentrancy_guard = 0;
+
+    // A callback that repays the loan and fee releases the guard once it returns
+    let repay = spl_token::instruction::transfer_checked(
+        &spl_token::ID,
+        user_x.key,
+        mint_x.key,
+        vault_x.key,
+        user.key,
+        &[],
+        100_300,
+        6,
+    )
+    .unwrap();
+    assert!(flash_loan(&token_program, &repay.data, &[user_x.clone(), mint_x.clone(), vault_x.clone(), user.clone()]) == Ok(()));
+    assert_eq!(native_amount(&vault_x), 1_000_300);
+    assert_eq!(native_amount(&user_x), 700);
+    assert_eq!(Config::load(&config).unwrap().reentrancy_guard, 0);
+    assert!(process_native(&swap_data, &swap_accounts).0 == Ok(()));
+}
+
+#[test]
+fn flash_swap_reentrancy() {
+    // Set our seed
+    let seed: u64 = 1337;
+
+    // Accounts
+    let user = Pubkey::new_from_array([0x01; 32]);
+    let mint_x = Pubkey::new_from_array([0x02; 32]);
+    let mint_y = Pubkey::new_from_array([0x03; 32]);
+    let user_x = Pubkey::new_from_array([0x04; 32]);
+    let user_y = Pubkey::new_from_array([0x05; 32]);
+    let (config, config_bump) =
+        Pubkey::find_program_address(&[b"config", &seed.to_le_bytes()], &crate::ID);
+    let (vault_x, x_bump) =
+        Pubkey::find_program_address(&[mint_x.as_ref(), config.as_ref()], &crate::ID);
+    let (vault_y, y_bump) =
+        Pubkey::find_program_address(&[mint_y.as_ref(), config.as_ref()], &crate::ID);
+
+    // A pool of 1,000,000 X and Y with a 0.3% fee
+    let config_data = bytes_of::<Config>(&Config {
+        discriminator: CONFIG_DISCRIMINATOR,
+        seed,
+        authority: crate::ID,
+        pending_authority: Pubkey::default(),
+        mint_x,
+        mint_y,
+        fee: 30u16,
+        locked: 0,
+        config_bump,
+        lp_bump: 0,
+        x_bump,
+        y_bump,
+        curve_type: 0,
+        curve_params: [0; 32],
+        sqrt_price: 0.into(),
+        liquidity: 0.into(),
+        fee_growth_global_x: 0.into(),
+        fee_growth_global_y: 0.into(),
+        tick_current: 0,
+        tick_spacing: 0,
+        protocol_fee: 0,
+        treasury: crate::ID,
+        protocol_fees_x: 0,
+        protocol_fees_y: 0,
+        last_fee_update: 0,
+        fee_update_interval: 0,
+        price_x_cumulative: 0.into(),
+        price_y_cumulative: 0.into(),
+        liquidity_cumulative: 0.into(),
+        price_last_update: 0,
+        locked_liquidity: MINIMUM_LIQUIDITY,
+        fee_max_step: 0,
+        flash_fee: 0,
+        version: CONFIG_VERSION,
+        reentrancy_guard: 0,
+        padding: [0; 2],
+    })
+    .to_vec();
+
+    let user = native_account(user, Pubkey::default(), vec![], true, true);
+    let mint_x = native_account(mint_x, spl_token::ID, token_mint(100_000_000_000, COption::None), false, false);
+    let mint_y = native_account(mint_y, spl_token::ID, token_mint(100_000_000_000, COption::None), false, false);
+    let user_x = native_account(user_x, spl_token::ID, token_account(*mint_x.key, *user.key, 10_000), false, true);
+    let user_y = native_account(user_y, spl_token::ID, token_account(*mint_y.key, *user.key, 0), false, true);
+    let vault_x = native_account(vault_x, spl_token::ID, token_account(*mint_x.key, config, 1_000_000), false, true);
+    let vault_y = native_account(vault_y, spl_token::ID, token_account(*mint_y.key, config, 1_000_000), false, true);
+    let config = native_account(config, crate::ID, config_data, false, true);
+    let token_program = native_account(spl_token::ID, Pubkey::default(), vec![], false, false);
+    let amm_program = native_account(crate::ID, Pubkey::default(), vec![], false, false);
+
+    let swap_accounts = [
+        user.clone(),
+        mint_x.clone(),
+        mint_y.clone(),
+        user_x.clone(),
+        user_y.clone(),
+        vault_x.clone(),
+        vault_y.clone(),
+        config.clone(),
+        token_program.clone(),
+        token_program.clone(),
+    ];
+    let swap_data = AMMInstructions::Swap.serialize::<Swap>(Swap {
+        amount: 10_000,
+        min: 0,
+        expiration: i64::MAX,
+    });
+    let flash_swap = |callback_program: &AccountInfo<'static>, callback_data: &[u8], remaining_accounts: &[AccountInfo<'static>]| {
+        let accounts = [&swap_accounts[..], &[callback_program.clone()], remaining_accounts].concat();
+        process_native(
+            &[
+                AMMInstructions::FlashSwap.serialize::<Swap>(Swap {
+                    amount: 10_000,
+                    min: 0,
+                    expiration: i64::MAX,
+                }),
+                callback_data.to_vec(),
+            ]
+            .concat(),
+            &accounts,
+        )
+        .0
+    };
+
+    // A callback can't call straight back into the pool, here to pay for the Y with a swap on the same pool
+    assert!(flash_swap(&amm_program, &swap_data, &swap_accounts) == Err(AmmError::Reentrancy.into()));
+    assert_eq!(native_amount(&vault_y), 1_000_000);
+
+    // A callback that pays the vault the X owed releases the guard once it returns
+    let pay = spl_token::instruction::transfer_checked(
+        &spl_token::ID,
+        user_x.key,
+        mint_x.key,
+        vault_x.key,
+        user.key,
+        &[],
+        10_000,
+        6,
+    )
+    .unwrap();
+    assert!(flash_swap(&token_program, &pay.data, &[user_x.clone(), mint_x.clone(), vault_x.clone(), user.clone()]) == Ok(()));
+    assert_eq!(native_amount(&vault_x), 1_010_000);
+    assert_eq!(native_amount(&user_x), 0);
+    assert_eq!(native_amount(&user_y), 1_000_000 - native_amount(&vault_y));
+    assert!(native_amount(&user_y) > 0);
+    assert_eq!(Config::load(&config).unwrap().reentrancy_guard, 0);
+}
+
+#[test]
+fn admin_reentrancy() {
+    // Set our seed
+    let seed: u64 = 1337;
+
+    // Accounts
+    let authority = Pubkey::new_from_array([0x01; 32]);
+    let mint_x = Pubkey::new_from_array([0x02; 32]);
+    let mint_y = Pubkey::new_from_array([0x03; 32]);
+    let treasury = Pubkey::new_from_array([0x06; 32]);
+    let treasury_x = Pubkey::new_from_array([0x07; 32]);
+    let treasury_y = Pubkey::new_from_array([0x08; 32]);
+    let (config, config_bump) =
+        Pubkey::find_program_address(&[b"config", &seed.to_le_bytes()], &crate::ID);
+    let (vault_x, x_bump) =
+        Pubkey::find_program_address(&[mint_x.as_ref(), config.as_ref()], &crate::ID);
+    let (vault_y, y_bump) =
+        Pubkey::find_program_address(&[mint_y.as_ref(), config.as_ref()], &crate::ID);
+
+    // A pool with protocol fees owed, caught inside a flash loan or flash swap callback
+    let config_data = bytes_of::<Config>(&Config {
+        discriminator: CONFIG_DISCRIMINATOR,
+        seed,
+        authority,
+        pending_authority: authority,
+        mint_x,
+        mint_y,
+        fee: 30u16,
+        locked: 0,
+        config_bump,
+        lp_bump: 0,
+        x_bump,
+        y_bump,
+        curve_type: 0,
+        curve_params: [0; 32],
+        sqrt_price: 0.into(),
+        liquidity: 0.into(),
+        fee_growth_global_x: 0.into(),
+        fee_growth_global_y: 0.into(),
+        tick_current: 0,
+        tick_spacing: 0,
+        protocol_fee: 0,
+        treasury,
+        protocol_fees_x: 100,
+        protocol_fees_y: 100,
+        last_fee_update: 0,
+        fee_update_interval: 0,
+        price_x_cumulative: 0.into(),
+        price_y_cumulative: 0.into(),
+        liquidity_cumulative: 0.into(),
+        price_last_update: 0,
+        locked_liquidity: MINIMUM_LIQUIDITY,
+        fee_max_step: 100,
+        flash_fee: 30,
+        version: CONFIG_VERSION,
+        reentrancy_guard: 1,
+        padding: [0; 2],
+    })
+    .to_vec();
+
+    let authority = native_account(authority, Pubkey::default(), vec![], true, true);
+    let treasury = native_account(treasury, Pubkey::default(), vec![], true, true);
+    let mint_x = native_account(mint_x, spl_token::ID, token_mint(100_000_000_000, COption::None), false, false);
+    let mint_y = native_account(mint_y, spl_token::ID, token_mint(100_000_000_000, COption::None), false, false);
+    let treasury_x = native_account(treasury_x, spl_token::ID, token_account(*mint_x.key, *treasury.key, 0), false, true);
+    let treasury_y = native_account(treasury_y, spl_token::ID, token_account(*mint_y.key, *treasury.key, 0), false, true);
+    let vault_x = native_account(vault_x, spl_token::ID, token_account(*mint_x.key, config, 1_000_000), false, true);
+    let vault_y = native_account(vault_y, spl_token::ID, token_account(*mint_y.key, config, 1_000_000), false, true);
+    let config = native_account(config, crate::ID, config_data, false, true);
+    let token_program = native_account(spl_token::ID, Pubkey::default(), vec![], false, false);
+
+    let collect_accounts = [
+        treasury.clone(),
+        mint_x.clone(),
+        mint_y.clone(),
+        treasury_x.clone(),
+        treasury_y.clone(),
+        vault_x.clone(),
+        vault_y.clone(),
+        config.clone(),
+        token_program.clone(),
+        token_program.clone(),
+    ];
+    let admin_instructions = [
+        AMMInstructions::UpdateFee.serialize::<UpdateFee>(UpdateFee { fee: 40 }),
+        AMMInstructions::ProposeAuthority.serialize::<ProposeAuthority>(ProposeAuthority { new_authority: *authority.key }),
+        vec![AMMInstructions::AcceptAuthority as u8],
+        vec![AMMInstructions::Lock as u8, 0],
+        vec![AMMInstructions::RenounceAuthority as u8],
+    ];
+
+    // Neither the treasury nor the authority can touch the pool while the guard is held
+    assert!(process_native(&[AMMInstructions::CollectProtocolFees as u8], &collect_accounts).0 == Err(AmmError::Reentrancy.into()));
+    for data in &admin_instructions {
+        assert!(process_native(data, &[authority.clone(), config.clone()]).0 == Err(AmmError::Reentrancy.into()));
+    }
+    {
+        let pool = Config::load(&config).unwrap();
+        assert_eq!(pool.protocol_fees_x, 100);
+        assert_eq!(pool.locked, 0);
+        assert_eq!(pool.fee, 30);
+        assert_eq!(pool.authority, *authority.key);
+    }
+
+    // Once the callback returns they can
+    load_account_mut::<Config>(&config).unwrap().reentrancy_guard = 0;
+    assert!(process_native(&[AMMInstructions::CollectProtocolFees as u8], &collect_accounts).0 == Ok(()));
+    assert_eq!(native_amount(&treasury_x), 100);
+    assert_eq!(native_amount(&treasury_y), 100);
+    for data in &admin_instructions {
+        assert!(process_native(data, &[authority.clone(), config.clone()]).0 == Ok(()));
+    }
+    assert_eq!(Config::load(&config).unwrap().locked, 2);
+}
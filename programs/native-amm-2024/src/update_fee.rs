@@ -0,0 +1,55 @@
+use crate::{AmmError, Config, UpdateFee};
+use solana_program::{
+    account_info::AccountInfo, clock::Clock, entrypoint::ProgramResult, program_error::ProgramError, sysvar::Sysvar,
+};
+
+pub fn process(accounts: &[AccountInfo<'_>], data: &[u8]) -> ProgramResult {
+    let UpdateFee { fee } = UpdateFee::try_from(data)?;
+
+    let [authority, config] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    // Ensure user is signer
+    if !authority.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    // Load our config, checking we own it
+    let mut config_account = Config::load_mut(config)?;
+
+    // Assert we aren't inside a flash loan or flash swap callback
+    if config_account.reentrancy_guard == 1 {
+        return Err(AmmError::Reentrancy.into());
+    }
+
+    // Assert signer is the correct authority
+    if authority.key.ne(&config_account.authority) {
+        return Err(AmmError::WrongAuthority.into());
+    }
+
+    // Assert status is not set to revoked (2)
+    if config_account.locked == 2 {
+        return Err(AmmError::AuthorityRevoked.into());
+    }
+
+    // Check that the fee is less than 100% and moves by no more than the maximum step
+    if fee >= 10_000 {
+        return Err(AmmError::InvalidFee.into());
+    }
+    if fee.abs_diff(config_account.fee) > config_account.fee_max_step {
+        return Err(AmmError::FeeStepExceeded.into());
+    }
+
+    // Check that enough time has passed since the last update
+    let now = Clock::get()?.unix_timestamp;
+    if now < config_account.last_fee_update.saturating_add(config_account.fee_update_interval) {
+        return Err(AmmError::FeeUpdateTooSoon.into());
+    }
+
+    // Update the fee
+    config_account.fee = fee;
+    config_account.last_fee_update = now;
+
+    Ok(())
+}
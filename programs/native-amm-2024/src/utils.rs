@@ -1,4 +1,5 @@
 use solana_program::{
+    instruction::{AccountMeta, Instruction},
     program_error::ProgramError,
     pubkey::Pubkey, 
     account_info::AccountInfo, 
@@ -7,11 +8,17 @@ use solana_program::{
     clock::Clock,
     sysvar::Sysvar,     
     program::{invoke, invoke_signed},
-    system_instruction::create_account,
+    system_instruction::{allocate, assign, create_account, transfer},
     program_pack::Pack, 
 };
-use spl_token::instruction::{initialize_account3, initialize_mint2, transfer_checked, mint_to_checked, burn_checked};
-use crate::state::Config;
+use spl_token_2022::{
+    extension::{metadata_pointer, transfer_fee::TransferFeeConfig, BaseStateWithExtensions, ExtensionType, StateWithExtensions},
+    instruction::{initialize_account3, initialize_mint2, mint_to_checked, burn_checked},
+    onchain::invoke_transfer_checked,
+    state::{Account, Mint},
+};
+use spl_token_metadata_interface::state::TokenMetadata;
+use crate::{errors::AmmError, state::Config};
 
 #[inline]
 pub fn check_eq_program_derived_address_with_bump(
@@ -20,7 +27,10 @@ pub fn check_eq_program_derived_address_with_bump(
     address: &Pubkey,
 ) -> Result<(), ProgramError> {
     let derived_address = Pubkey::create_program_address(seeds, program_id)?;
-    Ok(assert!(derived_address.eq(address)))
+    if derived_address.ne(address) {
+        return Err(AmmError::InvalidPda.into());
+    }
+    Ok(())
 }
 
 #[inline]
@@ -31,10 +41,129 @@ pub fn check_eq_program_derived_address_and_get_bump(
 ) -> Result<u8, ProgramError> {
     let (derived_address, bump) = Pubkey::try_find_program_address(seeds, program_id)
         .ok_or(ProgramError::InvalidAccountData)?;
-    assert!(derived_address.eq(address));
+    if derived_address.ne(address) {
+        return Err(AmmError::InvalidPda.into());
+    }
     Ok(bump)
 }
 
+#[inline]
+pub fn check_token_program(token_program: &AccountInfo, mint: &AccountInfo) -> ProgramResult {
+    // Assert we are using either the Token or Token-2022 program
+    if token_program.key.ne(&spl_token::ID) && token_program.key.ne(&spl_token_2022::ID) {
+        return Err(AmmError::InvalidTokenProgram.into());
+    }
+
+    // Assert the mint is owned by the token program we were given
+    if mint.owner.ne(token_program.key) {
+        return Err(AmmError::InvalidAccountOwner.into());
+    }
+
+    Ok(())
+}
+
+#[inline]
+pub fn unpack_mint(mint: &AccountInfo) -> Result<Mint, ProgramError> {
+    Ok(StateWithExtensions::<Mint>::unpack(&mint.try_borrow_data()?)?.base)
+}
+
+#[inline]
+pub fn unpack_token_account(ta: &AccountInfo) -> Result<Account, ProgramError> {
+    Ok(StateWithExtensions::<Account>::unpack(&ta.try_borrow_data()?)?.base)
+}
+
+// Get the fee withheld by a TransferFeeConfig mint when transferring `amount`
+#[inline]
+pub fn get_transfer_fee(mint: &AccountInfo, amount: u64) -> Result<u64, ProgramError> {
+    let mint_data = mint.try_borrow_data()?;
+    let mint_state = StateWithExtensions::<Mint>::unpack(&mint_data)?;
+    match mint_state.get_extension::<TransferFeeConfig>() {
+        Ok(transfer_fee_config) => transfer_fee_config
+            .calculate_epoch_fee(Clock::get()?.epoch, amount)
+            .ok_or(ProgramError::ArithmeticOverflow),
+        Err(_) => Ok(0),
+    }
+}
+
+// Get the fee that must be added to a transfer so that `amount` arrives after fees
+#[inline]
+pub fn get_transfer_inverse_fee(mint: &AccountInfo, amount: u64) -> Result<u64, ProgramError> {
+    let mint_data = mint.try_borrow_data()?;
+    let mint_state = StateWithExtensions::<Mint>::unpack(&mint_data)?;
+    match mint_state.get_extension::<TransferFeeConfig>() {
+        Ok(transfer_fee_config) => transfer_fee_config
+            .calculate_inverse_epoch_fee(Clock::get()?.epoch, amount)
+            .ok_or(ProgramError::ArithmeticOverflow),
+        Err(_) => Ok(0),
+    }
+}
+
+// Get a short symbol for a mint, preferring its on-chain TokenMetadata symbol
+#[inline]
+pub fn get_mint_symbol(mint: &AccountInfo) -> Result<String, ProgramError> {
+    let mint_data = mint.try_borrow_data()?;
+    let mint_state = StateWithExtensions::<Mint>::unpack(&mint_data)?;
+    Ok(match mint_state.get_variable_len_extension::<TokenMetadata>() {
+        Ok(metadata) => metadata.symbol,
+        Err(_) => mint.key.to_string()[..4].to_string(),
+    })
+}
+
+#[inline]
+pub fn create_program_account<'a>(
+    seeds: &[&[u8]],
+    payer: &AccountInfo<'a>,
+    account: &AccountInfo<'a>,
+    space: usize,
+) -> ProgramResult {
+    let lamports = Rent::get()?.minimum_balance(space);
+
+    // Anyone can send lamports to a PDA before it exists, which makes create_account fail. Top such an account up to
+    // rent exemption, then allocate and assign it ourselves
+    if account.lamports() > 0 {
+        let top_up = lamports.saturating_sub(account.lamports());
+        if top_up > 0 {
+            invoke(
+                &transfer(payer.key, account.key, top_up),
+                &[payer.clone(), account.clone()],
+            )?;
+        }
+        invoke_signed(&allocate(account.key, space as u64), &[account.clone()], &[seeds])?;
+        return invoke_signed(&assign(account.key, &crate::ID), &[account.clone()], &[seeds]);
+    }
+
+    // Create a rent-exempt PDA owned by this program
+    invoke_signed(
+        &create_account(
+            payer.key,
+            account.key,
+            lamports,
+            space as u64,
+            &crate::ID,
+        ),
+        &[payer.clone(), account.clone()],
+        &[seeds],
+    )
+}
+
+#[inline]
+pub fn resize_program_account<'a>(
+    payer: &AccountInfo<'a>,
+    account: &AccountInfo<'a>,
+    space: usize,
+) -> ProgramResult {
+    // Top up the rent exemption for the new size
+    let lamports = Rent::get()?.minimum_balance(space).saturating_sub(account.lamports());
+    if lamports > 0 {
+        invoke(
+            &transfer(payer.key, account.key, lamports),
+            &[payer.clone(), account.clone()],
+        )?;
+    }
+
+    account.realloc(space, true)
+}
+
 #[inline]
 pub fn create_token_account<'a>(
     seeds: &[&[u8]],
@@ -44,7 +173,13 @@ pub fn create_token_account<'a>(
     mint: &AccountInfo<'a>,
     authority: &AccountInfo<'a>,
 ) -> ProgramResult {
-    let token_space = spl_token::state::Account::LEN;
+    // Size the account for any extensions the mint requires its token accounts to carry
+    let token_space = {
+        let mint_data = mint.try_borrow_data()?;
+        let mint_extensions = StateWithExtensions::<Mint>::unpack(&mint_data)?.get_extension_types()?;
+        let account_extensions = ExtensionType::get_required_init_account_extensions(&mint_extensions);
+        ExtensionType::try_calculate_account_len::<Account>(&account_extensions)?
+    };
     let token_rent = Rent::get()?.minimum_balance(token_space);
 
     invoke_signed(
@@ -53,7 +188,7 @@ pub fn create_token_account<'a>(
             ta.key,
             token_rent,
             token_space as u64,
-            &spl_token::ID,    
+            token_program,
         ),
         &[payer.clone(), ta.clone()],
         &[seeds],
@@ -72,8 +207,9 @@ pub fn create_mint<'a>(
     payer: &AccountInfo<'a>,
     mint: &AccountInfo<'a>,
     authority: &AccountInfo<'a>,
+    decimals: u8,
 ) -> ProgramResult {
-    let mint_space = spl_token::state::Mint::LEN;
+    let mint_space = Mint::LEN;
     let mint_rent = Rent::get()?.minimum_balance(mint_space);
 
     invoke_signed(
@@ -82,15 +218,79 @@ pub fn create_mint<'a>(
             mint.key,
             mint_rent,
             mint_space as u64,
-            &spl_token::ID,
+            token_program,
+        ),
+        &[payer.clone(), mint.clone()],
+        &[seeds],
+    )?;
+
+    invoke(
+        &initialize_mint2(token_program, mint.key, authority.key, None, decimals)?,
+        &[mint.clone()],
+    )
+}
+
+#[inline]
+pub fn create_mint_with_metadata<'a>(
+    seeds: &[&[u8]],
+    authority_seeds: &[&[u8]],
+    token_program: &Pubkey,
+    payer: &AccountInfo<'a>,
+    mint: &AccountInfo<'a>,
+    authority: &AccountInfo<'a>,
+    decimals: u8,
+    name: String,
+    symbol: String,
+    uri: String,
+) -> ProgramResult {
+    let mint_space = ExtensionType::try_calculate_account_len::<Mint>(&[ExtensionType::MetadataPointer])?;
+    let metadata_space = TokenMetadata {
+        name: name.clone(),
+        symbol: symbol.clone(),
+        uri: uri.clone(),
+        ..Default::default()
+    }
+    .tlv_size_of()?;
+
+    // Fund the metadata up front, as Token-2022 reallocs the mint when initializing it
+    let mint_rent = Rent::get()?.minimum_balance(mint_space + metadata_space);
+
+    invoke_signed(
+        &create_account(
+            payer.key,
+            mint.key,
+            mint_rent,
+            mint_space as u64,
+            token_program,
         ),
         &[payer.clone(), mint.clone()],
         &[seeds],
     )?;
 
+    // Point the metadata at the mint itself
+    invoke(
+        &metadata_pointer::instruction::initialize(token_program, mint.key, Some(*authority.key), Some(*mint.key))?,
+        &[mint.clone()],
+    )?;
+
     invoke(
-        &initialize_mint2(token_program, mint.key, authority.key, None, 0)?,
+        &initialize_mint2(token_program, mint.key, authority.key, None, decimals)?,
         &[mint.clone()],
+    )?;
+
+    invoke_signed(
+        &spl_token_metadata_interface::instruction::initialize(
+            token_program,
+            mint.key,
+            authority.key,
+            mint.key,
+            authority.key,
+            name,
+            symbol,
+            uri,
+        ),
+        &[mint.clone(), authority.clone()],
+        &[authority_seeds],
     )
 }
 
@@ -104,13 +304,24 @@ pub fn perform_basic_checks(
     vault_y: &AccountInfo,
 ) -> ProgramResult {
     // Expiration check
-    assert!(Clock::get()?.unix_timestamp <= expiration);
+    if Clock::get()?.unix_timestamp > expiration {
+        return Err(AmmError::Expired.into());
+    }
     
     // Assert we own config
-    assert_eq!(config.owner, &crate::ID);
+    if config.owner.ne(&crate::ID) {
+        return Err(AmmError::InvalidAccountOwner.into());
+    }
 
     // Assert pool isn't locked
-    assert_ne!(config_account.locked, 1);
+    if config_account.locked == 1 {
+        return Err(AmmError::PoolLocked.into());
+    }
+
+    // Assert we aren't inside a flash loan or flash swap callback
+    if config_account.reentrancy_guard == 1 {
+        return Err(AmmError::Reentrancy.into());
+    }
 
     // Check LP mint
     check_eq_program_derived_address_with_bump(
@@ -152,13 +363,24 @@ pub fn perform_basic_checks_with_no_lp(
     vault_y: &AccountInfo,
 ) -> ProgramResult {
     // Expiration check
-    assert!(Clock::get()?.unix_timestamp <= expiration);
+    if Clock::get()?.unix_timestamp > expiration {
+        return Err(AmmError::Expired.into());
+    }
     
     // Assert we own config
-    assert_eq!(config.owner, &crate::ID);
+    if config.owner.ne(&crate::ID) {
+        return Err(AmmError::InvalidAccountOwner.into());
+    }
 
     // Assert pool isn't locked
-    assert_ne!(config_account.locked, 1);
+    if config_account.locked == 1 {
+        return Err(AmmError::PoolLocked.into());
+    }
+
+    // Assert we aren't inside a flash loan or flash swap callback
+    if config_account.reentrancy_guard == 1 {
+        return Err(AmmError::Reentrancy.into());
+    }
 
     // Check vault X
     check_eq_program_derived_address_with_bump(
@@ -194,20 +416,19 @@ pub fn deposit<'a>(
     user: &AccountInfo<'a>,
     amount: u64,
     decimals: u8,
+    remaining_accounts: &[AccountInfo<'a>],
 ) -> ProgramResult {
-    // Transfer the funds from the maker's token account to the vault
-    invoke(
-        &transfer_checked(
-            token_program,
-            user_from.key,
-            mint.key,
-            vault.key,
-            user.key,
-            &[],
-            amount,
-            decimals,
-        )?,
-        &[user_from.clone(), mint.clone(), vault.clone(), user.clone()],
+    // Transfer the funds from the maker's token account to the vault, resolving any transfer hook accounts
+    invoke_transfer_checked(
+        token_program,
+        user_from.clone(),
+        mint.clone(),
+        vault.clone(),
+        user.clone(),
+        remaining_accounts,
+        amount,
+        decimals,
+        &[],
     )
 }
 
@@ -221,25 +442,18 @@ pub fn withdraw<'a>(
     amount: u64,
     decimals: u8,
     seeds: &[&[u8]],
+    remaining_accounts: &[AccountInfo<'a>],
 ) -> ProgramResult {
-    // Transfer the funds from the maker's token account to the vault
-    invoke_signed(
-        &transfer_checked(
-            token_program,
-            vault.key,
-            mint.key,
-            user_to.key,
-            authority.key,
-            &[],
-            amount,
-            decimals,
-        )?,
-        &[
-            user_to.clone(),
-            mint.clone(),
-            vault.clone(),
-            authority.clone(),
-        ],
+    // Transfer the funds from the vault to the user's token account, resolving any transfer hook accounts
+    invoke_transfer_checked(
+        token_program,
+        vault.clone(),
+        mint.clone(),
+        user_to.clone(),
+        authority.clone(),
+        remaining_accounts,
+        amount,
+        decimals,
         &[seeds],
     )
 }
@@ -296,42 +510,70 @@ pub fn burn<'a>(
 
 #[inline]
 pub fn execute_swap<'a>(
-    token_program_key: &Pubkey,
+    token_program_from: &Pubkey,
+    token_program_to: &Pubkey,
     amount: u64,
     amount_out: u64,
     config_account: &Config,
     decimals_from: u8,
     decimals_to: u8,
     config: &AccountInfo<'a>,
+    user: &AccountInfo<'a>,
     user_from: &AccountInfo<'a>,
     user_to: &AccountInfo<'a>,
     mint_from: &AccountInfo<'a>,
     mint_to: &AccountInfo<'a>,
     vault_from: &AccountInfo<'a>,
     vault_to: &AccountInfo<'a>,
+    remaining_accounts: &[AccountInfo<'a>],
 ) -> Result<(), ProgramError> {
     // Deposit the token from the user
     deposit(
-        token_program_key,
+        token_program_from,
         user_from,
         mint_from,
         vault_from,
-        user_from,
+        user,
         amount,
         decimals_from,
+        remaining_accounts,
     )?;
 
     // Withdraw the corresponding token to the user
     withdraw(
-        token_program_key,
-        vault_to,
-        mint_to,
+        token_program_to,
         user_to,
+        mint_to,
+        vault_to,
         config,
         amount_out,
         decimals_to,
         &[b"config", config_account.seed.to_le_bytes().as_ref(), &[config_account.config_bump]],
+        remaining_accounts,
     )?;
 
     Ok(())
-}
\ No newline at end of file
+}
+
+// Call into a flash loan or flash swap borrower's program, passing on the remaining accounts and its instruction data
+pub fn invoke_callback<'a>(
+    callback_program: &AccountInfo<'a>,
+    callback_data: &[u8],
+    remaining_accounts: &[AccountInfo<'a>],
+) -> ProgramResult {
+    invoke(
+        &Instruction {
+            program_id: *callback_program.key,
+            accounts: remaining_accounts
+                .iter()
+                .map(|account| AccountMeta {
+                    pubkey: *account.key,
+                    is_signer: account.is_signer,
+                    is_writable: account.is_writable,
+                })
+                .collect(),
+            data: callback_data.to_vec(),
+        },
+        remaining_accounts,
+    )
+}
@@ -1,10 +1,11 @@
-use crate::{utils::perform_basic_checks, Config, Withdraw};
+use crate::{utils::{check_token_program, perform_basic_checks}, Config, Withdraw};
 use solana_program::{
     account_info::AccountInfo,
     entrypoint::ProgramResult,
     program_error::ProgramError,
 };
 
+/// Burn LP for X and Y at the pool's ratio. The config must be writable, as it carries the pool's accumulators
 pub fn process(accounts: &[AccountInfo<'_>], data: &[u8]) -> ProgramResult {
     let Withdraw {
         amount,
@@ -13,24 +14,28 @@ pub fn process(accounts: &[AccountInfo<'_>], data: &[u8]) -> ProgramResult {
         expiration,
     } = Withdraw::try_from(data)?;
 
-    let [user, mint_x, mint_y, mint_lp, user_x, user_y, user_lp, vault_x, vault_y, config, token_program] =
+    let [user, mint_x, mint_y, mint_lp, user_x, user_y, user_lp, vault_x, vault_y, config, token_program_x, token_program_y, token_program, remaining_accounts @ ..] =
         accounts
     else {
         return Err(ProgramError::NotEnoughAccountKeys);
     };
 
     // Ensure user is signer
-    assert!(user.is_signer);
+    if !user.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
 
-    // Ensure correct TokenProgram
-    assert_eq!(token_program.key, &spl_token::ID);
+    // Ensure correct TokenPrograms
+    check_token_program(token_program_x, mint_x)?;
+    check_token_program(token_program_y, mint_y)?;
+    check_token_program(token_program, mint_lp)?;
 
     // Load Config
-    let config_account = Config::try_from(config.data.borrow().as_ref())?;
+    let config_account = Config::load(config)?;
 
     // Perform Basic Checks
     perform_basic_checks(&config_account, expiration, config, mint_lp, vault_x, vault_y)?;
 
-    Config::perform_user_withdraw(amount, min_x, min_y, &config_account, token_program.key, user_x, 
-        user_y, user_lp, vault_x, vault_y, mint_x, mint_y, mint_lp, config, user)
+    Config::perform_user_withdraw(amount, min_x, min_y, &config_account, token_program_x.key, token_program_y.key, token_program.key, user_x, 
+        user_y, user_lp, vault_x, vault_y, mint_x, mint_y, mint_lp, config, user, remaining_accounts)
 }
\ No newline at end of file
@@ -0,0 +1,40 @@
+use crate::{utils::{check_token_program, perform_basic_checks}, Config, WithdrawSingle};
+use solana_program::{
+    account_info::AccountInfo,
+    entrypoint::ProgramResult,
+    program_error::ProgramError,
+};
+
+/// Burn LP for only X or Y, swapping the other side of the withdrawal back through the pool
+pub fn process(accounts: &[AccountInfo<'_>], data: &[u8]) -> ProgramResult {
+    let WithdrawSingle {
+        amount,
+        min_out,
+        expiration,
+    } = WithdrawSingle::try_from(data)?;
+
+    let [user, mint_to, mint_lp, user_to, user_lp, vault_x, vault_y, config, token_program_to, token_program, remaining_accounts @ ..] =
+        accounts
+    else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    // Ensure user is signer
+    if !user.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    // Ensure correct TokenPrograms
+    check_token_program(token_program_to, mint_to)?;
+    check_token_program(token_program, mint_lp)?;
+
+    // Load Config
+    let config_account = Config::load(config)?;
+
+    // Perform Basic Checks
+    perform_basic_checks(&config_account, expiration, config, mint_lp, vault_x, vault_y)?;
+
+    // Perform User Withdraw
+    Config::perform_user_withdraw_single(amount, min_out, &config_account, token_program_to.key, token_program.key, user_to,
+        user_lp, vault_x, vault_y, mint_to, mint_lp, config, user, remaining_accounts)
+}